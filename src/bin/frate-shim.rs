@@ -0,0 +1,87 @@
+//! `frate-shim`: the executable behind binary shims (`shim-mode = "binary"`).
+//!
+//! frate copies or hardlinks this executable into `.frate/shims` once per tool. When run, it
+//! reads the [`ShimConfig`] next to itself, sets up the environment and runs the target with
+//! all arguments. On Unix the process is replaced by the target; elsewhere the shim waits for
+//! it and exits with its exit code.
+//!
+//! If the package of the shim is locked but missing or outdated, it is installed first,
+//! unless lazy installs are disabled (`lazy-install = false` or `FRATE_LAZY_INSTALL=0`).
+
+use std::path::Path;
+use std::process::ExitCode;
+use frate::error::Result;
+use frate::{get_binary, shim_config_path, Event, Project, ShimConfig};
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("frate-shim: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<ExitCode> {
+    // The shims of `frate shell --group` are symlinks to the real shims.
+    let exe = std::env::current_exe()?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    let config_path = shim_config_path(&exe);
+    let config = ShimConfig::load(&config_path).map_err(|e| {
+        frate::Error::Other(format!("cannot read {}: {}", config_path.display(), e))
+    })?;
+    let shim_dir = exe.parent().unwrap_or(&exe);
+    let config = match config.package.clone() {
+        Some(package) => prepare_package(shim_dir, &config_path, config, &package)?,
+        None => config,
+    };
+    let mut command = config.command(shim_dir)?;
+    command.args(std::env::args_os().skip(1));
+    let target = command.get_program().to_string_lossy().into_owned();
+    let cannot_run = |e: std::io::Error| frate::Error::Other(format!("cannot run {}: {}", target, e));
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = command.exec();
+        Err(cannot_run(error))
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command.status().map_err(cannot_run)?;
+        // `ExitCode` only holds a `u8`, which would turn e.g. 256 into success.
+        std::process::exit(status.code().unwrap_or(1))
+    }
+}
+
+/// Installs `package` if it needs to be, and returns the configuration to run it with.
+///
+/// Shims live in `<root>/.frate/shims`; if there is no project at `<root>`, the shim
+/// runs its target unchanged.
+fn prepare_package(shim_dir: &Path, config_path: &Path, config: ShimConfig, package: &str) -> Result<ShimConfig> {
+    let Some(root) = shim_dir.parent().and_then(Path::parent) else {
+        return Ok(config);
+    };
+    let Ok(project) = Project::load(root) else {
+        return Ok(config);
+    };
+    let mut config = config;
+    if project.settings().lazy_install_enabled() && project.needs_install(package) {
+        eprintln!("frate: installing {}", package);
+        project.install(Some(package), &|event: Event| {
+            if let Event::Downloading { url } = event {
+                eprintln!("frate: downloading {}", url);
+            }
+        })?;
+        config = ShimConfig::load(config_path)?;
+    }
+    if !shim_dir.join(&config.target).is_file() {
+        // A shim created before installing is named after the package. If the binary has a
+        // different name, the real shim has that name, so look the binary up instead.
+        let frate_dir = project.frate_dir();
+        if let (Some(target), Some(locked)) = (get_binary(&frate_dir, package)?, project.package(package)) {
+            config = ShimConfig::for_package(&locked, &frate_dir, &target);
+        }
+    }
+    Ok(config)
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::global::cache::archive_name;
+use crate::lock::{FrateLock, LockedPackage};
+
+/// The name of the [`BundleManifest`] inside a bundle.
+pub const BUNDLE_MANIFEST: &str = "bundle.toml";
+
+/// The version of the bundle layout written by [`Bundle::write`].
+pub const BUNDLE_FORMAT: u32 = 1;
+
+/// Describes the contents of a [`Bundle`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BundleManifest {
+    /// The version of the bundle layout, see [`BUNDLE_FORMAT`].
+    pub format: u32,
+    /// The target triples the bundle contains archives for.
+    pub targets: Vec<String>,
+    /// The archives in the bundle.
+    #[serde(default)]
+    pub archives: Vec<BundledArchive>,
+}
+
+/// A tool archive in a [`Bundle`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct BundledArchive {
+    /// The target triple the archive is for.
+    pub target: String,
+    /// Name of the package.
+    pub name: String,
+    /// Resolved version of the package, including the target triple.
+    pub version: String,
+    /// The URL the archive was resolved to. Used as its name in the global cache.
+    pub source: String,
+    /// SHA-256 hash of the archive.
+    pub hash: String,
+    /// Path of the archive inside the bundle.
+    pub file: String,
+}
+
+/// A portable tool environment: `frate.toml`, `frate.lock` and the archives of all required
+/// tools for one or more target triples, stored as a zstd-compressed tarball (`.tar.zst`).
+///
+/// Created by `frate bundle create` ([`Project::create_bundle`](crate::Project::create_bundle))
+/// and installed without network access by `frate bundle install`
+/// ([`Project::install_bundle`](crate::Project::install_bundle)).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bundle {
+    /// The bundle manifest.
+    pub manifest: BundleManifest,
+    /// The content of `frate.toml`.
+    pub frate_toml: String,
+    /// The lockfile of the platform the bundle was created on.
+    pub frate_lock: FrateLock,
+    /// The archives by their path inside the bundle.
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl Bundle {
+    /// Creates an empty bundle of `frate_toml` and `frate_lock`.
+    pub fn new(frate_toml: String, frate_lock: FrateLock) -> Bundle {
+        Bundle {
+            manifest: BundleManifest { format: BUNDLE_FORMAT, ..Default::default() },
+            frate_toml,
+            frate_lock,
+            files: BTreeMap::new(),
+        }
+    }
+    /// Adds the archive `bytes` of `package` for `target`.
+    ///
+    /// # Errors
+    /// Returns an error if the archive name cannot be determined from the package source.
+    pub fn add_archive(&mut self, target: &str, package: &LockedPackage, bytes: Vec<u8>) -> Result<()> {
+        let file = format!("archives/{}/{}", target, archive_name(&package.source)?);
+        if !self.manifest.targets.iter().any(|known| known == target) {
+            self.manifest.targets.push(target.to_string());
+        }
+        self.manifest.archives.push(BundledArchive {
+            target: target.to_string(),
+            name: package.name.clone(),
+            version: package.version.clone(),
+            source: package.source.clone(),
+            hash: package.hash.clone(),
+            file: file.clone(),
+        });
+        self.files.insert(file, bytes);
+        Ok(())
+    }
+    /// Returns the archives for `target`.
+    pub fn archives_for(&self, target: &str) -> Vec<&BundledArchive> {
+        self.manifest.archives.iter().filter(|archive| archive.target == target).collect()
+    }
+    /// Returns the lockfile for `target`: the bundled lockfile, with the version, source and
+    /// hash of every package taken from its archive for `target`.
+    pub fn lock_for(&self, target: &str) -> FrateLock {
+        let mut lock = self.frate_lock.clone();
+        for archive in self.archives_for(target) {
+            let index = match lock.packages.iter().position(|package| package.name == archive.name) {
+                Some(index) => index,
+                None => {
+                    lock.packages.push(LockedPackage { name: archive.name.clone(), ..Default::default() });
+                    lock.packages.len() - 1
+                }
+            };
+            let package = &mut lock.packages[index];
+            package.version = archive.version.clone();
+            package.source = archive.source.clone();
+            package.hash = archive.hash.clone();
+        }
+        lock.sort();
+        lock
+    }
+    /// Writes the bundle to `path` as a zstd-compressed tarball.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let encoder = zstd::Encoder::new(File::create(path)?, 0)?;
+        let mut builder = tar::Builder::new(encoder);
+        let mut append = |name: &str, bytes: &[u8]| -> Result<()> {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(0);
+            builder.append_data(&mut header, name, bytes)?;
+            Ok(())
+        };
+        append(BUNDLE_MANIFEST, toml::to_string_pretty(&self.manifest)?.as_bytes())?;
+        append("frate.toml", self.frate_toml.as_bytes())?;
+        append("frate.lock", self.frate_lock.to_toml_string()?.as_bytes())?;
+        for (name, bytes) in &self.files {
+            append(name, bytes)?;
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    }
+    /// Reads a bundle written by [`Bundle::write`].
+    ///
+    /// # Errors
+    /// Returns [`Error::Parse`] if the file is not a bundle, lacks one of its files, or was
+    /// written in a newer format, or an error if it cannot be read.
+    pub fn read(path: &Path) -> Result<Bundle> {
+        let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(path)?)?);
+        let mut entries = BTreeMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            entries.insert(name, bytes);
+        }
+        let mut text = |name: &str| -> Result<String> {
+            let bytes = entries.remove(name)
+                .ok_or_else(|| Error::Parse(format!("{} is not a frate bundle: {} is missing", path.display(), name)))?;
+            String::from_utf8(bytes).map_err(|e| Error::Parse(format!("{} in {}: {}", name, path.display(), e)))
+        };
+        let manifest: BundleManifest = toml::from_str(&text(BUNDLE_MANIFEST)?)?;
+        if manifest.format > BUNDLE_FORMAT {
+            return Err(Error::Parse(format!("{} has bundle format {}; this frate reads up to {}", path.display(), manifest.format, BUNDLE_FORMAT)));
+        }
+        let frate_toml = text("frate.toml")?;
+        let frate_lock = toml::from_str(&text("frate.lock")?)?;
+        if let Some(missing) = manifest.archives.iter().find(|archive| !entries.contains_key(&archive.file)) {
+            return Err(Error::Parse(format!("{} is incomplete: {} is missing", path.display(), missing.file)));
+        }
+        Ok(Bundle { manifest, frate_toml, frate_lock, files: entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn package(name: &str, version: &str) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: format!("https://example.test/{}-{}.tar.gz", name, version),
+            hash: format!("sha256:{}", version),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("tools.tar.zst");
+        let mut locked = package("just", "1.0.0-x86_64-unknown-linux-gnu");
+        locked.env.insert("JUST".to_string(), "1".to_string());
+        let mut bundle = Bundle::new("[dependencies]\njust = \"1.0.0\"\n".to_string(), FrateLock { packages: vec![locked] });
+        bundle.add_archive("x86_64-unknown-linux-gnu", &package("just", "1.0.0-x86_64-unknown-linux-gnu"), b"gnu".to_vec()).unwrap();
+        bundle.add_archive("aarch64-apple-darwin", &package("just", "1.0.0-aarch64-apple-darwin"), b"mac".to_vec()).unwrap();
+        bundle.write(&path).unwrap();
+
+        let read = Bundle::read(&path).unwrap();
+        assert_eq!(read, bundle);
+        assert_eq!(read.manifest.targets, ["x86_64-unknown-linux-gnu", "aarch64-apple-darwin"]);
+        let lock = read.lock_for("aarch64-apple-darwin");
+        assert_eq!(lock.packages[0].version, "1.0.0-aarch64-apple-darwin");
+        assert_eq!(lock.packages[0].env["JUST"], "1");
+        assert!(read.archives_for("x86_64-pc-windows-msvc").is_empty());
+    }
+
+    #[test]
+    fn test_read_invalid_bundle() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("empty.tar.zst");
+        Bundle { manifest: BundleManifest { format: BUNDLE_FORMAT + 1, ..Default::default() }, ..Default::default() }
+            .write(&path)
+            .unwrap();
+        assert!(matches!(Bundle::read(&path), Err(Error::Parse(_))));
+        std::fs::write(&path, b"not a bundle").unwrap();
+        assert!(Bundle::read(&path).is_err());
+    }
+}
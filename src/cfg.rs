@@ -0,0 +1,332 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use crate::error::{Error, Result};
+use crate::util::current_target_triple;
+
+/// The properties of a target triple that `cfg(...)` expressions and the `targets` key
+/// of a dependency are evaluated against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetInfo {
+    /// The full target triple, e.g. `x86_64-unknown-linux-gnu`.
+    pub triple: String,
+    /// The architecture, e.g. `x86_64`, `aarch64` or `x86`.
+    pub arch: String,
+    /// The vendor, e.g. `unknown`, `apple` or `pc`.
+    pub vendor: String,
+    /// The operating system, e.g. `linux`, `macos` or `windows`.
+    pub os: String,
+    /// The family of the operating system: `unix` or `windows`.
+    pub family: String,
+    /// The C library or ABI, e.g. `gnu`, `musl` or `msvc`. Empty if the triple names none.
+    pub env: String,
+}
+
+impl TargetInfo {
+    /// Derives the properties of `triple`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use frate::TargetInfo;
+    ///
+    /// let target = TargetInfo::from_triple("aarch64-apple-darwin");
+    /// assert_eq!(target.os, "macos");
+    /// assert_eq!(target.family, "unix");
+    /// ```
+    pub fn from_triple(triple: &str) -> TargetInfo {
+        let parts: Vec<&str> = triple.split('-').collect();
+        let arch = match parts[0] {
+            "i386" | "i586" | "i686" => "x86",
+            "arm64" => "aarch64",
+            arch => arch,
+        };
+        let vendor = if parts.len() > 2 { parts[1] } else { "unknown" };
+        let os = if triple.contains("android") {
+            "android"
+        }
+        else if triple.contains("linux") {
+            "linux"
+        }
+        else if triple.contains("darwin") || triple.contains("macos") {
+            "macos"
+        }
+        else if triple.contains("windows") {
+            "windows"
+        }
+        else {
+            parts.get(2).or(parts.get(1)).copied().unwrap_or("unknown")
+        };
+        let last = parts.last().copied().unwrap_or_default();
+        let env = ["gnu", "musl", "msvc"].into_iter()
+            .find(|env| last.starts_with(env) && parts.len() > 3)
+            .unwrap_or("");
+        TargetInfo {
+            triple: triple.to_string(),
+            arch: arch.to_string(),
+            vendor: vendor.to_string(),
+            os: os.to_string(),
+            family: if os == "windows" { "windows" } else { "unix" }.to_string(),
+            env: env.to_string(),
+        }
+    }
+    /// Returns the properties of the [current target triple](current_target_triple).
+    pub fn current() -> TargetInfo {
+        TargetInfo::from_triple(&current_target_triple())
+    }
+    /// Returns true if `target` names this platform: its operating system (`"linux"`, `"macos"`,
+    /// `"windows"`), family (`"unix"`), architecture (`"x86_64"`) or target triple.
+    pub fn matches(&self, target: &str) -> bool {
+        [&self.os, &self.family, &self.arch, &self.triple].iter().any(|value| *value == target)
+    }
+}
+
+/// A parsed `cfg(...)` expression, as used in `[target.'cfg(...)'.dependencies]` of `frate.toml`.
+///
+/// Supported predicates are `unix`, `windows`, and `target_os`, `target_family`, `target_arch`,
+/// `target_env` and `target_vendor` compared to a string, combined with `all(...)`, `any(...)`
+/// and `not(...)`.
+///
+/// # Example
+///
+/// ```
+/// use frate::{Cfg, TargetInfo};
+///
+/// let cfg: Cfg = "cfg(all(unix, not(target_os = \"macos\")))".parse().unwrap();
+/// assert!(cfg.matches(&TargetInfo::from_triple("x86_64-unknown-linux-gnu")));
+/// assert!(!cfg.matches(&TargetInfo::from_triple("aarch64-apple-darwin")));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cfg {
+    /// True if all expressions are true.
+    All(Vec<Cfg>),
+    /// True if any expression is true.
+    Any(Vec<Cfg>),
+    /// True if the expression is false.
+    Not(Box<Cfg>),
+    /// `unix` or `windows`.
+    Name(String),
+    /// A key compared to a value, e.g. `target_os = "linux"`.
+    KeyValue(String, String),
+}
+
+const KEYS: [&str; 5] = ["target_os", "target_family", "target_arch", "target_env", "target_vendor"];
+
+impl Cfg {
+    /// Evaluates the expression for `target`.
+    pub fn matches(&self, target: &TargetInfo) -> bool {
+        match self {
+            Cfg::All(cfgs) => cfgs.iter().all(|cfg| cfg.matches(target)),
+            Cfg::Any(cfgs) => cfgs.iter().any(|cfg| cfg.matches(target)),
+            Cfg::Not(cfg) => !cfg.matches(target),
+            Cfg::Name(name) => target.family == *name,
+            Cfg::KeyValue(key, value) => {
+                let actual = match key.as_str() {
+                    "target_os" => &target.os,
+                    "target_family" => &target.family,
+                    "target_arch" => &target.arch,
+                    "target_env" => &target.env,
+                    "target_vendor" => &target.vendor,
+                    _ => return false,
+                };
+                actual == value
+            }
+        }
+    }
+}
+
+impl FromStr for Cfg {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Cfg> {
+        let mut parser = Parser { tokens: tokenize(s)?, position: 0, source: s };
+        parser.expect(Token::Ident("cfg".to_string()))?;
+        parser.expect(Token::Open)?;
+        let cfg = parser.expression()?;
+        parser.expect(Token::Close)?;
+        if parser.position != parser.tokens.len() {
+            return Err(parser.error("unexpected input after `cfg(...)`"));
+        }
+        Ok(cfg)
+    }
+}
+
+/// Returns true if the key of a `[target.<key>]` table applies to `target`.
+/// The key is either a `cfg(...)` expression or a target triple.
+///
+/// # Errors
+/// Returns [`Error::Parse`] if the key is an invalid `cfg(...)` expression.
+pub fn target_key_matches(key: &str, target: &TargetInfo) -> Result<bool> {
+    if key.starts_with("cfg(") {
+        return Ok(key.parse::<Cfg>()?.matches(target));
+    }
+    Ok(key == target.triple)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Open,
+    Close,
+    Comma,
+    Equals,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "`{}`", ident),
+            Token::Str(value) => write!(f, "\"{}\"", value),
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Equals => write!(f, "`=`"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '=' => tokens.push(Token::Equals),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(Error::Parse(format!("unterminated string in `{}`", s))),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_whitespace() => {}
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_') {
+                    ident.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            c => return Err(Error::Parse(format!("unexpected character `{}` in `{}`", c, s))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    source: &'a str,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::Parse(format!("{} in `{}`", message, self.source))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(self.error(&format!("expected {}, found {}", expected, token))),
+            None => Err(self.error(&format!("expected {}", expected))),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Cfg> {
+        let Some(Token::Ident(ident)) = self.next() else {
+            return Err(self.error("expected a predicate"));
+        };
+        match ident.as_str() {
+            "all" | "any" | "not" => {
+                self.expect(Token::Open)?;
+                let mut cfgs = Vec::new();
+                while self.peek() != Some(&Token::Close) {
+                    cfgs.push(self.expression()?);
+                    if self.peek() == Some(&Token::Comma) {
+                        self.next();
+                    }
+                    else {
+                        break;
+                    }
+                }
+                self.expect(Token::Close)?;
+                match ident.as_str() {
+                    "all" => Ok(Cfg::All(cfgs)),
+                    "any" => Ok(Cfg::Any(cfgs)),
+                    _ if cfgs.len() == 1 => Ok(Cfg::Not(Box::new(cfgs.remove(0)))),
+                    _ => Err(self.error("`not` takes exactly one predicate")),
+                }
+            }
+            "unix" | "windows" => Ok(Cfg::Name(ident)),
+            key if KEYS.contains(&key) => {
+                self.expect(Token::Equals)?;
+                match self.next() {
+                    Some(Token::Str(value)) => Ok(Cfg::KeyValue(ident, value)),
+                    _ => Err(self.error(&format!("expected a string after `{} =`", key))),
+                }
+            }
+            _ => Err(self.error(&format!("unsupported predicate `{}`", ident))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linux() -> TargetInfo {
+        TargetInfo::from_triple("x86_64-unknown-linux-musl")
+    }
+
+    #[test]
+    fn test_target_info() {
+        let linux = linux();
+        assert_eq!((linux.arch.as_str(), linux.os.as_str(), linux.env.as_str()), ("x86_64", "linux", "musl"));
+        let windows = TargetInfo::from_triple("i686-pc-windows-msvc");
+        assert_eq!((windows.arch.as_str(), windows.family.as_str(), windows.vendor.as_str()), ("x86", "windows", "pc"));
+        assert_eq!(TargetInfo::from_triple("aarch64-apple-darwin").env, "");
+        assert!(linux.matches("unix") && linux.matches("x86_64") && !linux.matches("windows"));
+    }
+
+    #[test]
+    fn test_parse_and_match() {
+        let cfg: Cfg = r#"cfg(any(target_os = "macos", all(target_arch = "x86_64", target_env = "musl")))"#.parse().unwrap();
+        assert!(cfg.matches(&linux()));
+        assert!(cfg.matches(&TargetInfo::from_triple("aarch64-apple-darwin")));
+        assert!(!cfg.matches(&TargetInfo::from_triple("x86_64-unknown-linux-gnu")));
+        let cfg: Cfg = "cfg(not(windows))".parse().unwrap();
+        assert!(cfg.matches(&linux()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for invalid in ["cfg(target_os)", "cfg(foo = \"x\")", "cfg(not(unix, windows))", "cfg(unix", "cfg(unix) x", "target_os = \"linux\""] {
+            assert!(matches!(invalid.parse::<Cfg>(), Err(Error::Parse(_))), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_target_key_matches() {
+        assert!(target_key_matches("x86_64-unknown-linux-musl", &linux()).unwrap());
+        assert!(!target_key_matches("aarch64-apple-darwin", &linux()).unwrap());
+        assert!(target_key_matches("cfg(target_os = \"linux\")", &linux()).unwrap());
+        assert!(target_key_matches("cfg(linux)", &linux()).is_err());
+    }
+}
@@ -1,4 +1,6 @@
+use std::path::PathBuf;
 use clap::{Parser, Subcommand};
+use frate::{ExportFormat, ShellKind};
 
 #[derive(Debug, Parser, Clone)]
 #[clap(author, version, about, long_about = None)]
@@ -10,13 +12,23 @@ pub struct Cli {
 #[derive(Debug, Subcommand, Clone, PartialEq)]
 pub enum FrateCommand {
     /// Uses the `activate` script to start a new shell with all installed tools in the `PATH`
-    Shell,
+    Shell {
+        /// Only puts the tools of this group in the `PATH`. Can be repeated.
+        #[clap(short, long)]
+        group: Vec<String>,
+    },
     /// Installs packages listed in the `frate.lock` file.
-    /// If no package name is specified, installs all packages.
+    /// If no package name is specified, installs all packages that are not optional.
     Install {
         /// Install a specific package by name.
         #[clap(short, long)]
         name: Option<String>,
+        /// Install only the tools of this group, including optional ones. Can be repeated.
+        #[clap(short, long, conflicts_with = "name")]
+        group: Vec<String>,
+        /// Skip the tools of this group. Can be repeated.
+        #[clap(long, conflicts_with = "name")]
+        without: Vec<String>,
     },
     /// Uninstalls packages and removes related directories and shims.
     /// If no package name is specified, uninstalls all packages.
@@ -42,17 +54,83 @@ pub enum FrateCommand {
         #[clap(short, long)]
         verbose: bool,
     },
-    /// Runs the executable binary of a tool from `.frate/bin/<tool_name>/`.
+    /// Runs a script from the `[scripts]` table of `frate.toml`, or the executable binary
+    /// of a tool from `.frate/bin/<tool_name>/`.
+    /// Arguments after the name are passed through unchanged, and `frate` exits
+    /// with the exit code of the tool or script.
     Run {
-        /// Command to run e.g., `just --version`
-        command: String,
+        /// Name of the script or tool to run, e.g. `lint` or `just`
+        #[clap(required_unless_present = "list")]
+        name: Option<String>,
+        /// Arguments for the tool, e.g. `frate run just -- --list`
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+        /// Lists all scripts defined in `frate.toml`.
+        #[clap(long, conflicts_with = "name")]
+        list: bool,
+    },
+    /// Prints shell code that puts all installed tools of the nearest project in the `PATH`
+    /// of the current shell. Use it with `eval "$(frate env)"` instead of starting a nested shell.
+    Env {
+        /// Shell to generate code for: bash, zsh, fish, nu or powershell.
+        /// Defaults to the shell in `$SHELL`.
+        #[clap(long)]
+        shell: Option<ShellKind>,
+        /// Prints code that restores the `PATH` from before activation.
+        #[clap(long)]
+        unset: bool,
+    },
+    /// Prints a shell hook that activates the nearest project whenever the directory changes.
+    /// Add `eval "$(frate hook bash)"` to `~/.bashrc`, `eval "$(frate hook zsh)"` to `~/.zshrc`,
+    /// or `frate hook fish | source` to `~/.config/fish/config.fish`.
+    Hook {
+        /// Shell to generate the hook for: bash, zsh or fish.
+        shell: ShellKind,
+    },
+    /// Prints instructions that download, verify and extract the locked tools without frate,
+    /// for a Dockerfile, a shell script or a GitHub Actions job.
+    Export {
+        /// Output format: dockerfile, sh or github-actions.
+        #[clap(short, long, default_value = "sh")]
+        format: ExportFormat,
+        /// Target triple to export the tools for. Defaults to the current platform.
+        #[clap(long)]
+        target: Option<String>,
+        /// Writes the output to a file instead of stdout.
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Copies the archives of all locked tools into the project, so `frate install` works offline.
+    /// The directory is saved as `vendor-dir` in `frate.toml` and defaults to `vendor/frate`.
+    Vendor {
+        /// Vendor directory, relative to the project root.
+        dir: Option<String>,
+    },
+    /// Packs `frate.toml`, `frate.lock` and the archives of all locked tools into a portable bundle,
+    /// or installs such a bundle without network access.
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
     },
     /// Synchronizes the `frate.lock` file with the current `frate.toml`.
     Sync,
     /// Initializes a new `frate.toml` in the current directory.
     Init,
-    /// Checks the health of the setup. (Currently unimplemented)
-    Doctor,
+    /// Checks the health of the project setup and reports problems with suggested fixes.
+    Doctor {
+        /// Applies all safe fixes automatically, then checks again.
+        #[clap(long)]
+        fix: bool,
+    },
+    /// Checks installed files against the hashes recorded at install time.
+    /// If no package name is specified, checks all installed packages.
+    Verify {
+        /// Name of the package to check.
+        name: Option<String>,
+        /// Reinstalls modified packages from the cache.
+        #[clap(long)]
+        repair: bool,
+    },
     /// Cleans global cache of a tool.
     /// If no name is given, all caches are cleaned
     Clean {
@@ -65,6 +143,23 @@ pub enum FrateCommand {
         /// Package name and version in the format `<name>@<version>` (version without leading 'v').
         name_at_version: String,
     },
+    /// Removes a tool from `frate.toml` and `frate.lock`, and uninstalls it if it is installed.
+    Remove {
+        /// Name of the tool to remove.
+        name: String,
+    },
+    /// Changes the version of a tool in `frate.toml` and locks the new version.
+    /// Note: The new version is not installed automatically.
+    Update {
+        /// Package name and new version in the format `<name>@<version>` (version without leading 'v').
+        name_at_version: String,
+    },
+    /// Adds the tools pinned in `.tool-versions` (asdf), `mise.toml`, `.mise.toml`, `aqua.yaml` or `aqua.yml` to `frate.toml`.
+    /// Tools that cannot be mapped to the registry are reported and skipped.
+    Import {
+        /// Files to import. Defaults to all of the above that exist in the current directory.
+        files: Vec<PathBuf>,
+    },
     /// Outputs the paths to installed binaries and shims for a given tool, if found.
     Which {
         /// Name of the tool to query.
@@ -73,9 +168,48 @@ pub enum FrateCommand {
         #[clap(short, long)]
         verbose: bool,
     },
+    /// Inspects lockfiles.
+    Lock {
+        #[command(subcommand)]
+        command: LockCommand,
+    },
     /// Outputs a list of all registered tools
     Registry {
         #[clap(short, long)]
         verbose: bool,
     },
 }
+
+#[derive(Debug, Subcommand, Clone, PartialEq)]
+pub enum LockCommand {
+    /// Shows the packages added, removed and changed between two lockfiles,
+    /// e.g. `frate lock diff <(git show main:frate.lock) frate.lock`.
+    Diff {
+        /// The old lockfile.
+        old: PathBuf,
+        /// The new lockfile.
+        new: PathBuf,
+    },
+}
+
+#[derive(Debug, Subcommand, Clone, PartialEq)]
+pub enum BundleCommand {
+    /// Syncs the lockfile and writes a bundle of `frate.toml`, `frate.lock` and the archives
+    /// of all tools for the given targets, e.g. `frate bundle create tools.tar.zst`.
+    Create {
+        /// The bundle to write, a zstd-compressed tarball.
+        output: PathBuf,
+        /// Target triple to bundle the tools for. Can be repeated. Defaults to the current platform.
+        #[clap(long)]
+        target: Vec<String>,
+    },
+    /// Writes `frate.toml` and `frate.lock` from a bundle, copies its archives into the
+    /// global cache and installs the tools, all without network access.
+    Install {
+        /// The bundle to install.
+        bundle: PathBuf,
+        /// Project directory to install into. Defaults to the current directory.
+        #[clap(long)]
+        dir: Option<PathBuf>,
+    },
+}
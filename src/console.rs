@@ -0,0 +1,43 @@
+use colored::Colorize;
+use verbosio::verbose;
+use frate::report::{Event, Reporter};
+
+/// Prints library progress events as coloured status lines to stdout.
+pub struct Console;
+
+impl Reporter for Console {
+    fn report(&self, event: Event) {
+        match event {
+            Event::CacheCreated { path } => {
+                println!("Cache directory created: {}", path.display());
+            }
+            Event::Downloading { url } => {
+                println!(" {} {}", "Downloading".bold().green(), url);
+            }
+            Event::Extracting { source, dest, cached: false } => {
+                println!("  {} {} to {}", "Extracting".bold().green(), source, dest.display());
+            }
+            Event::Extracting { source, dest, cached: true } => {
+                verbose!("  {} FROM CACHE {} to {}", "Extracting".bold().green(), source, dest.display());
+            }
+            Event::Caching { .. } => {
+                println!("     {}", "Caching".bold().green());
+            }
+            Event::Installed { name } => {
+                println!("   {} {}", "Installed".bold().green(), name);
+            }
+            Event::Uninstalling { name } => {
+                println!("{} {}", "Uninstalling".bold().yellow(), name.as_deref().unwrap_or("all packages"));
+            }
+            Event::Uninstalled { .. } => {
+                println!("        {}", "Done".bold().green());
+            }
+            Event::ResolveFailed { error, .. } => {
+                eprintln!("{} {}", "Failed to resolve dependency".red(), error.red());
+            }
+            Event::Vendored { name, path } => {
+                println!("    {} {} ({})", "Vendored".bold().green(), name, path.display());
+            }
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use std::path::{Path, PathBuf};
+use crate::error::Result;
+use crate::global::utils::get_global_cache_dir;
+use crate::project::Project;
+use crate::receipt::Receipt;
+use crate::report::Reporter;
+use crate::shims::{read_shim, remove_shim, shim_config_path, ShimConfig};
+use crate::util::{get_locked, is_installed, version_satisfies};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The check passed.
+    Ok,
+    /// Something is off, but frate keeps working.
+    Warning,
+    /// Something is broken.
+    Error,
+}
+
+/// A safe, automatic fix for a problem found by [`diagnose`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    /// Remove a package that is not declared in `frate.toml` from the lockfile.
+    Unlock(String),
+    /// Re-resolve a package whose locked version differs from `frate.toml`.
+    Relock(String),
+    /// Lock all dependencies that are missing from the lockfile.
+    Sync,
+    /// Remove a shim whose binary no longer exists.
+    RemoveShim(PathBuf),
+    /// Install, or reinstall, a locked package.
+    Install(String),
+    /// Regenerate the activation scripts.
+    WriteActivate,
+}
+
+/// The result of a single health check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Short name of the check, e.g. `"lock"` or `"shims"`.
+    pub check: &'static str,
+    /// How serious the finding is.
+    pub severity: Severity,
+    /// What was found.
+    pub message: String,
+    /// How to resolve the problem manually, if there is one.
+    pub suggestion: Option<String>,
+    /// A fix that [`apply_fix`] can perform automatically.
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    fn ok(check: &'static str, message: impl Into<String>) -> Diagnostic {
+        Diagnostic { check, severity: Severity::Ok, message: message.into(), suggestion: None, fix: None }
+    }
+    fn problem(
+        check: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+        suggestion: impl Into<String>,
+        fix: Option<Fix>,
+    ) -> Diagnostic {
+        Diagnostic { check, severity, message: message.into(), suggestion: Some(suggestion.into()), fix }
+    }
+}
+
+/// Runs all health checks against a project.
+///
+/// Checks that manifest and lockfile agree, that every locked package is installed
+/// from the locked archive, that all shims point at existing binaries, that the activation
+/// scripts exist, that the global cache is writable, whether `.frate/shims` is on the
+/// `PATH` of the current process, and whether the registry is reachable.
+pub fn diagnose(project: &Project) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    diagnostics.extend(check_lock(project));
+    diagnostics.extend(check_installed(project));
+    diagnostics.extend(check_shims(project));
+    diagnostics.push(check_activate(project));
+    diagnostics.push(check_cache());
+    diagnostics.push(check_path(project));
+    diagnostics.push(check_registry(project));
+    diagnostics
+}
+
+/// Applies a fix returned by [`diagnose`].
+///
+/// Fixes that change the lockfile save it to disk.
+///
+/// # Errors
+/// Returns an error if the fix cannot be applied, e.g. because resolving or installing fails.
+pub fn apply_fix(project: &mut Project, fix: &Fix, reporter: &dyn Reporter) -> Result<()> {
+    match fix {
+        Fix::Unlock(name) => {
+            project.lock_mut().remove(name);
+            project.save_lock()
+        }
+        Fix::Relock(name) => {
+            project.lock_mut().remove(name);
+            project.sync(reporter).map(|_| ())
+        }
+        Fix::Sync => project.sync(reporter).map(|_| ()),
+        Fix::RemoveShim(path) => remove_shim(path),
+        Fix::Install(name) => project.install(Some(name), reporter),
+        Fix::WriteActivate => project.write_activate(),
+    }
+}
+
+/// Collects the fixes of `diagnostics` in the order they must be applied.
+///
+/// Lockfile changes come before installs that depend on them, duplicates are removed,
+/// and packages that are about to be unlocked are not reinstalled.
+pub fn plan_fixes(diagnostics: &[Diagnostic]) -> Vec<Fix> {
+    let mut fixes: Vec<Fix> = diagnostics.iter().filter_map(|d| d.fix.clone()).collect();
+    fixes.sort_by_key(|fix| match fix {
+        Fix::Unlock(_) => 0,
+        Fix::Relock(_) => 1,
+        Fix::Sync => 2,
+        Fix::RemoveShim(_) => 3,
+        Fix::Install(_) => 4,
+        Fix::WriteActivate => 5,
+    });
+    fixes.dedup();
+    let unlocked: Vec<String> = fixes.iter()
+        .filter_map(|fix| match fix {
+            Fix::Unlock(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    fixes.retain(|fix| !matches!(fix, Fix::Install(name) if unlocked.contains(name)));
+    fixes
+}
+
+fn check_lock(project: &Project) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let lock = project.lock();
+    let manifest = project.effective_manifest();
+    for conflict in project.conflicts() {
+        diagnostics.push(Diagnostic::problem(
+            "lock",
+            Severity::Error,
+            format!("workspace members request different versions of {}", conflict),
+            format!("declare the same version of {} in every member", conflict.name),
+            None,
+        ));
+    }
+    for (name, dependency) in &manifest.current_dependencies() {
+        let version = dependency.version();
+        match get_locked(name, lock) {
+            None => diagnostics.push(Diagnostic::problem(
+                "lock",
+                Severity::Error,
+                format!("{} is declared in frate.toml but not locked", name),
+                "run `frate sync`",
+                Some(Fix::Sync),
+            )),
+            Some(locked) if !version_satisfies(&locked.version, version) => diagnostics.push(Diagnostic::problem(
+                "lock",
+                Severity::Error,
+                format!("{} is locked at {}, but frate.toml requires {}", name, locked.version, version),
+                format!("remove {} from frate.lock and run `frate sync`", name),
+                Some(Fix::Relock(name.clone())),
+            )),
+            Some(_) => {}
+        }
+    }
+    for package in &lock.packages {
+        if !manifest.declares(&package.name) {
+            diagnostics.push(Diagnostic::problem(
+                "lock",
+                Severity::Warning,
+                format!("{} is locked but not declared in frate.toml", package.name),
+                format!("remove {} from frate.lock", package.name),
+                Some(Fix::Unlock(package.name.clone())),
+            ));
+        }
+    }
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic::ok("lock", "frate.toml and frate.lock are consistent"));
+    }
+    diagnostics
+}
+
+fn check_installed(project: &Project) -> Vec<Diagnostic> {
+    let frate_dir = project.frate_dir();
+    let mut diagnostics = Vec::new();
+    for package in &project.lock().packages {
+        let fix = Some(Fix::Install(package.name.clone()));
+        let suggestion = format!("run `frate install --name {}`", package.name);
+        if !is_installed(&frate_dir, &package.name) {
+            if project.is_optional(&package.name) {
+                continue;
+            }
+            diagnostics.push(Diagnostic::problem(
+                "install",
+                Severity::Error,
+                format!("{} is locked but not installed", package.name),
+                suggestion,
+                fix,
+            ));
+            continue;
+        }
+        match Receipt::load(&frate_dir, &package.name) {
+            Ok(Some(receipt)) if receipt.hash == package.hash => {}
+            Ok(Some(receipt)) => diagnostics.push(Diagnostic::problem(
+                "install",
+                Severity::Error,
+                format!(
+                    "{} {} is installed, but frate.lock requires {} with hash {}",
+                    package.name, receipt.version, package.version, package.hash
+                ),
+                suggestion,
+                fix,
+            )),
+            Ok(None) => diagnostics.push(Diagnostic::problem(
+                "install",
+                Severity::Warning,
+                format!("{} has no install receipt, its hash cannot be checked", package.name),
+                suggestion,
+                fix,
+            )),
+            Err(e) => diagnostics.push(Diagnostic::problem(
+                "install",
+                Severity::Warning,
+                format!("the install receipt of {} cannot be read: {}", package.name, e),
+                suggestion,
+                fix,
+            )),
+        }
+    }
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic::ok("install", "all locked packages are installed"));
+    }
+    diagnostics
+}
+
+fn check_shims(project: &Project) -> Vec<Diagnostic> {
+    let shims_dir = project.frate_dir().join("shims");
+    let Ok(entries) = std::fs::read_dir(&shims_dir) else {
+        return vec![Diagnostic::problem(
+            "shims",
+            Severity::Warning,
+            format!("{} does not exist", shims_dir.display()),
+            "run `frate install`",
+            None,
+        )];
+    };
+    let mut diagnostics = Vec::new();
+    for entry in entries.flatten() {
+        let shim = entry.path();
+        let Ok(Some(target)) = read_shim(&shim) else {
+            continue;
+        };
+        let target = resolve_target(&shim, &target);
+        if !target.exists() && !is_pending(project, &shim) {
+            diagnostics.push(Diagnostic::problem(
+                "shims",
+                Severity::Error,
+                format!("{} points at missing binary {}", shim.display(), target.display()),
+                "remove the shim and reinstall the package",
+                Some(Fix::RemoveShim(shim)),
+            ));
+        }
+    }
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic::ok("shims", "all shims point at existing binaries"));
+    }
+    diagnostics
+}
+
+/// Returns true if `shim` is a binary shim that installs its package on first use,
+/// and the package is not installed yet. `check_installed` reports those packages.
+fn is_pending(project: &Project, shim: &Path) -> bool {
+    let Ok(config) = ShimConfig::load(shim_config_path(shim)) else {
+        return false;
+    };
+    config.package.is_some_and(|package| {
+        get_locked(&package, project.lock()).is_some() && !is_installed(&project.frate_dir(), &package)
+    })
+}
+
+/// Resolves a relative shim target against the directory of the shim.
+fn resolve_target(shim: &Path, target: &Path) -> PathBuf {
+    match shim.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target.to_path_buf(),
+    }
+}
+
+fn check_activate(project: &Project) -> Diagnostic {
+    let frate_dir = project.frate_dir();
+    let missing: Vec<&str> = ["activate", "activate.fish", "activate.ps1", "activate.bat", "deactivate.bat"]
+        .into_iter()
+        .filter(|script| !frate_dir.join(script).exists())
+        .collect();
+    if !missing.is_empty() {
+        return Diagnostic::problem(
+            "activate",
+            Severity::Error,
+            format!("missing activation scripts in {}: {}", frate_dir.display(), missing.join(", ")),
+            "regenerate the activation scripts",
+            Some(Fix::WriteActivate),
+        );
+    }
+    let activate = frate_dir.join("activate");
+    if !is_executable(&activate) {
+        return Diagnostic::problem(
+            "activate",
+            Severity::Error,
+            format!("{} is not executable", activate.display()),
+            "regenerate the activation scripts",
+            Some(Fix::WriteActivate),
+        );
+    }
+    Diagnostic::ok("activate", "all activation scripts exist and `activate` is executable")
+}
+
+/// Returns true if the POSIX `activate` script at `path` can be executed. Windows has no
+/// executable bit for it, so there it only has to exist.
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        crate::util::is_executable(path)
+    }
+    #[cfg(not(unix))]
+    {
+        path.exists()
+    }
+}
+
+fn check_cache() -> Diagnostic {
+    let result = get_global_cache_dir().and_then(|cache_dir| {
+        std::fs::create_dir_all(&cache_dir)?;
+        let probe = cache_dir.join(".frate-doctor");
+        std::fs::write(&probe, b"")?;
+        std::fs::remove_file(&probe)?;
+        Ok(cache_dir)
+    });
+    match result {
+        Ok(cache_dir) => Diagnostic::ok("cache", format!("{} is writable", cache_dir.display())),
+        Err(e) => Diagnostic::problem(
+            "cache",
+            Severity::Error,
+            format!("the global cache is not writable: {}", e),
+            "fix the directory permissions or set FRATE_CACHE_DIR to a writable directory",
+            None,
+        ),
+    }
+}
+
+fn check_path(project: &Project) -> Diagnostic {
+    let shims_dir = project.frate_dir().join("shims");
+    let shims_dir = shims_dir.canonicalize().unwrap_or(shims_dir);
+    let on_path = std::env::var_os("PATH")
+        .map(|path| {
+            std::env::split_paths(&path)
+                .any(|dir| dir.canonicalize().unwrap_or(dir) == shims_dir)
+        })
+        .unwrap_or(false);
+    if on_path {
+        Diagnostic::ok("path", format!("{} is on PATH", shims_dir.display()))
+    }
+    else {
+        Diagnostic::problem(
+            "path",
+            Severity::Warning,
+            format!("{} is not on PATH", shims_dir.display()),
+            "run `frate shell` or source the activation script in .frate/",
+            None,
+        )
+    }
+}
+
+fn check_registry(project: &Project) -> Diagnostic {
+    let registry = &project.settings().registry;
+    let url = format!("{}/registry.json", registry.trim_end_matches('/'));
+    let message = match project.fetcher().fetch(&url) {
+        Ok(Some(_)) => return Diagnostic::ok("registry", format!("{} is reachable", registry)),
+        Ok(None) => format!("{} is not a frate registry: {} was not found", registry, url),
+        Err(e) => format!("{} is not reachable: {}", registry, e),
+    };
+    Diagnostic::problem(
+        "registry",
+        Severity::Error,
+        message,
+        "check your network connection or the `registry` setting in frate.toml",
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock::LockedPackage;
+    use crate::util::expand_version;
+    use tempfile::tempdir;
+
+    fn problems(diagnostics: &[Diagnostic], check: &str) -> Vec<Diagnostic> {
+        diagnostics.iter()
+            .filter(|d| d.check == check && d.severity != Severity::Ok)
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_lock_consistency() {
+        let dir = tempdir().unwrap();
+        let mut project = Project::init(dir.path()).unwrap();
+        project.manifest_mut().add("unlocked", "1.0.0").unwrap();
+        project.manifest_mut().add("outdated", "2.0.0").unwrap();
+        project.lock_mut().packages.push(LockedPackage {
+            name: "outdated".to_string(),
+            version: expand_version("1.0.0"),
+            source: String::new(),
+            hash: String::new(),
+            ..Default::default()
+        });
+        project.lock_mut().packages.push(LockedPackage {
+            name: "stale".to_string(),
+            version: expand_version("1.0.0"),
+            source: String::new(),
+            hash: String::new(),
+            ..Default::default()
+        });
+
+        let mut diagnostics = check_lock(&project);
+        diagnostics.push(Diagnostic::problem(
+            "install", Severity::Error, "", "", Some(Fix::Install("stale".to_string())),
+        ));
+        assert_eq!(plan_fixes(&diagnostics), vec![
+            Fix::Unlock("stale".to_string()),
+            Fix::Relock("outdated".to_string()),
+            Fix::Sync,
+        ]);
+    }
+
+    #[test]
+    fn test_missing_activate_is_fixed() {
+        let dir = tempdir().unwrap();
+        let mut project = Project::init(dir.path()).unwrap();
+        std::fs::remove_file(project.frate_dir().join("activate.fish")).unwrap();
+        let diagnostic = check_activate(&project);
+        assert_eq!(diagnostic.severity, Severity::Error);
+
+        apply_fix(&mut project, &diagnostic.fix.unwrap(), &crate::report::Silent).unwrap();
+        assert_eq!(check_activate(&project).severity, Severity::Ok);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_executable_activate_is_fixed() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempdir().unwrap();
+        let mut project = Project::init(dir.path()).unwrap();
+        let activate = project.frate_dir().join("activate");
+        std::fs::set_permissions(&activate, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let diagnostic = check_activate(&project);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert!(diagnostic.message.contains("is not executable"), "{}", diagnostic.message);
+
+        apply_fix(&mut project, &diagnostic.fix.unwrap(), &crate::report::Silent).unwrap();
+        assert_eq!(check_activate(&project).severity, Severity::Ok);
+    }
+
+    #[test]
+    fn test_registry_without_index() {
+        use crate::fetch::testing::InMemory;
+        let dir = tempdir().unwrap();
+        let project = Project::init(dir.path()).unwrap();
+        let registry = format!("{}/registry.json", project.settings().registry.trim_end_matches('/'));
+
+        let missing = project.with_fetcher(InMemory(Default::default()));
+        let diagnostic = check_registry(&missing);
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert!(diagnostic.message.contains("was not found"), "{}", diagnostic.message);
+
+        let found = missing.with_fetcher(InMemory([(registry, b"{}".to_vec())].into()));
+        assert_eq!(check_registry(&found).severity, Severity::Ok);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dangling_shim() {
+        let dir = tempdir().unwrap();
+        let project = Project::init(dir.path()).unwrap();
+        let shim = project.frate_dir().join("shims").join("gone");
+        std::os::unix::fs::symlink(dir.path().join("missing"), &shim).unwrap();
+
+        let diagnostics = problems(&check_shims(&project), "shims");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].fix, Some(Fix::RemoveShim(shim)));
+    }
+}
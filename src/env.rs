@@ -0,0 +1,499 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use crate::error::{Error, Result};
+use crate::lock::LockedPackage;
+
+/// Placeholder for the installation directory of a tool (`.frate/bin/<name>`) in
+/// environment variables and `PATH` entries.
+pub const INSTALL_DIR: &str = "{install_dir}";
+/// Name of the variable holding the `PATH` from before activation.
+pub const OLD_PATH_VAR: &str = "FRATE_OLD_PATH";
+/// Name of the variable listing the names of all variables set on activation,
+/// so they can be unset again.
+pub const VARS_VAR: &str = "FRATE_VARS";
+
+/// A shell that [`Environment`] can generate activation code for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
+    PowerShell,
+}
+
+impl ShellKind {
+    /// Guesses the user's shell from `$SHELL`.
+    /// Falls back to PowerShell on Windows and to bash everywhere else.
+    pub fn detect() -> ShellKind {
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        let name = shell.rsplit(['/', '\\']).next().unwrap_or_default();
+        name.trim_end_matches(".exe").parse().unwrap_or(if cfg!(windows) {
+            ShellKind::PowerShell
+        } else {
+            ShellKind::Bash
+        })
+    }
+}
+
+impl FromStr for ShellKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ShellKind> {
+        match s {
+            "bash" | "sh" => Ok(ShellKind::Bash),
+            "zsh" => Ok(ShellKind::Zsh),
+            "fish" => Ok(ShellKind::Fish),
+            "nu" | "nushell" => Ok(ShellKind::Nu),
+            "powershell" | "pwsh" => Ok(ShellKind::PowerShell),
+            _ => Err(Error::Other(format!("unsupported shell: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for ShellKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShellKind::Bash => "bash",
+            ShellKind::Zsh => "zsh",
+            ShellKind::Fish => "fish",
+            ShellKind::Nu => "nu",
+            ShellKind::PowerShell => "powershell",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The environment of an activated project: directories to prepend to `PATH`
+/// and variables to set.
+///
+/// Activation saves the original `PATH` in `FRATE_OLD_PATH` and always builds the new `PATH`
+/// from it, so activating twice, or activating another project, does not stack entries.
+///
+/// # Example
+///
+/// ```
+/// use frate::{Environment, ShellKind};
+///
+/// let env = Environment { paths: vec!["/project/.frate/shims".into()], ..Default::default() };
+/// let code = env.activate(ShellKind::Bash);
+/// assert!(code.contains("export PATH='/project/.frate/shims':\"$FRATE_OLD_PATH\""));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Environment {
+    /// Directories to prepend to `PATH`, in order.
+    pub paths: Vec<PathBuf>,
+    /// Variables to set.
+    pub vars: BTreeMap<String, String>,
+}
+
+impl Environment {
+    /// Returns the environment variables and extra `PATH` directories of `packages`,
+    /// with `{install_dir}` replaced by the absolute installation directory of each package.
+    /// Relative directories are [relative to it](anchor_path).
+    ///
+    /// Later packages override variables of earlier ones.
+    pub fn for_packages(frate_dir: &Path, packages: &[LockedPackage]) -> Environment {
+        let mut environment = Environment::default();
+        for package in packages {
+            let install_dir = std::path::absolute(frate_dir.join("bin").join(&package.name))
+                .unwrap_or_else(|_| frate_dir.join("bin").join(&package.name));
+            for path in &package.paths {
+                environment.paths.push(PathBuf::from(expand_install_dir(&anchor_path(path), &install_dir)));
+            }
+            for (name, value) in &package.env {
+                environment.vars.insert(name.clone(), expand_install_dir(value, &install_dir));
+            }
+        }
+        environment
+    }
+    /// Returns true if the environment neither adds `PATH` entries nor sets variables.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.vars.is_empty()
+    }
+    /// Applies the environment to a command: prepends [`Environment::paths`] to the `PATH`
+    /// of the current process and sets [`Environment::vars`].
+    ///
+    /// # Errors
+    /// Returns an error if a path contains the platform's `PATH` separator.
+    pub fn apply(&self, command: &mut Command) -> Result<()> {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let paths = self.paths.iter().cloned().chain(std::env::split_paths(&path));
+        command.env("PATH", std::env::join_paths(paths).map_err(|e| Error::Other(e.to_string()))?);
+        command.envs(&self.vars);
+        Ok(())
+    }
+    /// Returns code that activates the environment when evaluated by `shell`.
+    ///
+    /// For nushell the result is a JSON record for `from json | load-env`, because nushell
+    /// cannot evaluate generated code.
+    pub fn activate(&self, shell: ShellKind) -> String {
+        let paths: Vec<String> = self.paths.iter().map(|p| p.display().to_string()).collect();
+        let names = self.vars.keys().cloned().collect::<Vec<_>>().join(" ");
+        let mut out = String::new();
+        match shell {
+            ShellKind::Bash | ShellKind::Zsh => {
+                out.push_str(&format!("if [ -z \"${{{0}:-}}\" ]; then export {0}=\"$PATH\"; fi\n", OLD_PATH_VAR));
+                let prefix: String = paths.iter().map(|p| format!("{}:", posix_quote(p))).collect();
+                out.push_str(&format!("export PATH={}\"${}\"\n", prefix, OLD_PATH_VAR));
+                for (name, value) in &self.vars {
+                    out.push_str(&format!("export {}={}\n", name, posix_quote(value)));
+                }
+                if !self.vars.is_empty() {
+                    out.push_str(&format!("export {}={}\n", VARS_VAR, posix_quote(&names)));
+                }
+            }
+            ShellKind::Fish => {
+                out.push_str(&format!("if not set -q {0}; set -gx {0} $PATH; end\n", OLD_PATH_VAR));
+                let prefix: String = paths.iter().map(|p| format!("{} ", fish_quote(p))).collect();
+                out.push_str(&format!("set -gx PATH {}${}\n", prefix, OLD_PATH_VAR));
+                for (name, value) in &self.vars {
+                    out.push_str(&format!("set -gx {} {}\n", name, fish_quote(value)));
+                }
+                if !self.vars.is_empty() {
+                    out.push_str(&format!("set -gx {} {}\n", VARS_VAR, fish_quote(&names)));
+                }
+            }
+            ShellKind::PowerShell => {
+                out.push_str(&format!("if (-not $env:{0}) {{ $env:{0} = $env:PATH }}\n", OLD_PATH_VAR));
+                let prefix: String = paths.iter()
+                    .map(|p| format!("{} + [IO.Path]::PathSeparator + ", powershell_quote(p)))
+                    .collect();
+                out.push_str(&format!("$env:PATH = {}$env:{}\n", prefix, OLD_PATH_VAR));
+                for (name, value) in &self.vars {
+                    out.push_str(&format!("$env:{} = {}\n", name, powershell_quote(value)));
+                }
+                if !self.vars.is_empty() {
+                    out.push_str(&format!("$env:{} = {}\n", VARS_VAR, powershell_quote(&names)));
+                }
+            }
+            ShellKind::Nu => {
+                let old_path = current_old_path();
+                let mut record = serde_json::Map::new();
+                let path: Vec<String> = paths.into_iter()
+                    .chain(std::env::split_paths(&old_path).map(|p| p.display().to_string()))
+                    .collect();
+                record.insert("PATH".to_string(), path.into());
+                record.insert(OLD_PATH_VAR.to_string(), old_path.into());
+                for (name, value) in &self.vars {
+                    record.insert(name.clone(), value.clone().into());
+                }
+                if !self.vars.is_empty() {
+                    record.insert(VARS_VAR.to_string(), names.into());
+                }
+                out.push_str(&serde_json::Value::Object(record).to_string());
+                out.push('\n');
+            }
+        }
+        out
+    }
+    /// Returns code that restores the `PATH` from before activation and unsets
+    /// all variables set by [`Environment::activate`].
+    ///
+    /// Reads `FRATE_OLD_PATH` and `FRATE_VARS` from the environment of the current process,
+    /// so it must run as a child of the activated shell.
+    pub fn deactivate(shell: ShellKind) -> String {
+        deactivate_with(
+            shell,
+            std::env::var_os(OLD_PATH_VAR),
+            &std::env::var(VARS_VAR).unwrap_or_default(),
+        )
+    }
+}
+
+/// Generates deactivation code for the given values of `FRATE_OLD_PATH` and `FRATE_VARS`.
+fn deactivate_with(shell: ShellKind, old_path: Option<OsString>, vars: &str) -> String {
+    let mut names: Vec<String> = vars
+        .split_whitespace()
+        .map(ToString::to_string)
+        .collect();
+    if !names.is_empty() {
+        names.push(VARS_VAR.to_string());
+    }
+    let mut out = String::new();
+    match shell {
+        ShellKind::Bash | ShellKind::Zsh => {
+            out.push_str(&format!(
+                "if [ -n \"${{{0}:-}}\" ]; then export PATH=\"${0}\"; unset {0}; fi\n",
+                OLD_PATH_VAR
+            ));
+            if !names.is_empty() {
+                out.push_str(&format!("unset {}\n", names.join(" ")));
+            }
+        }
+        ShellKind::Fish => {
+            out.push_str(&format!(
+                "if set -q {0}; set -gx PATH ${0}; set -e {0}; end\n",
+                OLD_PATH_VAR
+            ));
+            for name in &names {
+                out.push_str(&format!("set -e {}\n", name));
+            }
+        }
+        ShellKind::PowerShell => {
+            out.push_str(&format!(
+                "if ($env:{0}) {{ $env:PATH = $env:{0}; Remove-Item Env:{0} }}\n",
+                OLD_PATH_VAR
+            ));
+            for name in &names {
+                out.push_str(&format!("Remove-Item Env:{} -ErrorAction SilentlyContinue\n", name));
+            }
+        }
+        ShellKind::Nu => {
+            // `load-env` cannot remove variables, so they are emptied instead.
+            let mut record = serde_json::Map::new();
+            if let Some(old_path) = old_path.filter(|p| !p.is_empty()) {
+                let path: Vec<String> = std::env::split_paths(&old_path)
+                    .map(|p| p.display().to_string())
+                    .collect();
+                record.insert("PATH".to_string(), path.into());
+                record.insert(OLD_PATH_VAR.to_string(), "".into());
+            }
+            for name in &names {
+                record.insert(name.clone(), "".into());
+            }
+            out.push_str(&serde_json::Value::Object(record).to_string());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Replaces the `{install_dir}` placeholder in `template`.
+pub fn expand_install_dir(template: &str, install_dir: &Path) -> String {
+    template.replace(INSTALL_DIR, &install_dir.display().to_string())
+}
+
+/// Returns the `PATH` directory `path` of a package relative to `{install_dir}` if it is
+/// relative, so that it means the same in shims, activation scripts and exports.
+///
+/// # Example
+///
+/// ```
+/// use frate::env::anchor_path;
+///
+/// assert_eq!(anchor_path("go/bin"), "{install_dir}/go/bin");
+/// assert_eq!(anchor_path("{install_dir}/go/bin"), "{install_dir}/go/bin");
+/// ```
+pub fn anchor_path(path: &str) -> String {
+    if path.starts_with(INSTALL_DIR) || Path::new(path).is_absolute() {
+        path.to_string()
+    } else {
+        format!("{}/{}", INSTALL_DIR, path)
+    }
+}
+
+/// Returns true if `name` is a valid environment variable name: an ASCII letter or `_`,
+/// followed by ASCII letters, digits and `_`. Only such names can be written into the
+/// generated shell code without quoting.
+pub fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Deserializes environment variables, rejecting names that are not [valid](is_valid_env_name).
+/// Used for the `env` tables of `frate.toml`, the registry and `frate.lock`.
+pub(crate) fn deserialize_env_vars<'de, D>(deserializer: D) -> std::result::Result<BTreeMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let vars = <BTreeMap<String, String> as serde::Deserialize>::deserialize(deserializer)?;
+    if let Some(name) = vars.keys().find(|name| !is_valid_env_name(name)) {
+        return Err(serde::de::Error::custom(format!("invalid environment variable name `{}`", name)));
+    }
+    Ok(vars)
+}
+
+/// Returns a hook for `shell` that activates the nearest project containing a `frate.toml`
+/// whenever the working directory changes, and deactivates it when leaving the project.
+///
+/// The hook runs before every prompt. It only looks for `frate.toml` when the working
+/// directory changed, without starting a process, and only runs `frate env` when the
+/// project changed. `frate_exe` is the path of the `frate` binary the hook calls.
+///
+/// # Errors
+/// Returns an error for shells other than bash, zsh and fish.
+pub fn hook(shell: ShellKind, frate_exe: &Path) -> Result<String> {
+    let exe = frate_exe.display().to_string();
+    let hook = match shell {
+        ShellKind::Bash | ShellKind::Zsh => {
+            let register = if shell == ShellKind::Bash {
+                concat!(
+                    "case \";${PROMPT_COMMAND:-};\" in\n",
+                    "  *\";_frate_hook;\"*) ;;\n",
+                    "  *) PROMPT_COMMAND=\"_frate_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}\" ;;\n",
+                    "esac\n",
+                )
+            } else {
+                "autoload -Uz add-zsh-hook\nadd-zsh-hook precmd _frate_hook\n"
+            };
+            format!(
+                concat!(
+                    "_frate_hook() {{\n",
+                    "  [ \"$PWD\" = \"${{_FRATE_PWD:-}}\" ] && return\n",
+                    "  _FRATE_PWD=\"$PWD\"\n",
+                    "  local dir=\"$PWD\" root=\"\"\n",
+                    "  while :; do\n",
+                    "    if [ -f \"$dir/frate.toml\" ]; then root=\"$dir\"; break; fi\n",
+                    "    [ -z \"$dir\" ] && break\n",
+                    "    dir=\"${{dir%/*}}\"\n",
+                    "  done\n",
+                    "  [ \"$root\" = \"${{_FRATE_ROOT:-}}\" ] && return\n",
+                    "  if [ -n \"${{_FRATE_ROOT:-}}\" ]; then eval \"$({exe} env --shell {shell} --unset)\"; fi\n",
+                    "  _FRATE_ROOT=\"$root\"\n",
+                    "  if [ -n \"$root\" ]; then eval \"$({exe} env --shell {shell})\"; fi\n",
+                    "}}\n",
+                    "{register}",
+                ),
+                exe = posix_quote(&exe),
+                shell = shell,
+                register = register,
+            )
+        }
+        ShellKind::Fish => format!(
+            concat!(
+                "function _frate_hook --on-event fish_prompt\n",
+                "  test \"$PWD\" = \"$_FRATE_PWD\"; and return\n",
+                "  set -g _FRATE_PWD $PWD\n",
+                "  set -l dir $PWD\n",
+                "  set -l root ''\n",
+                "  while true\n",
+                "    if test -f \"$dir/frate.toml\"; set root $dir; break; end\n",
+                "    test -z \"$dir\"; and break\n",
+                "    set dir (string replace -r '/[^/]*$' '' -- $dir)\n",
+                "  end\n",
+                "  test \"$root\" = \"$_FRATE_ROOT\"; and return\n",
+                "  if test -n \"$_FRATE_ROOT\"; {exe} env --shell fish --unset | source; end\n",
+                "  set -g _FRATE_ROOT $root\n",
+                "  if test -n \"$root\"; {exe} env --shell fish | source; end\n",
+                "end\n",
+            ),
+            exe = fish_quote(&exe),
+        ),
+        _ => return Err(Error::Other(format!("frate hook does not support {}", shell))),
+    };
+    Ok(hook)
+}
+
+/// Returns `FRATE_OLD_PATH` if a project is active, otherwise the current `PATH`.
+fn current_old_path() -> String {
+    std::env::var(OLD_PATH_VAR).ok()
+        .filter(|p| !p.is_empty())
+        .or_else(|| std::env::var("PATH").ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn posix_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+pub(crate) fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+pub(crate) fn powershell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment() -> Environment {
+        Environment {
+            paths: vec![PathBuf::from("/it's/.frate/shims")],
+            vars: BTreeMap::from([("TOOL_HOME".to_string(), "/opt/tool".to_string())]),
+        }
+    }
+
+    #[test]
+    fn test_env_names() {
+        assert!(is_valid_env_name("GOROOT"));
+        assert!(is_valid_env_name("_tool_2"));
+        assert!(!is_valid_env_name(""));
+        assert!(!is_valid_env_name("2TOOL"));
+        assert!(!is_valid_env_name("X; rm -rf ~"));
+        assert!(!is_valid_env_name("A-B"));
+
+        let package: std::result::Result<LockedPackage, _> = toml::from_str(
+            "name = \"t\"\nversion = \"1\"\nsource = \"s\"\nhash = \"h\"\n[env]\n\"X; rm -rf ~\" = \"1\"\n"
+        );
+        let error = package.unwrap_err().to_string();
+        assert!(error.contains("invalid environment variable name `X; rm -rf ~`"), "{}", error);
+    }
+
+    #[test]
+    fn test_for_packages() {
+        let package = LockedPackage {
+            name: "go".to_string(),
+            env: BTreeMap::from([("GOROOT".to_string(), "{install_dir}/go".to_string())]),
+            paths: vec!["{install_dir}/go/bin".to_string(), "go/pkg/tool".to_string()],
+            ..Default::default()
+        };
+        let environment = Environment::for_packages(Path::new("/p/.frate"), &[package]);
+        let install_dir = Path::new("/p/.frate").join("bin").join("go");
+        assert_eq!(environment.vars["GOROOT"], format!("{}/go", install_dir.display()));
+        assert_eq!(environment.paths, vec![
+            PathBuf::from(format!("{}/go/bin", install_dir.display())),
+            PathBuf::from(format!("{}/go/pkg/tool", install_dir.display())),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_shell() {
+        assert_eq!("zsh".parse::<ShellKind>().unwrap(), ShellKind::Zsh);
+        assert_eq!("pwsh".parse::<ShellKind>().unwrap(), ShellKind::PowerShell);
+        assert!("tcsh".parse::<ShellKind>().is_err());
+    }
+
+    #[test]
+    fn test_activate_bash() {
+        let code = environment().activate(ShellKind::Bash);
+        assert_eq!(code, concat!(
+            "if [ -z \"${FRATE_OLD_PATH:-}\" ]; then export FRATE_OLD_PATH=\"$PATH\"; fi\n",
+            "export PATH='/it'\\''s/.frate/shims':\"$FRATE_OLD_PATH\"\n",
+            "export TOOL_HOME='/opt/tool'\n",
+            "export FRATE_VARS='TOOL_HOME'\n",
+        ));
+    }
+
+    #[test]
+    fn test_activate_fish_and_powershell() {
+        let fish = environment().activate(ShellKind::Fish);
+        assert!(fish.contains("set -gx PATH '/it\\'s/.frate/shims' $FRATE_OLD_PATH\n"));
+        let powershell = environment().activate(ShellKind::PowerShell);
+        assert!(powershell.contains("$env:PATH = '/it''s/.frate/shims' + [IO.Path]::PathSeparator + $env:FRATE_OLD_PATH\n"));
+    }
+
+    #[test]
+    fn test_hook_shells() {
+        assert!(hook(ShellKind::Fish, Path::new("/bin/frate")).unwrap().contains("'/bin/frate' env --shell fish | source"));
+        assert!(hook(ShellKind::Nu, Path::new("/bin/frate")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_activate_and_deactivate_in_sh() {
+        let activate = environment().activate(ShellKind::Bash);
+        // Deactivation code is generated in the activated shell, where these variables are set.
+        let deactivate = deactivate_with(ShellKind::Bash, Some("/usr/bin:/bin".into()), "TOOL_HOME");
+        let script = format!(
+            "{}\necho \"$PATH\"\n{}echo \"$PATH\"\necho \"${{TOOL_HOME:-unset}}\"",
+            activate, deactivate
+        );
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .env("PATH", "/usr/bin:/bin")
+            .env_remove(OLD_PATH_VAR)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout, "/it's/.frate/shims:/usr/bin:/bin\n/usr/bin:/bin\nunset\n");
+    }
+}
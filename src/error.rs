@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+/// Errors returned by the `frate` library.
+///
+/// Each variant describes one class of failure, so callers can react to e.g. a hash
+/// mismatch differently than to a missing registry entry.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// No `frate.toml` exists in the project root.
+    #[error("frate.toml not found in {0}. Run `frate init` to create one.")]
+    ManifestNotFound(PathBuf),
+    /// The registry has no entry for the tool.
+    #[error("tool not found in registry: {0}")]
+    RegistryNotFound(String),
+    /// The registry entry exists, but has no release for the requested version and platform.
+    #[error("tool version not found in registry: {name} {version}")]
+    VersionNotFound {
+        /// Name of the tool.
+        name: String,
+        /// The fully expanded version that was requested.
+        version: String,
+    },
+    /// A package is not listed in `frate.lock`.
+    #[error("package not found in frate.lock: {0}")]
+    NotLocked(String),
+    /// A version string is not valid semver.
+    #[error("invalid version: {0}")]
+    InvalidVersion(String),
+    /// A dependency is already declared in `frate.toml`.
+    #[error("dependency {0} already exists")]
+    DependencyExists(String),
+    /// A dependency is not declared in `frate.toml`.
+    #[error("dependency {0} not found in frate.toml")]
+    DependencyNotFound(String),
+    /// The SHA-256 hash of an archive does not match the locked hash.
+    #[error("hash mismatch for {archive}:\n  expected: {expected}\n  got: {actual}")]
+    HashMismatch {
+        /// URL or path of the archive.
+        archive: String,
+        /// Hash recorded in the lockfile.
+        expected: String,
+        /// Hash of the actual archive contents.
+        actual: String,
+    },
+    /// The archive is neither `.zip` nor `.tar.gz`, or is corrupt.
+    #[error("unsupported archive: {0}")]
+    UnsupportedArchive(String),
+    /// No executable was found for an installed package.
+    #[error("binary not found: {0}")]
+    BinaryNotFound(String),
+    /// A network request failed or returned an unsuccessful status.
+    #[error("network error: {0}")]
+    Network(String),
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A manifest, lockfile or registry document could not be parsed or serialized.
+    #[error("parse error: {0}")]
+    Parse(String),
+    /// A script is not defined in the `[scripts]` table of `frate.toml`.
+    #[error("script not found: {0}")]
+    ScriptNotFound(String),
+    /// Scripts depend on each other in a cycle.
+    #[error("script dependency cycle: {0}")]
+    ScriptCycle(String),
+    /// Members of a workspace request different versions of the same tools.
+    #[error("version conflicts between workspace members:\n{0}")]
+    VersionConflict(String),
+    /// Any other failure, e.g. the platform's directories could not be determined.
+    #[error("{0}")]
+    Other(String),
+}
+
+/// A `Result` alias using the library [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<toml_edit::TomlError> for Error {
+    fn from(e: toml_edit::TomlError) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Network(e.to_string())
+    }
+}
+
+impl From<walkdir::Error> for Error {
+    fn from(e: walkdir::Error) -> Self {
+        Error::Io(e.into())
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Error::UnsupportedArchive(e.to_string())
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Self {
+        Error::Other(e.to_string())
+    }
+}
@@ -1,20 +1,19 @@
-use std::process::Command;
+use std::path::{Path, PathBuf};
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
-use serde::Deserialize;
 use verbosio::{set_verbosity, verbose};
-use frate::installer::{install_package, install_packages, uninstall_package, uninstall_packages};
-use frate::lock::FrateLock;
-use frate::registry::fetch_registry;
-use frate::{clean_cache, fetch_description, filter_versions, is_cached, remove_cached_archive};
-use frate::shims::{run_shell_with_frate_path};
-#[cfg(windows)]
-use frate::shims::{write_windows_activate};
-#[cfg(unix)]
-use frate::shims::{write_unix_activate};
-use frate::toml::FrateToml;
-use frate::util::{ensure_frate_dirs, find_installed_paths, get_frate_toml, get_locked, is_installed, sort_versions};
-use crate::cli::{FrateCommand, Cli};
+use frate::doctor::{apply_fix, diagnose, plan_fixes, Diagnostic, Severity};
+use frate::env::{hook, Environment, ShellKind};
+use frate::project::Project;
+use frate::scripts::{plan_script, script_command};
+use frate::fetch::ReqwestFetcher;
+use frate::registry::{fetch_index, fetch_registry};
+use frate::settings::DEFAULT_REGISTRY;
+use frate::{clean_cache, current_target_triple, export, fetch_description, filter_versions, find_import_files, read_pins, remove_cached_archive, ExportFormat, FrateLock, IMPORT_FILES};
+use frate::shims::run_shell;
+use frate::util::{get_frate_toml, get_locked, sort_versions};
+use crate::cli::{BundleCommand, FrateCommand, Cli, LockCommand};
+use crate::console::Console;
 
 /// Executes the given CLI command.
 ///
@@ -23,13 +22,17 @@ use crate::cli::{FrateCommand, Cli};
 pub fn execute(cli: Cli) -> Result<()> {
     match &cli.command {
         FrateCommand::Search { .. } |
-        FrateCommand::Shell |
+        FrateCommand::Shell { .. } |
         FrateCommand::Clean { .. } |
+        FrateCommand::Env { .. } |
+        FrateCommand::Hook { .. } |
+        FrateCommand::Lock { .. } |
+        FrateCommand::Bundle { command: BundleCommand::Install { .. } } |
         FrateCommand::Init => {},
         _ => {
             let toml_path = get_frate_toml()?;
             if !toml_path.exists() {
-                bail!("frate.toml not found. Run `frate init` to create one.")
+                return Err(frate::Error::ManifestNotFound(std::env::current_dir()?).into());
             }
         }
     }
@@ -40,9 +43,9 @@ pub fn execute(cli: Cli) -> Result<()> {
             }
             execute_list()
         },
-        FrateCommand::Shell => {
+        FrateCommand::Shell { group } => {
             set_verbosity!();
-            execute_shell()
+            execute_shell(&group)
         }
         FrateCommand::Init => {
             execute_init()
@@ -50,8 +53,8 @@ pub fn execute(cli: Cli) -> Result<()> {
         FrateCommand::Sync => {
             execute_sync()
         }
-        FrateCommand::Install { name } => {
-            execute_install(name)
+        FrateCommand::Install { name, group, without } => {
+            execute_install(name, &group, &without)
         }
         FrateCommand::Uninstall { name } => {
             execute_uninstall(name)
@@ -62,12 +65,36 @@ pub fn execute(cli: Cli) -> Result<()> {
             }
             execute_which(&name)
         }
-        FrateCommand::Run { command } => {
-            execute_run(&command)
+        FrateCommand::Run { name, args, list } => {
+            match name {
+                Some(name) if !list => execute_run(&name, &args),
+                _ => execute_list_scripts(),
+            }
         }
         FrateCommand::Add { name_at_version } => {
             execute_add(name_at_version)
         }
+        FrateCommand::Remove { name } => {
+            execute_remove(&name)
+        }
+        FrateCommand::Update { name_at_version } => {
+            execute_update(name_at_version)
+        }
+        FrateCommand::Export { format, target, output } => {
+            execute_export(format, target, output.as_deref())
+        }
+        FrateCommand::Vendor { dir } => {
+            execute_vendor(dir.as_deref())
+        }
+        FrateCommand::Bundle { command: BundleCommand::Create { output, target } } => {
+            execute_bundle_create(&output, &target)
+        }
+        FrateCommand::Bundle { command: BundleCommand::Install { bundle, dir } } => {
+            execute_bundle_install(&bundle, dir)
+        }
+        FrateCommand::Import { files } => {
+            execute_import(&files)
+        }
         FrateCommand::Search { name, versions, verbose } => {
             if verbose {
                 set_verbosity!();
@@ -77,14 +104,26 @@ pub fn execute(cli: Cli) -> Result<()> {
         FrateCommand::Clean { name } => {
             execute_clean(name)
         }
+        FrateCommand::Lock { command: LockCommand::Diff { old, new } } => {
+            execute_lock_diff(&old, &new)
+        }
         FrateCommand::Registry { verbose } => {
             if verbose {
                 set_verbosity!();
             }
             execute_registry()
         }
-        _ => {
-            Ok(())
+        FrateCommand::Env { shell, unset } => {
+            execute_env(shell.unwrap_or_else(ShellKind::detect), unset)
+        }
+        FrateCommand::Hook { shell } => {
+            execute_hook(shell)
+        }
+        FrateCommand::Doctor { fix } => {
+            execute_doctor(fix)
+        }
+        FrateCommand::Verify { name, repair } => {
+            execute_verify(name, repair)
         }
     }
 }
@@ -98,51 +137,38 @@ pub fn execute(cli: Cli) -> Result<()> {
 /// # Errors
 /// Returns an error if reading or parsing the manifest or lock file fails.
 pub fn execute_list() -> Result<()> {
-    let toml_path = get_frate_toml()?;
-    let toml_str = std::fs::read_to_string(toml_path)?;
-    let toml: FrateToml = toml::from_str(&toml_str)?;
-    let lock_path = std::env::current_dir()?.join("frate.lock");
-    let lock: Option<FrateLock> = if lock_path.exists() {
-        let lock_str = std::fs::read_to_string(lock_path)?;
-        Some(toml::from_str(&lock_str)?)
-    }
-    else {
-        None
-    };
+    let project = Project::load(std::env::current_dir()?)?;
+    let has_lock = project.lock_path().exists();
+    let tools = project.list();
 
-    if toml.dependencies.is_empty() {
+    if tools.is_empty() {
         println!("{}", "No dependencies".yellow());
         return Ok(());
     }
 
-    for (name, version) in &toml.dependencies {
-        println!("{}: {}", name.bold(), version.bold());
-        if let Some(lock) = &lock {
-                let locked = get_locked(name, lock);
-                match locked {
+    for tool in &tools {
+        println!("{}: {}", tool.name.bold(), tool.version.bold());
+        if has_lock {
+                match &tool.locked {
                     Some(locked) => {
-                        print!("  {}", " locked".green());
+                        print!("  {}", " locked".green());
                         verbose!(@lvl 1, " {} {}", "at:".green(), locked.version.green());
-                        verbose!(@lvl 1, "  {} {}", " hash:".green(), locked.hash.green());
+                        verbose!(@lvl 1, "  {} {}", " hash:".green(), locked.hash.green());
                         verbose!(@lvl 1, "  {} {}", "󰳏 source:".cyan(), locked.source.cyan());
-                        match is_cached(format!("{}-{}", locked.name, locked.version ).as_str()) {
-                            Ok(true) => {
-                                println!("  {}", "󰃨 cached".green());
-                            }
-                            _ => {}
+                        if tool.cached {
+                            println!("  {}", "󰃨 cached".green());
                         }
-
                     },
                     None => {
-                        println!("  {}", " unlocked".yellow());
+                        println!("  {}", " unlocked".yellow());
                     }
                 }
-                match is_installed(name) {
+                match tool.installed {
                     true => {
-                        print!("  {}", " installed".green());
+                        print!("  {}", " installed".green());
                     },
                     false => {
-                        print!("  {}", " not installed".red());
+                        print!("  {}", " not installed".red());
                     },
                 }
         }
@@ -155,20 +181,9 @@ pub fn execute_list() -> Result<()> {
 /// # Errors
 /// Returns an error if the current directory name cannot be determined or file operations fail.
 pub fn execute_init() -> Result<()> {
-    let cwd = std::env::current_dir()?;
-    let name = cwd.file_name().ok_or(anyhow::anyhow!("Could not get file name"))?
-        .to_str().ok_or(anyhow::anyhow!("Invalid directory name"))?;
-    let _ = ensure_frate_dirs(&cwd)
-        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-    println!(" {} {}", "Initialized".green().bold(), ".frate/");
-    let toml = FrateToml::default(name);
-    toml.save(cwd.join("frate.toml")).map_err(|e| anyhow::anyhow!("{:?}", e))?;
-    println!(" {} {}", "Initialized".green().bold(), "frate.toml");
-
-    #[cfg(windows)]
-    write_windows_activate()?;
-    #[cfg(not(windows))]
-    write_unix_activate()?;
+    Project::init(std::env::current_dir()?)?;
+    println!(" {} .frate/", "Initialized".green().bold());
+    println!(" {} frate.toml", "Initialized".green().bold());
     Ok(())
 }
 /// Synchronizes the `frate.lock` file with the current `frate.toml`.
@@ -176,11 +191,8 @@ pub fn execute_init() -> Result<()> {
 /// # Errors
 /// Returns an error if reading, parsing, syncing or saving fails.
 pub fn execute_sync() -> Result<()> {
-    let cwd = std::env::current_dir()?;
-    let toml_str = std::fs::read_to_string(cwd.join("frate.toml"))?;
-    let toml: FrateToml = toml::from_str(&toml_str)?;
-    let mut lock = FrateLock::load_or_default(cwd.join("frate.lock"));
-    let added = lock.sync(&toml)?;
+    let mut project = Project::load(std::env::current_dir()?)?;
+    let added = project.sync(&Console)?;
     if added.is_empty() {
         println!("      {} {}", "Synced".bold().green(), "None".bold().yellow());
     }
@@ -191,32 +203,23 @@ pub fn execute_sync() -> Result<()> {
             added.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
         );
     }
-
-    lock.save(cwd.join("frate.lock"))?;
-    println!("       {} {}", "Saved".bold().green(), "frate.lock");
+    println!("       {} frate.lock", "Saved".bold().green());
     Ok(())
 }
-/// Installs a specific package or all packages if none specified.
+/// Installs a specific package, or the packages selected by `groups` and `without` if none specified.
 ///
 /// # Arguments
 /// * `name` - Optional package name to install.
+/// * `groups` - Groups to install; all non-optional packages if empty.
+/// * `without` - Groups to skip.
 ///
 /// # Errors
 /// Returns an error if the package is not found or installation fails.
-pub fn execute_install(name: Option<String>) -> Result<()> {
-    let cwd = std::env::current_dir()?;
-    let lock = FrateLock::load_or_default(cwd.join("frate.lock"));
+pub fn execute_install(name: Option<String>, groups: &[String], without: &[String]) -> Result<()> {
+    let project = Project::load(std::env::current_dir()?)?;
     match name {
-        Some(name) => {
-            let package = get_locked(&name, &lock)
-                .ok_or(anyhow::anyhow!(" Package not found: {}", name))?;
-            install_package(&package, &cwd.join(".frate"))
-                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-        }
-        None => {
-            install_packages(&lock, &cwd)
-                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-        }
+        Some(name) => project.install(Some(&name), &Console)?,
+        None => project.install_groups(groups, without, &Console)?,
     }
     Ok(())
 }
@@ -228,16 +231,8 @@ pub fn execute_install(name: Option<String>) -> Result<()> {
 /// # Errors
 /// Returns an error if uninstallation fails.
 pub fn execute_uninstall(name: Option<String>) -> Result<()> {
-    match name {
-        Some(name) => {
-            uninstall_package(&name)
-                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-        }
-        None => {
-            uninstall_packages()
-                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-        }
-    }
+    let project = Project::load(std::env::current_dir()?)?;
+    project.uninstall(name.as_deref(), &Console)?;
     Ok(())
 }
 /// Prints paths of installed executable and shim for the given package name.
@@ -248,7 +243,8 @@ pub fn execute_uninstall(name: Option<String>) -> Result<()> {
 /// # Errors
 /// Returns an error if path lookup fails.
 pub fn execute_which(name: &str) -> Result<()> {
-    let (exe_path, shim_path) = find_installed_paths(name)?;
+    let project = Project::load(std::env::current_dir()?)?;
+    let (exe_path, shim_path) = project.which(name)?;
     if exe_path.is_none() && shim_path.is_none() {
         println!("{}", "No installed paths found".yellow());
         return Ok(());
@@ -261,44 +257,81 @@ pub fn execute_which(name: &str) -> Result<()> {
     }
     Ok(())
 }
-/// Runs an installed executable with given arguments.
+/// Runs a script from `frate.toml`, or an installed executable with given arguments.
+/// Scripts take precedence over tools of the same name.
+///
+/// The tool inherits stdin, stdout and stderr. On Unix, `frate` replaces itself with the tool,
+/// so signals reach it directly and its exit status becomes the exit status of `frate`.
+/// On other platforms the tool is spawned and `frate` exits with its exit code.
 ///
 /// # Arguments
 /// * `name` - Name of the executable.
 /// * `args` - Arguments to pass to the executable.
 ///
 /// # Errors
-/// Returns an error if execution fails or the executable is not found.
-pub fn execute_run(command: &str) -> Result<()> {
-    let (name, args) = match command.split_once(' ') {
-        Some((name, args)) => {
-            (name, args.split_whitespace().into_iter().collect::<Vec<_>>())
-        },
-        None => {
-            bail!("Invalid command: {}", command);
-        }
-    };
-    let (exe_path, _) = find_installed_paths(name)?;
-    let exe_path = match exe_path {
-        Some(exe_path) => {
-            exe_path
+/// Returns an error if the executable is not found or cannot be started.
+pub fn execute_run(name: &str, args: &[String]) -> Result<()> {
+    let project = Project::load(std::env::current_dir()?)?;
+    if project.manifest().scripts.contains_key(name) {
+        return execute_script(&project, name, args);
+    }
+    let mut command = project.command(name)?;
+    command.args(args);
+    verbose!("     {} {} {}", "Running".green().bold(), command.get_program().to_string_lossy(), args.join(" "));
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = command.exec();
+        Err(error).with_context(|| format!("Failed to run {}", name))
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command.status().with_context(|| format!("Failed to run {}", name))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+/// Runs a script and its dependencies, after installing missing tools from the lockfile.
+///
+/// `args` are appended to the command line of `name`, but not to its dependencies.
+/// If a script fails, `frate` exits with its exit code.
+///
+/// # Errors
+/// Returns an error if the script or a dependency does not exist, installing fails,
+/// or a script cannot be started.
+fn execute_script(project: &Project, name: &str, args: &[String]) -> Result<()> {
+    project.install_missing(&Console)?;
+    let scripts = &project.manifest().scripts;
+    let environment = project.environment();
+    for step in plan_script(scripts, name)? {
+        let run = scripts[&step].run();
+        let step_args = if step == name { args } else { &[] };
+        eprintln!("     {} {}: {}", "Running".green().bold(), step.bold(), run);
+        let status = script_command(project.manifest_dir(), &environment, run, step_args)?
+            .status()
+            .with_context(|| format!("Failed to run script {}", step))?;
+        if !status.success() {
+            std::process::exit(status.code().unwrap_or(1));
         }
-        None => {
-            bail!("Executable not found: {}", name);
+    }
+    Ok(())
+}
+/// Lists all scripts defined in `frate.toml` with their command lines.
+///
+/// # Errors
+/// Returns an error if the manifest cannot be loaded.
+pub fn execute_list_scripts() -> Result<()> {
+    let project = Project::load(std::env::current_dir()?)?;
+    let scripts = &project.manifest().scripts;
+    if scripts.is_empty() {
+        println!("{}", "No scripts".yellow());
+        return Ok(());
+    }
+    for (name, script) in scripts {
+        println!("{}: {}", name.bold(), script.run());
+        if !script.depends().is_empty() {
+            println!("  {} {}", "depends on:".dimmed(), script.depends().join(", ").dimmed());
         }
-    };
-    println!(
-        "     {} {} {}",
-        "Running".green().bold(),
-        exe_path.display(),
-        args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(" ")
-    );
-    let output = Command::new(exe_path)
-        .args(args).output()?;
-    if !output.status.success() {
-        bail!("{}", String::from_utf8(output.stderr)?.red());
     }
-    print!("{}", String::from_utf8(output.stdout)?);
     Ok(())
 }
 /// Parses a string of the format "name@version" into a tuple.
@@ -323,14 +356,201 @@ fn extract_name_at_version(name_at_version: &str) -> Result<(String, String)> {
 /// Returns an error if parsing, loading, or saving fails.
 pub fn execute_add(name_at_version: String) -> Result<()> {
     let (name, version) = extract_name_at_version(&name_at_version)?;
-    let mut toml = FrateToml::load(std::env::current_dir()?.join("frate.toml"))
-        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-    toml.add(&name, &version)?;
-    toml.save(std::env::current_dir()?.join("frate.toml"))
-        .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let mut project = Project::load(std::env::current_dir()?)?;
+    project.manifest_mut().add(&name, &version)?;
+    project.save_manifest()?;
     println!("       {} {}", "Added".green().bold(), name_at_version);
     Ok(())
 }
+/// Removes a dependency from `frate.toml` and `frate.lock` and uninstalls it.
+///
+/// # Arguments
+/// * `name` - Name of the dependency.
+///
+/// # Errors
+/// Returns an error if the dependency does not exist, or saving or uninstalling fails.
+pub fn execute_remove(name: &str) -> Result<()> {
+    let mut project = Project::load(std::env::current_dir()?)?;
+    project.remove(name, &Console)?;
+    println!("     {} {}", "Removed".green().bold(), name);
+    Ok(())
+}
+/// Adds the tools pinned by other version managers to `frate.toml`, and reports those that
+/// could not be added.
+///
+/// # Arguments
+/// * `files` - Files to import. If empty, the [import files](frate::IMPORT_FILES) in the current
+///   directory are used.
+///
+/// # Errors
+/// Returns an error if there is nothing to import, a file cannot be parsed, or the registry
+/// index cannot be fetched.
+pub fn execute_import(files: &[PathBuf]) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let files = if files.is_empty() { find_import_files(&cwd) } else { files.to_vec() };
+    if files.is_empty() {
+        bail!("Nothing to import: none of {} found", IMPORT_FILES.join(", "));
+    }
+    let mut project = Project::load(&cwd)?;
+    for file in &files {
+        let pins = read_pins(file).with_context(|| format!("cannot import {}", file.display()))?;
+        let report = project.import(&pins)?;
+        for (name, version) in &report.added {
+            println!("    {} {}@{} from {}", "Imported".green().bold(), name, version, file.display());
+        }
+        for skipped in &report.skipped {
+            eprintln!("     {} {}: {}", "Skipped".yellow().bold(), skipped.pin, skipped.reason);
+        }
+    }
+    Ok(())
+}
+/// Copies the archives of all locked packages into the vendor directory.
+///
+/// # Arguments
+/// * `dir` - Vendor directory relative to the project root; defaults to the configured one.
+///
+/// # Errors
+/// Returns an error if an archive cannot be obtained or verified, or writing fails.
+pub fn execute_vendor(dir: Option<&str>) -> Result<()> {
+    let mut project = Project::load(std::env::current_dir()?)?;
+    let manifest = project.vendor(dir, &Console)?;
+    println!("    {} {} archives in {}", "Vendored".green().bold(), manifest.archives.len(), project.vendor_dir().display());
+    Ok(())
+}
+/// Syncs the lockfile and writes a bundle of the project and the archives of its tools.
+///
+/// # Arguments
+/// * `output` - The bundle to write.
+/// * `targets` - Target triples to bundle the tools for; defaults to the current platform.
+///
+/// # Errors
+/// Returns an error if syncing fails, an archive cannot be obtained, or writing fails.
+pub fn execute_bundle_create(output: &Path, targets: &[String]) -> Result<()> {
+    let mut project = Project::load(std::env::current_dir()?)?;
+    project.sync(&Console)?;
+    let targets = match targets.is_empty() {
+        true => vec![current_target_triple()],
+        false => targets.to_vec(),
+    };
+    let manifest = project.create_bundle(output, &targets, &Console)
+        .with_context(|| format!("cannot bundle {}", output.display()))?;
+    println!(
+        "     {} {} archives for {} into {}",
+        "Bundled".green().bold(),
+        manifest.archives.len(),
+        targets.join(", "),
+        output.display()
+    );
+    Ok(())
+}
+/// Installs a bundle created by `frate bundle create` without network access.
+///
+/// # Arguments
+/// * `bundle` - The bundle to install.
+/// * `dir` - Project directory to install into; defaults to the current directory.
+///
+/// # Errors
+/// Returns an error if the bundle cannot be read, does not fit the directory or platform,
+/// or installation fails.
+pub fn execute_bundle_install(bundle: &Path, dir: Option<PathBuf>) -> Result<()> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => std::env::current_dir()?,
+    };
+    let project = Project::install_bundle(bundle, &dir, &Console)
+        .with_context(|| format!("cannot install {}", bundle.display()))?;
+    println!(
+        "   {} {} tools from {} into {}",
+        "Installed".green().bold(),
+        project.select_packages(&[], &[])?.len(),
+        bundle.display(),
+        dir.display()
+    );
+    Ok(())
+}
+/// Prints or writes instructions that install the locked tools without frate.
+///
+/// # Arguments
+/// * `format` - The output format.
+/// * `target` - Target triple to export for; defaults to the current platform.
+/// * `output` - File to write to instead of stdout.
+///
+/// # Errors
+/// Returns an error if a tool cannot be resolved for the target, an archive format is not
+/// supported, or the output cannot be written.
+pub fn execute_export(format: ExportFormat, target: Option<String>, output: Option<&Path>) -> Result<()> {
+    let project = Project::load(std::env::current_dir()?)?;
+    let triple = target.unwrap_or_else(current_target_triple);
+    let packages = project.export_packages(&triple)?;
+    let exported = export(&packages, format, &triple)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, exported).with_context(|| format!("cannot write {}", path.display()))?;
+            eprintln!("    {} {} ({} tools)", "Exported".green().bold(), path.display(), packages.len());
+        }
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+/// Changes the version of a dependency in `frate.toml` and locks the new version.
+///
+/// # Arguments
+/// * `name_at_version` - Dependency in the form "name@version".
+///
+/// # Errors
+/// Returns an error if parsing fails, the dependency does not exist, or saving fails.
+pub fn execute_update(name_at_version: String) -> Result<()> {
+    let (name, version) = extract_name_at_version(&name_at_version)?;
+    let mut project = Project::load(std::env::current_dir()?)?;
+    match project.update(&name, &version, &Console)? {
+        Some(locked) => println!("     {} {}@{}", "Updated".green().bold(), name, locked.version),
+        None => println!("     {} {} (not locked)", "Updated".yellow().bold(), name_at_version),
+    }
+    Ok(())
+}
+/// Prints the packages added, removed and changed between two lockfiles.
+///
+/// # Arguments
+/// * `old` - Path to the old lockfile.
+/// * `new` - Path to the new lockfile.
+///
+/// # Errors
+/// Returns an error if a lockfile cannot be read or parsed.
+pub fn execute_lock_diff(old: &Path, new: &Path) -> Result<()> {
+    let load = |path: &Path| FrateLock::load(path).with_context(|| format!("cannot read {}", path.display()));
+    let diff = load(old)?.diff(&load(new)?);
+    if diff.is_empty() {
+        println!("{}", "No changes".yellow());
+        return Ok(());
+    }
+    for package in &diff.added {
+        println!("{} {} {}", "+".green().bold(), package.name.bold(), package.version);
+    }
+    for package in &diff.removed {
+        println!("{} {} {}", "-".red().bold(), package.name.bold(), package.version);
+    }
+    for (old, new) in &diff.changed {
+        if old.version != new.version {
+            println!("{} {} {} -> {}", "~".yellow().bold(), new.name.bold(), old.version, new.version);
+        }
+        else {
+            println!("{} {} {} ({} changed)", "~".yellow().bold(), new.name.bold(), new.version, old.changed_fields(new).join(", "));
+        }
+    }
+    Ok(())
+}
+/// Returns the registry configured for the project in the current directory,
+/// or the default registry if there is no project.
+///
+/// # Errors
+/// Returns an error if an existing `frate.toml` cannot be parsed.
+fn registry_url() -> Result<String> {
+    let cwd = std::env::current_dir()?;
+    if !cwd.join("frate.toml").exists() {
+        return Ok(DEFAULT_REGISTRY.to_string());
+    }
+    Ok(Project::load(cwd)?.settings().registry.clone())
+}
 /// Searches the registry for a tool and lists available versions.
 ///
 /// # Arguments
@@ -339,12 +559,14 @@ pub fn execute_add(name_at_version: String) -> Result<()> {
 /// # Errors
 /// Returns an error if fetching or parsing registry data fails.
 pub fn execute_search(name: String, versions: usize) -> Result<()> {
-    let tool = fetch_registry(&name)?;
+    let fetcher = ReqwestFetcher::new();
+    let tool = fetch_registry(&fetcher, &registry_url()?, &name)?;
     let sorted = sort_versions(tool.releases);
     let filtered = filter_versions(sorted);
     
     println!("{}", name.bold());
-    if let Some(desc) = fetch_description(tool.repo.as_str())? {
+    // The description is cosmetic, so a failing GitHub API request is not an error.
+    if let Ok(Some(desc)) = fetch_description(&fetcher, tool.repo.as_str()) {
         println!("  {}", desc.dimmed());
     }
 
@@ -356,7 +578,7 @@ pub fn execute_search(name: String, versions: usize) -> Result<()> {
     }
     let (latest_version, latest_info) = filtered.last().unwrap();
     println!("  {}", "latest:".bold());
-    println!("      {}", latest_version.split('-').next().unwrap_or(&latest_version).bold().green());
+    println!("      {}", latest_version.split('-').next().unwrap_or(latest_version).bold().green());
     verbose!("          {}", latest_info.url.cyan());
     verbose!("          {}", latest_info.hash.cyan());
 
@@ -365,15 +587,58 @@ pub fn execute_search(name: String, versions: usize) -> Result<()> {
     }
     println!("  {}", "other versions:".bold());
     for (version, info) in filtered[1..versions].iter() {
-        println!("      {}", version.split('-').next().unwrap_or(&version).bold());
+        println!("      {}", version.split('-').next().unwrap_or(version).bold());
         verbose!("          {}", &info.url.cyan());
         verbose!("          {}", &info.hash.cyan());
     }
     Ok(())
 }
 
-pub fn execute_shell() -> Result<()> {
-    run_shell_with_frate_path().with_context(|| "Failed to run shell")
+/// Prints shell code that activates the nearest project, or deactivates it if `unset` is true.
+///
+/// # Arguments
+/// * `shell` - The shell to generate code for.
+/// * `unset` - If true, prints code that restores the `PATH` from before activation.
+///
+/// # Errors
+/// Returns an error if the project cannot be loaded.
+pub fn execute_env(shell: ShellKind, unset: bool) -> Result<()> {
+    if unset {
+        print!("{}", Environment::deactivate(shell));
+        return Ok(());
+    }
+    let project = Project::discover(std::env::current_dir()?)?;
+    print!("{}", project.environment().activate(shell));
+    Ok(())
+}
+/// Prints a shell hook that runs `frate env` whenever the nearest project changes.
+///
+/// # Errors
+/// Returns an error if the shell is not supported or the path of `frate` cannot be determined.
+pub fn execute_hook(shell: ShellKind) -> Result<()> {
+    print!("{}", hook(shell, &std::env::current_exe()?)?);
+    Ok(())
+}
+
+/// Starts a new interactive shell with the environment of the nearest project,
+/// or with `.frate/shims` of the current directory on `PATH` if there is no project.
+///
+/// # Arguments
+/// * `groups` - Only the tools of these groups are put on `PATH`, unless empty.
+///
+/// # Errors
+/// Returns an error if the project cannot be loaded or the shell cannot be started.
+pub fn execute_shell(groups: &[String]) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let environment = match Project::discover(&cwd) {
+        Ok(project) => project.group_environment(groups)?,
+        Err(frate::Error::ManifestNotFound(_)) if groups.is_empty() => Environment {
+            paths: vec![cwd.join(".frate").join("shims")],
+            ..Default::default()
+        },
+        Err(e) => return Err(e.into()),
+    };
+    run_shell(&environment).with_context(|| "Failed to run shell")
 }
 
 pub fn execute_clean(name: Option<String>) -> Result<()> {
@@ -386,21 +651,8 @@ pub fn execute_clean(name: Option<String>) -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct ToolInfo {
-    name: String,
-    repo: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct RegistryIndex {
-    registered: Vec<ToolInfo>,
-}
-
 pub fn execute_registry() -> Result<()> {
-    let url = "https://raw.githubusercontent.com/konni332/frate-registry/refs/heads/master/registry.json";
-    let resp = reqwest::blocking::get(url)?;
-    let registry: RegistryIndex = serde_json::from_reader(resp)?;
+    let registry = fetch_index(&ReqwestFetcher::new(), &registry_url()?)?;
     println!("{}", "Available tools:".bold());
     for tool in &registry.registered {
         println!("  {}", tool.name.bold().green());
@@ -408,3 +660,107 @@ pub fn execute_registry() -> Result<()> {
     }
     Ok(())
 }
+
+/// Checks the health of the project and prints one line per finding.
+///
+/// # Arguments
+/// * `fix` - If true, applies all safe fixes and checks again.
+///
+/// # Errors
+/// Returns an error if the project cannot be loaded, a fix fails,
+/// or problems with error severity remain.
+pub fn execute_doctor(fix: bool) -> Result<()> {
+    let mut project = Project::load(std::env::current_dir()?)?;
+    let mut diagnostics = diagnose(&project);
+    if fix {
+        // Fixes can uncover further problems, e.g. a package is only installable
+        // once it is locked, so fix until nothing changes.
+        let mut applied = Vec::new();
+        loop {
+            let fixes: Vec<_> = plan_fixes(&diagnostics).into_iter()
+                .filter(|fix| !applied.contains(fix))
+                .collect();
+            if fixes.is_empty() {
+                break;
+            }
+            for fix in fixes {
+                apply_fix(&mut project, &fix, &Console)?;
+                applied.push(fix);
+            }
+            diagnostics = diagnose(&project);
+        }
+    }
+    for diagnostic in &diagnostics {
+        print_diagnostic(diagnostic);
+    }
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    if errors > 0 {
+        bail!("{} problem(s) found", errors);
+    }
+    Ok(())
+}
+
+fn print_diagnostic(diagnostic: &Diagnostic) {
+    let symbol = match diagnostic.severity {
+        Severity::Ok => "✔".green().bold(),
+        Severity::Warning => "⚠".yellow().bold(),
+        Severity::Error => "✘".red().bold(),
+    };
+    println!("{} {:<8} {}", symbol, diagnostic.check, diagnostic.message);
+    if let Some(suggestion) = &diagnostic.suggestion {
+        println!("           {}", suggestion.dimmed());
+    }
+}
+
+/// Checks installed files against the digests recorded at install time and
+/// prints modified, missing and unexpected files per package.
+///
+/// # Arguments
+/// * `name` - Optional package name to check.
+/// * `repair` - If true, reinstalls packages that do not match. Packages that are installed
+///   but not locked cannot be reinstalled and are skipped.
+///
+/// # Errors
+/// Returns an error if `name` is neither locked nor installed, checking or reinstalling fails,
+/// or packages do not match and `repair` is false or they are not locked.
+pub fn execute_verify(name: Option<String>, repair: bool) -> Result<()> {
+    let project = Project::load(std::env::current_dir()?)?;
+    let verifications = project.verify(name.as_deref())?;
+    let mut tampered = Vec::new();
+    for verification in &verifications {
+        if !verification.recorded {
+            println!("{} {} {}", "⚠".yellow().bold(), verification.name.bold(), "has no recorded file hashes, reinstall it to record them".yellow());
+            continue;
+        }
+        if verification.is_intact() {
+            println!("{} {}", "✔".green().bold(), verification.name.bold());
+            continue;
+        }
+        println!("{} {}", "✘".red().bold(), verification.name.bold());
+        for path in &verification.modified {
+            println!("    {} {}", "modified:".red(), path);
+        }
+        for path in &verification.missing {
+            println!("     {} {}", "missing:".red(), path);
+        }
+        for path in &verification.unexpected {
+            println!("  {} {}", "unexpected:".yellow(), path);
+        }
+        tampered.push(verification.name.clone());
+    }
+    if tampered.is_empty() {
+        return Ok(());
+    }
+    if !repair {
+        bail!("{} package(s) do not match their install receipt: {}", tampered.len(), tampered.join(", "));
+    }
+    let (locked, unlocked): (Vec<String>, Vec<String>) = tampered.into_iter()
+        .partition(|name| get_locked(name, project.lock()).is_some());
+    for name in &locked {
+        project.install(Some(name), &Console)?;
+    }
+    if !unlocked.is_empty() {
+        bail!("{} package(s) are not in frate.lock and cannot be repaired: {}", unlocked.len(), unlocked.join(", "));
+    }
+    Ok(())
+}
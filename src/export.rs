@@ -0,0 +1,385 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::env::{anchor_path, posix_quote, INSTALL_DIR};
+use crate::error::{Error, Result};
+use crate::lock::LockedPackage;
+use crate::util::format_hash;
+
+/// The install directory of the exported scripts, used when `FRATE_DIR` is not set.
+pub const EXPORT_DIR: &str = "/opt/frate";
+
+/// An output format of [`export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `RUN` and `ENV` instructions to paste into a Dockerfile.
+    Dockerfile,
+    /// A POSIX shell script.
+    Sh,
+    /// Steps to paste into a GitHub Actions job.
+    GithubActions,
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ExportFormat> {
+        match s {
+            "dockerfile" | "docker" => Ok(ExportFormat::Dockerfile),
+            "sh" | "shell" => Ok(ExportFormat::Sh),
+            "github-actions" | "github" => Ok(ExportFormat::GithubActions),
+            _ => Err(Error::Other(format!("unsupported export format: {} (expected dockerfile, sh or github-actions)", s))),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ExportFormat::Dockerfile => "dockerfile",
+            ExportFormat::Sh => "sh",
+            ExportFormat::GithubActions => "github-actions",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Generates instructions that install `packages` without `frate`: each archive is downloaded
+/// from its `source`, checked against its SHA-256 `hash`, and extracted to
+/// `$FRATE_DIR/bin/<name>`. Its executables are linked into `$FRATE_DIR/shims`, which goes on
+/// `PATH` together with the extra directories and variables of the packages.
+///
+/// The instructions need `curl`, `sha256sum`, `tar` and, for `.zip` archives, `unzip`.
+/// `triple` is only mentioned in the header comment.
+///
+/// # Errors
+/// Returns [`Error::UnsupportedArchive`] if a source is neither `.tar.gz` nor `.zip`.
+///
+/// # Example
+///
+/// ```
+/// use frate::{export, ExportFormat, LockedPackage};
+///
+/// let package = LockedPackage {
+///     name: "just".into(),
+///     version: "1.42.0-x86_64-unknown-linux-musl".into(),
+///     source: "https://example.com/just.tar.gz".into(),
+///     hash: "sha256:abc123".into(),
+///     ..Default::default()
+/// };
+/// let script = export(&[package], ExportFormat::Sh, "x86_64-unknown-linux-musl").unwrap();
+/// assert!(script.contains("echo \"abc123  $tmp/just.tar.gz\" | sha256sum -c -"));
+/// ```
+pub fn export(packages: &[LockedPackage], format: ExportFormat, triple: &str) -> Result<String> {
+    let header = format!("# Generated by `frate export --format {}` for {}.", format, triple);
+    let mut out = String::new();
+    match format {
+        ExportFormat::Sh => {
+            out.push_str(&format!("#!/bin/sh\n{}\n", header));
+            out.push_str(&format!("# Installs into $FRATE_DIR (default: {}) and writes $FRATE_DIR/env.sh to source.\n", EXPORT_DIR));
+            out.push_str("set -eu\n");
+            out.push_str(&format!("FRATE_DIR=\"${{FRATE_DIR:-{}}}\"\n", EXPORT_DIR));
+            out.push_str("tmp=\"$(mktemp -d)\"\ntrap 'rm -rf \"$tmp\"' EXIT\n");
+            out.push_str("mkdir -p \"$FRATE_DIR/bin\" \"$FRATE_DIR/shims\"\n");
+            for package in packages {
+                out.push_str(&format!("\n# {} {}\n", package.name, package.version));
+                for command in install_commands(package, "$tmp")? {
+                    out.push_str(&command);
+                    out.push('\n');
+                }
+            }
+            out.push_str("\ncat > \"$FRATE_DIR/env.sh\" <<EOF\n");
+            for (key, value) in variables(packages) {
+                out.push_str(&format!("export {}={}\n", key, sh_word(&value, true, "")));
+            }
+            out.push_str(&format!("export PATH={}\nEOF\n", sh_word(&path(packages), true, ":\\$PATH")));
+        }
+        ExportFormat::Dockerfile => {
+            out.push_str(&format!("{}\n", header));
+            out.push_str(&format!("ENV FRATE_DIR={}\n", EXPORT_DIR));
+            out.push_str("RUN mkdir -p \"$FRATE_DIR/bin\" \"$FRATE_DIR/shims\"\n");
+            for package in packages {
+                out.push_str(&format!("# {} {}\n", package.name, package.version));
+                let commands = install_commands(package, "/tmp")?;
+                out.push_str(&format!("RUN {}\n", commands.join(" \\\n && ")));
+            }
+            for (key, value) in variables(packages) {
+                out.push_str(&format!("ENV {}={}\n", key, dockerfile_word(&value, "")?));
+            }
+            out.push_str(&format!("ENV PATH={}\n", dockerfile_word(&path(packages), ":$PATH")?));
+        }
+        ExportFormat::GithubActions => {
+            out.push_str(&format!("{}\n", header));
+            out.push_str("- name: Set up tools directory\n  shell: bash\n  run: |\n");
+            out.push_str("    echo \"FRATE_DIR=$RUNNER_TEMP/frate\" >> \"$GITHUB_ENV\"\n");
+            out.push_str("    mkdir -p \"$RUNNER_TEMP/frate/bin\" \"$RUNNER_TEMP/frate/shims\"\n");
+            for package in packages {
+                out.push_str(&format!("- name: Install {} {}\n  shell: bash\n  run: |\n", package.name, package.version));
+                for command in install_commands(package, "$RUNNER_TEMP")? {
+                    out.push_str(&format!("    {}\n", command));
+                }
+            }
+            out.push_str("- name: Add tools to PATH\n  shell: bash\n  run: |\n");
+            for (key, mut value) in variables(packages) {
+                value.insert(0, Part::Text(format!("{}=", key)));
+                out.push_str(&format!("    echo {} >> \"$GITHUB_ENV\"\n", single_line(&value, sh_word(&value, false, ""))?));
+            }
+            for dir in path_dirs(packages).iter().rev() {
+                out.push_str(&format!("    echo {} >> \"$GITHUB_PATH\"\n", single_line(dir, sh_word(dir, false, ""))?));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Returns the shell commands that install `package`, downloading its archive to `tmp`.
+///
+/// Every executable of the package is linked into the shims directory under its own name,
+/// except those renamed with `[tools.<name>.bins]`, which are linked under their new name.
+fn install_commands(package: &LockedPackage, tmp: &str) -> Result<Vec<String>> {
+    let (archive, extract) = if package.source.ends_with(".tar.gz") {
+        (format!("{}/{}.tar.gz", tmp, package.name), "tar -xzf")
+    }
+    else if package.source.ends_with(".zip") {
+        (format!("{}/{}.zip", tmp, package.name), "unzip -q -o")
+    }
+    else {
+        return Err(Error::UnsupportedArchive(package.source.clone()));
+    };
+    let dir = format!("$FRATE_DIR/bin/{}", package.name);
+    let target = if extract == "tar -xzf" { "-C" } else { "-d" };
+    let mut commands = vec![
+        format!("curl -fsSL -o \"{}\" {}", archive, posix_quote(&package.source)),
+        format!("echo \"{}  {}\" | sha256sum -c -", format_hash(&package.hash), archive),
+        format!("mkdir -p \"{}\"", dir),
+        format!("{} \"{}\" {} \"{}\"", extract, archive, target, dir),
+        format!("find \"{}\" -type f -perm -u+x -exec ln -sf {{}} \"$FRATE_DIR/shims/\" \\;", dir),
+    ];
+    for (binary, shim) in &package.bins {
+        if binary != shim {
+            commands.push(format!("rm -f \"$FRATE_DIR/shims/\"{}", posix_quote(binary)));
+        }
+        commands.push(format!(
+            "find \"{}\" -type f -perm -u+x -name {} -exec ln -sf {{}} \"$FRATE_DIR/shims/\"{} \\;",
+            dir, posix_quote(binary), posix_quote(shim)
+        ));
+    }
+    commands.push(format!("rm \"{}\"", archive));
+    Ok(commands)
+}
+
+/// A piece of an exported value: literal text, or the install directory `$FRATE_DIR`,
+/// which is the only thing the generated instructions expand.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    FrateDir,
+}
+
+/// Splits `template` into parts, with `{install_dir}` standing for `$FRATE_DIR/bin/<name>`.
+fn parts(package: &LockedPackage, template: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    for (i, text) in template.split(INSTALL_DIR).enumerate() {
+        if i > 0 {
+            parts.push(Part::FrateDir);
+            parts.push(Part::Text(format!("/bin/{}", package.name)));
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text.to_string()));
+        }
+    }
+    parts
+}
+
+/// Returns the variables of all packages.
+fn variables(packages: &[LockedPackage]) -> Vec<(String, Vec<Part>)> {
+    packages.iter()
+        .flat_map(|package| package.env.iter().map(|(key, value)| (key.clone(), parts(package, value))))
+        .collect()
+}
+
+/// Returns the shims directory and the extra `PATH` directories of all packages.
+fn path_dirs(packages: &[LockedPackage]) -> Vec<Vec<Part>> {
+    let mut dirs = vec![vec![Part::FrateDir, Part::Text("/shims".to_string())]];
+    for package in packages {
+        dirs.extend(package.paths.iter().map(|dir| parts(package, &anchor_path(dir))));
+    }
+    dirs
+}
+
+/// Returns the [directories](path_dirs) joined by `:`.
+fn path(packages: &[LockedPackage]) -> Vec<Part> {
+    let mut path = Vec::new();
+    for (i, dir) in path_dirs(packages).into_iter().enumerate() {
+        if i > 0 {
+            path.push(Part::Text(":".to_string()));
+        }
+        path.extend(dir);
+    }
+    path
+}
+
+/// Returns true if `text` means the same unquoted in a heredoc and inside double quotes
+/// in a shell or a Dockerfile.
+fn is_plain(text: &str) -> bool {
+    text.chars().all(|c| c.is_ascii_alphanumeric() || " _./:@%+,-=~".contains(c))
+}
+
+/// Quotes `parts` followed by the unescaped `suffix` as one shell word. With `heredoc`, the
+/// word is written into an unquoted heredoc, which expands `$FRATE_DIR` once more, so the text
+/// is escaped for that as well.
+fn sh_word(parts: &[Part], heredoc: bool, suffix: &str) -> String {
+    if parts.iter().all(|part| matches!(part, Part::FrateDir) || matches!(part, Part::Text(text) if is_plain(text))) {
+        let word: String = parts.iter()
+            .map(|part| match part {
+                Part::Text(text) => text.as_str(),
+                Part::FrateDir => "$FRATE_DIR",
+            })
+            .collect();
+        return format!("\"{}{}\"", word, suffix);
+    }
+    let mut word = String::new();
+    let mut text = String::new();
+    let quote = |text: &mut String, word: &mut String| {
+        if !text.is_empty() {
+            let quoted = posix_quote(text);
+            if heredoc {
+                word.push_str(&quoted.replace('\\', "\\\\").replace('$', "\\$").replace('`', "\\`"));
+            } else {
+                word.push_str(&quoted);
+            }
+            text.clear();
+        }
+    };
+    for part in parts {
+        match part {
+            Part::Text(part) => text.push_str(part),
+            Part::FrateDir => {
+                quote(&mut text, &mut word);
+                word.push_str("\"$FRATE_DIR\"");
+            }
+        }
+    }
+    quote(&mut text, &mut word);
+    if !suffix.is_empty() {
+        word.push_str(&format!("\"{}\"", suffix));
+    }
+    word
+}
+
+/// Quotes `parts` followed by the unescaped `suffix` as a double-quoted Dockerfile word.
+///
+/// # Errors
+/// Returns an error if the text contains a line break, which a Dockerfile cannot quote.
+fn dockerfile_word(parts: &[Part], suffix: &str) -> Result<String> {
+    let mut word = String::from("\"");
+    for part in parts {
+        match part {
+            Part::Text(text) => word.push_str(&text.replace('\\', "\\\\").replace('"', "\\\"").replace('$', "\\$")),
+            Part::FrateDir => word.push_str("$FRATE_DIR"),
+        }
+    }
+    word.push_str(suffix);
+    word.push('"');
+    single_line(parts, word)
+}
+
+/// Returns `word` if no text of `parts` contains a line break, which would start a new entry
+/// in a Dockerfile, `$GITHUB_ENV` or `$GITHUB_PATH`.
+///
+/// # Errors
+/// Returns an error naming the text with the line break.
+fn single_line(parts: &[Part], word: String) -> Result<String> {
+    match parts.iter().find(|part| matches!(part, Part::Text(text) if text.contains(['\n', '\r']))) {
+        Some(Part::Text(text)) => Err(Error::Other(format!("cannot export a value with a line break: {:?}", text))),
+        _ => Ok(word),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, source: &str) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: "1.0.0-x86_64-unknown-linux-gnu".to_string(),
+            source: source.to_string(),
+            hash: "sha256:0123".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let mut go = package("go", "https://example.com/go.tar.gz");
+        go.env.insert("GOROOT".to_string(), "{install_dir}/go".to_string());
+        go.paths.push("{install_dir}/go/bin".to_string());
+        let packages = [go, package("zip-tool", "https://example.com/zip-tool.zip")];
+
+        let sh = export(&packages, ExportFormat::Sh, "x86_64-unknown-linux-gnu").unwrap();
+        assert!(sh.starts_with("#!/bin/sh\n# Generated by `frate export --format sh` for x86_64-unknown-linux-gnu."));
+        assert!(sh.contains("tar -xzf \"$tmp/go.tar.gz\" -C \"$FRATE_DIR/bin/go\"\n"));
+        assert!(sh.contains("unzip -q -o \"$tmp/zip-tool.zip\" -d \"$FRATE_DIR/bin/zip-tool\"\n"));
+        assert!(sh.contains("export GOROOT=\"$FRATE_DIR/bin/go/go\"\nexport PATH=\"$FRATE_DIR/shims:$FRATE_DIR/bin/go/go/bin:\\$PATH\"\n"));
+
+        let dockerfile = export(&packages, ExportFormat::Dockerfile, "x86_64-unknown-linux-gnu").unwrap();
+        assert!(dockerfile.contains("RUN curl -fsSL -o \"/tmp/go.tar.gz\" 'https://example.com/go.tar.gz' \\\n && echo \"0123  /tmp/go.tar.gz\" | sha256sum -c - \\\n"));
+        assert!(dockerfile.ends_with("ENV PATH=\"$FRATE_DIR/shims:$FRATE_DIR/bin/go/go/bin:$PATH\"\n"));
+
+        let actions = export(&packages, ExportFormat::GithubActions, "x86_64-unknown-linux-gnu").unwrap();
+        assert!(actions.contains("- name: Install zip-tool 1.0.0-x86_64-unknown-linux-gnu\n  shell: bash\n  run: |\n    curl"));
+        assert!(actions.ends_with("    echo \"$FRATE_DIR/bin/go/go/bin\" >> \"$GITHUB_PATH\"\n    echo \"$FRATE_DIR/shims\" >> \"$GITHUB_PATH\"\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_export_escapes_values() {
+        let value = "say \"hi\" $(touch pwned) `id` 'q' \\ {install_dir}/lib";
+        let mut tool = package("tool", "https://example.com/tool.tar.gz");
+        tool.env.insert("TOOL_OPTS".to_string(), value.to_string());
+        let packages = [tool];
+
+        // Run only the part of the script that writes env.sh, then source it.
+        let sh = export(&packages, ExportFormat::Sh, "x86_64-unknown-linux-gnu").unwrap();
+        let env_sh = &sh[sh.find("cat > ").unwrap()..];
+        let dir = tempfile::tempdir().unwrap();
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{}. \"$FRATE_DIR/env.sh\"\nprintf '%s' \"$TOOL_OPTS\"", env_sh))
+            .env("FRATE_DIR", dir.path())
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        let expected = value.replace("{install_dir}", &format!("{}/bin/tool", dir.path().display()));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+        assert!(!dir.path().join("pwned").exists());
+
+        let dockerfile = export(&packages, ExportFormat::Dockerfile, "x86_64-unknown-linux-gnu").unwrap();
+        assert!(dockerfile.contains("ENV TOOL_OPTS=\"say \\\"hi\\\" \\$(touch pwned) `id` 'q' \\\\ $FRATE_DIR/bin/tool/lib\"\n"), "{}", dockerfile);
+
+        let actions = export(&packages, ExportFormat::GithubActions, "x86_64-unknown-linux-gnu").unwrap();
+        assert!(actions.contains("    echo 'TOOL_OPTS=say \"hi\" $(touch pwned) `id` '\\''q'\\'' \\ '\"$FRATE_DIR\"'/bin/tool/lib' >> \"$GITHUB_ENV\"\n"), "{}", actions);
+
+        let mut multiline = package("tool", "https://example.com/tool.tar.gz");
+        multiline.env.insert("TOOL_OPTS".to_string(), "a\nRUN evil".to_string());
+        assert!(export(&[multiline], ExportFormat::Dockerfile, "x86_64-unknown-linux-gnu").is_err());
+    }
+
+    #[test]
+    fn test_export_renamed_bins() {
+        let mut python = package("python", "https://example.com/python.tar.gz");
+        python.bins.insert("python3".to_string(), "python".to_string());
+        let sh = export(&[python], ExportFormat::Sh, "x86_64-unknown-linux-gnu").unwrap();
+        assert!(sh.contains(concat!(
+            "rm -f \"$FRATE_DIR/shims/\"'python3'\n",
+            "find \"$FRATE_DIR/bin/python\" -type f -perm -u+x -name 'python3' -exec ln -sf {} \"$FRATE_DIR/shims/\"'python' \\;\n",
+        )), "{}", sh);
+    }
+
+    #[test]
+    fn test_export_unsupported_archive() {
+        let packages = [package("tool", "https://example.com/tool.tar.xz")];
+        assert!(matches!(export(&packages, ExportFormat::Sh, "x86_64-unknown-linux-gnu"), Err(Error::UnsupportedArchive(_))));
+        assert!("yaml".parse::<ExportFormat>().is_err());
+    }
+}
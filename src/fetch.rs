@@ -0,0 +1,113 @@
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use crate::error::{Error, Result};
+
+/// Performs all network access of the library.
+///
+/// Registries, release archives and repository metadata are fetched through this trait,
+/// so callers can plug in a caching or offline implementation, an in-memory fake for tests,
+/// or a client configured for a corporate proxy.
+///
+/// # Example
+///
+/// A fetcher that serves archives from a local mirror directory by file name:
+///
+/// ```
+/// use std::io::ErrorKind;
+/// use std::path::PathBuf;
+/// use frate::fetch::Fetcher;
+///
+/// struct LocalMirror(PathBuf);
+///
+/// impl Fetcher for LocalMirror {
+///     fn fetch(&self, url: &str) -> frate::error::Result<Option<Vec<u8>>> {
+///         let name = url.rsplit('/').next().unwrap_or_default();
+///         match std::fs::read(self.0.join(name)) {
+///             Ok(bytes) => Ok(Some(bytes)),
+///             Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+///             Err(e) => Err(e.into()),
+///         }
+///     }
+/// }
+/// ```
+pub trait Fetcher {
+    /// Fetches the resource at `url` and returns its body.
+    ///
+    /// Returns `Ok(None)` if the resource does not exist (e.g. HTTP 404).
+    ///
+    /// # Errors
+    /// Returns an error if the resource exists but cannot be fetched.
+    fn fetch(&self, url: &str) -> Result<Option<Vec<u8>>>;
+}
+
+/// The default [`Fetcher`], backed by a blocking `reqwest` client.
+///
+/// Proxies configured through the usual `HTTP_PROXY`/`HTTPS_PROXY` environment variables
+/// are honoured. Use [`ReqwestFetcher::with_client`] for custom TLS or proxy settings.
+#[derive(Debug, Clone)]
+pub struct ReqwestFetcher {
+    client: Client,
+}
+
+impl ReqwestFetcher {
+    /// Creates a fetcher with a default client identifying itself as `frate`.
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .user_agent("frate")
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+    /// Creates a fetcher using the given client.
+    pub fn with_client(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Default for ReqwestFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fetcher for ReqwestFetcher {
+    fn fetch(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.client.get(url).send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(Error::Network(format!("failed to fetch {}: {}", url, response.status())));
+        }
+        Ok(Some(response.bytes()?.to_vec()))
+    }
+}
+
+/// A [`Fetcher`] for hosts without network access: every fetch fails with [`Error::Network`].
+///
+/// Used where all archives must come from the cache, e.g. when installing a [bundle](crate::bundle).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Offline;
+
+impl Fetcher for Offline {
+    fn fetch(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        Err(Error::Network(format!("cannot fetch {} without network access", url)))
+    }
+}
+
+/// Fetchers for unit tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::collections::HashMap;
+    use super::Fetcher;
+    use crate::error::Result;
+
+    /// Serves the bodies in its map by URL; every other URL is not found.
+    pub(crate) struct InMemory(pub(crate) HashMap<String, Vec<u8>>);
+
+    impl Fetcher for InMemory {
+        fn fetch(&self, url: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.get(url).cloned())
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::path::PathBuf;
 use crate::global::utils::get_global_cache_dir;
-use anyhow::{anyhow, bail, Context, Result};
+use crate::report::{Event, Reporter};
+use crate::error::{Error, Result};
 use walkdir::WalkDir;
 
 pub fn get_cached_archive(url: &str) -> Result<Option<PathBuf>> {
     let cache_dir = get_global_cache_dir()?;
-    let file_name = url.split('/').next_back().ok_or(anyhow!("Could not determine archive name"))?;
+    let file_name = archive_name(url)?;
     let archive_path = cache_dir.join(file_name);
     if archive_path.exists() {
         Ok(Some(archive_path))
@@ -14,17 +15,13 @@ pub fn get_cached_archive(url: &str) -> Result<Option<PathBuf>> {
         Ok(None)
     }
 }
-pub fn cache_archive(url: &str, bytes: &[u8]) -> Result<()>{
+pub fn cache_archive(url: &str, bytes: &[u8], reporter: &dyn Reporter) -> Result<()>{
     let cache_dir = get_global_cache_dir()?;
     if !cache_dir.exists() {
-        std::fs::create_dir_all(&cache_dir)
-            .with_context(|| format!("Could not create cache dir {:?}", cache_dir))?;
-        println!("Cache directory created: {}", cache_dir.display());
+        std::fs::create_dir_all(&cache_dir)?;
+        reporter.report(Event::CacheCreated { path: cache_dir.clone() });
     }
-    let file_name = url.split('/').next_back().ok_or(anyhow!("Could not determine archive name"))?;
-    let path = cache_dir.join(file_name);
-    std::fs::File::create(&path)
-        .with_context(|| format!("Could not create cache file {:?}", path))?;
+    let path = cache_dir.join(archive_name(url)?);
     std::fs::write(&path, bytes)?;
     Ok(())
 }
@@ -41,7 +38,7 @@ pub fn clean_cache() -> Result<()> {
 pub fn remove_cached_archive(name: &str) -> Result<()> {
     let cache_dir = get_global_cache_dir()?;
     if !cache_dir.exists() {
-        bail!("Cache directory does not exist");
+        return Err(Error::Other("Cache directory does not exist".to_string()));
     }
     let dir = WalkDir::new(&cache_dir);
     for entry in dir {
@@ -68,4 +65,11 @@ pub fn is_cached(full_name: &str) -> Result<bool> {
         }
     }
     Ok(false)
-}
\ No newline at end of file
+}
+
+/// Returns the file name of an archive URL, which is used as its name in the cache.
+pub(crate) fn archive_name(url: &str) -> Result<&str> {
+    url.split('/').next_back()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| Error::Other(format!("Could not determine archive name: {}", url)))
+}
@@ -1,5 +1,5 @@
 use std::path::PathBuf;
-use anyhow::{anyhow, Result};
+use crate::error::{Error, Result};
 use directories::ProjectDirs;
 
 pub fn get_global_config_dir() -> Result<PathBuf> {
@@ -7,7 +7,13 @@ pub fn get_global_config_dir() -> Result<PathBuf> {
     Ok(config_dir)
 }
 
+/// Returns the global cache directory for downloaded archives.
+///
+/// Can be overridden with the `FRATE_CACHE_DIR` environment variable.
 pub fn get_global_cache_dir() -> Result<PathBuf> {
+    if let Some(cache_dir) = std::env::var_os("FRATE_CACHE_DIR") {
+        return Ok(PathBuf::from(cache_dir));
+    }
     let (_, cache_dir, _) = get_global_dirs()?;
     Ok(cache_dir)
 }
@@ -19,7 +25,7 @@ pub fn get_global_data_dir() -> Result<PathBuf> {
 
 pub fn get_global_dirs() -> Result<(PathBuf, PathBuf, PathBuf)> {
     let proj_dirs = ProjectDirs::from("org", "frate", "frate")
-        .ok_or_else(|| anyhow!("Could not get project directories"))?;
+        .ok_or_else(|| Error::Other("Could not get project directories".to_string()))?;
 
     let config_dir = proj_dirs.config_dir().to_path_buf();
     let cache_dir = proj_dirs.cache_dir().to_path_buf();
@@ -0,0 +1,245 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use yaml_rust2::{Yaml, YamlLoader};
+use crate::error::{Error, Result};
+use crate::registry::RegistryIndex;
+
+/// The files `frate import` reads, in the order they are looked for.
+pub const IMPORT_FILES: [&str; 5] = [".tool-versions", "mise.toml", ".mise.toml", "aqua.yaml", "aqua.yml"];
+
+/// Tool names of asdf and mise that differ from the name of the tool in the frate registry.
+pub const RENAMES: [(&str, &str); 6] = [
+    ("golang", "go"),
+    ("nodejs", "node"),
+    ("github-cli", "gh"),
+    ("rg", "ripgrep"),
+    ("fd-find", "fd"),
+    ("cargo-just", "just"),
+];
+
+/// A tool version pinned in the config file of another version manager.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolPin {
+    /// The tool as named in the file, e.g. `nodejs` or `BurntSushi/ripgrep`.
+    pub tool: String,
+    /// The pinned version, without a leading `v`.
+    pub version: String,
+}
+
+impl Display for ToolPin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.tool, self.version)
+    }
+}
+
+/// A pin that [`Project::import`](crate::Project::import) did not add to `frate.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedPin {
+    /// The pin as read from the file.
+    pub pin: ToolPin,
+    /// Why it was not added.
+    pub reason: String,
+}
+
+/// The outcome of [`Project::import`](crate::Project::import).
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportReport {
+    /// Dependencies added to `frate.toml`, as `(name, version)`.
+    pub added: Vec<(String, String)>,
+    /// Pins that could not be mapped or added.
+    pub skipped: Vec<SkippedPin>,
+}
+
+/// Returns the [import files](IMPORT_FILES) that exist in `dir`.
+pub fn find_import_files(dir: &Path) -> Vec<PathBuf> {
+    IMPORT_FILES.iter()
+        .map(|file| dir.join(file))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Reads the tool pins of an asdf `.tool-versions`, a `mise.toml` or an `aqua.yaml`,
+/// choosing the format by the file name.
+///
+/// # Errors
+/// Returns an error if the file name is not one of [`IMPORT_FILES`], or the file cannot be
+/// read or parsed.
+pub fn read_pins(path: &Path) -> Result<Vec<ToolPin>> {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let content = fs::read_to_string(path)?;
+    match file_name {
+        ".tool-versions" => Ok(parse_tool_versions(&content)),
+        "mise.toml" | ".mise.toml" => parse_mise(&content),
+        "aqua.yaml" | "aqua.yml" => parse_aqua(&content),
+        _ => Err(Error::Other(format!("cannot import {}: expected one of {}", path.display(), IMPORT_FILES.join(", ")))),
+    }
+}
+
+/// Parses an asdf `.tool-versions` file. Only the first version of each tool is used.
+///
+/// # Example
+///
+/// ```
+/// use frate::parse_tool_versions;
+///
+/// let pins = parse_tool_versions("nodejs 20.11.0 18.19.0 # LTS\njust 1.42.1\n");
+/// assert_eq!(pins[0].tool, "nodejs");
+/// assert_eq!(pins[0].version, "20.11.0");
+/// ```
+pub fn parse_tool_versions(content: &str) -> Vec<ToolPin> {
+    content.lines()
+        .filter_map(|line| {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let tool = fields.next()?;
+            let version = fields.next()?;
+            Some(pin(tool, version))
+        })
+        .collect()
+}
+
+/// Parses the `[tools]` table of a `mise.toml`. A tool's version is a string, a list whose
+/// first entry is used, or a table with a `version` key. Any other entry is returned with the
+/// entry itself as version, so that [`Project::import`](crate::Project::import) skips it.
+///
+/// # Errors
+/// Returns [`Error::Parse`] if the file is not valid TOML.
+pub fn parse_mise(content: &str) -> Result<Vec<ToolPin>> {
+    #[derive(Deserialize)]
+    struct Mise {
+        #[serde(default)]
+        tools: toml::Table,
+    }
+    let mise: Mise = toml::from_str(content)?;
+    let mut pins = Vec::new();
+    for (tool, value) in &mise.tools {
+        let version = match value {
+            toml::Value::String(version) => Some(version.as_str()),
+            toml::Value::Array(versions) => versions.first().and_then(toml::Value::as_str),
+            toml::Value::Table(table) => table.get("version").and_then(toml::Value::as_str),
+            _ => None,
+        };
+        match version {
+            Some(version) => pins.push(pin(tool, version)),
+            None => pins.push(ToolPin { tool: tool.clone(), version: value.to_string() }),
+        }
+    }
+    Ok(pins)
+}
+
+/// Parses the `packages` of an `aqua.yaml`. A package is named `<owner>/<repo>@<version>`,
+/// or has the version in a separate `version` key.
+///
+/// # Errors
+/// Returns [`Error::Parse`] if the file is not valid YAML, or a package has no name or version.
+pub fn parse_aqua(content: &str) -> Result<Vec<ToolPin>> {
+    let documents = YamlLoader::load_from_str(content).map_err(|e| Error::Parse(e.to_string()))?;
+    let packages = match documents.first().map(|document| &document["packages"]) {
+        Some(Yaml::Array(packages)) => packages.as_slice(),
+        None | Some(Yaml::BadValue | Yaml::Null) => &[],
+        Some(_) => return Err(Error::Parse("packages in aqua.yaml is not a list".to_string())),
+    };
+    packages.iter()
+        .map(|package| {
+            let name = package["name"].as_str()
+                .ok_or_else(|| Error::Parse("a package in aqua.yaml has no name".to_string()))?;
+            let (tool, version) = match (name.split_once('@'), package["version"].as_str()) {
+                (Some((tool, version)), _) => (tool, version),
+                (None, Some(version)) => (name, version),
+                (None, None) => return Err(Error::Parse(format!("package {} in aqua.yaml has no version", name))),
+            };
+            Ok(pin(tool, version))
+        })
+        .collect()
+}
+
+/// Returns the name of the registry entry for `tool`, or `None` if the registry has none.
+///
+/// A mise backend prefix such as `aqua:` is ignored. [`RENAMES`] is consulted first; then
+/// `tool` is looked up by name, and a tool named `<owner>/<repo>` by the repository of
+/// the registry entries.
+///
+/// # Example
+///
+/// ```
+/// use frate::{map_tool_name, IndexEntry, RegistryIndex};
+///
+/// let entry = |name: &str, repo: &str| IndexEntry { name: name.into(), repo: repo.into() };
+/// let index = RegistryIndex { registered: vec![entry("ripgrep", "https://github.com/BurntSushi/ripgrep")] };
+/// assert_eq!(map_tool_name("rg", &index).as_deref(), Some("ripgrep"));
+/// assert_eq!(map_tool_name("aqua:BurntSushi/ripgrep", &index).as_deref(), Some("ripgrep"));
+/// assert_eq!(map_tool_name("bat", &index), None);
+/// ```
+pub fn map_tool_name(tool: &str, index: &RegistryIndex) -> Option<String> {
+    let tool = tool.split_once(':').map_or(tool, |(_, tool)| tool);
+    let name = RENAMES.iter()
+        .find(|(from, _)| *from == tool)
+        .map_or(tool, |(_, to)| *to);
+    if let Some(entry) = index.registered.iter().find(|entry| entry.name == name) {
+        return Some(entry.name.clone());
+    }
+    if !tool.contains('/') {
+        return None;
+    }
+    let suffix = format!("/{}", tool.to_lowercase());
+    index.registered.iter()
+        .find(|entry| entry.repo.trim_end_matches('/').to_lowercase().ends_with(&suffix))
+        .map(|entry| entry.name.clone())
+}
+
+fn pin(tool: &str, version: &str) -> ToolPin {
+    ToolPin { tool: tool.to_string(), version: version.strip_prefix('v').unwrap_or(version).to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::IndexEntry;
+
+    fn index() -> RegistryIndex {
+        let entry = |name: &str, repo: &str| IndexEntry { name: name.to_string(), repo: repo.to_string() };
+        RegistryIndex { registered: vec![
+            entry("go", "https://github.com/golang/go"),
+            entry("ripgrep", "https://github.com/BurntSushi/ripgrep"),
+            entry("just", "https://github.com/casey/just/"),
+        ] }
+    }
+
+    #[test]
+    fn test_parse_tool_versions() {
+        let pins = parse_tool_versions("# tools\ngolang 1.22.1\n\nripgrep v14.1.1 13.0.0 # fast grep\nnodejs\n");
+        assert_eq!(pins, [pin("golang", "1.22.1"), pin("ripgrep", "14.1.1")]);
+    }
+
+    #[test]
+    fn test_parse_mise() {
+        let content = "[env]\nA = \"1\"\n\n[tools]\njust = \"1.42.1\"\nnode = [\"20.11.0\", \"18\"]\n\"aqua:BurntSushi/ripgrep\" = { version = \"14.1.1\" }\n";
+        let pins = parse_mise(content).unwrap();
+        assert_eq!(pins, [pin("aqua:BurntSushi/ripgrep", "14.1.1"), pin("just", "1.42.1"), pin("node", "20.11.0")]);
+        let pins = parse_mise("[tools]\njust = 1\npython = { path = \"./venv\" }\n").unwrap();
+        assert_eq!(pins, [pin("just", "1"), pin("python", "{ path = \"./venv\" }")]);
+        assert!(matches!(parse_mise("[tools\n"), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_aqua() {
+        let content = "registries:\n- type: standard\n  ref: v4.0.0\npackages:\n- name: BurntSushi/ripgrep@v14.1.1\n- name: casey/just\n  version: 1.42.1\n";
+        let pins = parse_aqua(content).unwrap();
+        assert_eq!(pins, [pin("BurntSushi/ripgrep", "14.1.1"), pin("casey/just", "1.42.1")]);
+        assert!(matches!(parse_aqua("packages:\n- name: casey/just\n"), Err(Error::Parse(_))));
+        assert!(matches!(parse_aqua("packages: [\n"), Err(Error::Parse(_))));
+        assert_eq!(parse_aqua("registries: []\n").unwrap(), []);
+    }
+
+    #[test]
+    fn test_map_tool_name() {
+        let index = index();
+        assert_eq!(map_tool_name("golang", &index).as_deref(), Some("go"));
+        assert_eq!(map_tool_name("burntsushi/ripgrep", &index).as_deref(), Some("ripgrep"));
+        assert_eq!(map_tool_name("ubi:casey/just", &index).as_deref(), Some("just"));
+        assert_eq!(map_tool_name("someone/just", &index), None);
+        assert_eq!(map_tool_name("python", &index), None);
+    }
+}
@@ -1,14 +1,18 @@
 use std::io::{Cursor};
-use std::path::{Path};
+use std::path::{Path, PathBuf};
+use crate::fetch::Fetcher;
 use crate::lock::{FrateLock, LockedPackage};
-use crate::shims::create_shim;
-use crate::util::{ensure_frate_dirs, get_frate_dir};
-use anyhow::{anyhow, bail, Result};
-use colored::Colorize;
+use crate::receipt::Receipt;
+use crate::report::{Event, Reporter};
+use crate::env::Environment;
+use crate::settings::ShimMode;
+use crate::shims::{create_binary_shim, create_shim_with_env, find_shim_binary, remove_shim, ShimConfig};
+use crate::util::ensure_frate_dirs;
+use crate::error::{Error, Result};
 use sha2::Digest;
-use verbosio::verbose;
-use crate::{get_binary, is_cached};
+use crate::{find_package_binary, get_binary, is_cached};
 use crate::global::cache::{cache_archive, get_cached_archive};
+use crate::vendor::vendored_archive;
 
 /// Installs all packages listed in the lockfile by downloading and extracting them
 /// and creating executable shims in the `.frate/shims` directory.
@@ -17,24 +21,44 @@ use crate::global::cache::{cache_archive, get_cached_archive};
 ///
 /// * `lock` - Reference to the parsed `frate.lock` file containing resolved packages.
 /// * `project_root` - Path to the root of the project where the `.frate` directory resides.
+/// * `shim_mode` - How shims are created.
+/// * `vendor_dir` - Directory of [vendored](crate::vendor) archives to use first, if any.
+/// * `fetcher` - Used to download archives that are neither vendored nor cached.
+/// * `reporter` - Receives progress events.
 ///
 /// # Errors
 ///
 /// Returns an error if any package fails to download, extract, or install properly.
-pub fn install_packages<P: AsRef<Path>>(lock: &FrateLock, project_root: P) -> Result<()> {
+pub fn install_packages<P: AsRef<Path>>(
+    lock: &FrateLock,
+    project_root: P,
+    shim_mode: ShimMode,
+    vendor_dir: Option<&Path>,
+    fetcher: &dyn Fetcher,
+    reporter: &dyn Reporter
+) -> Result<()> {
     let frate_dir = ensure_frate_dirs(project_root)?;
     for package in &lock.packages {
-        install_package(package, &frate_dir)?;
+        install_package(package, &frate_dir, shim_mode, vendor_dir, fetcher, reporter)?;
     }
     Ok(())
 }
 /// Installs a single package by downloading and extracting it into `.frate/bin/{name}`,
-/// and creating a shim in `.frate/shims/{name}` pointing to the main binary.
+/// creating shims in `.frate/shims` for the main binary and for every binary in [`LockedPackage::bins`],
+/// which also set the package's environment variables and `PATH` directories if it declares any,
+/// and writing a [`Receipt`] with the digests of all installed files to `.frate/receipts/{name}.toml`.
+/// A previous installation of the package is replaced once the new archive is verified and
+/// extracted; if that fails, it is left untouched.
 ///
 /// # Arguments
 ///
 /// * `package` - The locked package to install.
 /// * `frate_dir` - Path to the `.frate` directory.
+/// * `shim_mode` - How the shim is created.
+/// * `vendor_dir` - Directory of [vendored](crate::vendor) archives. An archive found there is
+///   used before the global cache and the network, after checking it against the lock hash.
+/// * `fetcher` - Used to download the archive if it is neither vendored nor cached.
+/// * `reporter` - Receives progress events.
 ///
 /// # Errors
 ///
@@ -45,67 +69,139 @@ pub fn install_packages<P: AsRef<Path>>(lock: &FrateLock, project_root: P) -> Re
 ///
 /// ```no_run
 /// use std::path::PathBuf;
-/// use frate::{install_package, LockedPackage};
+/// use frate::{install_package, LockedPackage, ReqwestFetcher, ShimMode, Silent};
 ///
 /// let package = LockedPackage {
 ///     name: "example".to_string(),
 ///     version: "0.1.0".to_string(),
 ///     source: "https://example.com/example.zip".to_string(),
 ///     hash: "sha256:abc123...".to_string(),
+///     ..Default::default()
 /// };
 /// let frate_dir = PathBuf::from(".frate");
-/// install_package(&package, &frate_dir).unwrap();
+/// install_package(&package, &frate_dir, ShimMode::Symlink, None, &ReqwestFetcher::new(), &Silent).unwrap();
 /// ```
-pub fn install_package(package: &LockedPackage, frate_dir: &Path) -> Result<()> {
+pub fn install_package(
+    package: &LockedPackage,
+    frate_dir: &Path,
+    shim_mode: ShimMode,
+    vendor_dir: Option<&Path>,
+    fetcher: &dyn Fetcher,
+    reporter: &dyn Reporter
+) -> Result<()> {
     let bin_dir = frate_dir.join("bin");
     let shims_dir = frate_dir.join("shims");
     // install
     let url = &package.source;
     let dest_dir = bin_dir.join(&package.name);
-    std::fs::create_dir_all(&dest_dir)?;
-    if let Some(cached_path) = get_cached_archive(&package.source)? {
-        extract_cached(cached_path, dest_dir, &package.hash)?;
+    // Extract into an empty sibling directory first, so a failed download or hash check leaves
+    // a previous install intact, and files of a previous install are not left behind. The name
+    // is unique, so that concurrent installs of the same package, e.g. lazy installs by two
+    // shims, do not extract into each other's files.
+    std::fs::create_dir_all(&bin_dir)?;
+    let staging = tempfile::Builder::new()
+        .prefix(&format!(".{}.", package.name))
+        .suffix(".partial")
+        .tempdir_in(&bin_dir)?;
+    let staging_dir = staging.path().to_path_buf();
+    if let Some(vendored_path) = vendor_dir.and_then(|dir| vendored_archive(dir, package)) {
+        extract_cached(vendored_path, staging_dir.clone(), &package.hash, reporter)?;
+    }
+    else if let Some(cached_path) = get_cached_archive(&package.source)? {
+        extract_cached(cached_path, staging_dir.clone(), &package.hash, reporter)?;
     }
     else {
-        download_and_extract(url, &dest_dir.to_string_lossy(), &package.hash)?;
-    }
-    // create shim
-    let target_path = get_binary(&package.name)?
-        .ok_or(anyhow!("Binary not found: {}", package.name))?;
-    let shim_path = shims_dir.join(
-        target_path
-            .file_stem()
-            .ok_or_else(|| anyhow!("Invalid file name: {}", target_path.display()))?
-    );
-
-    create_shim(target_path, shim_path)?;
-    println!("   {} {}", "Installed".bold().green(), package.name);
+        download_and_extract(fetcher, url, &staging_dir.to_string_lossy(), &package.hash, reporter)?;
+    }
+    if dest_dir.exists() {
+        std::fs::remove_dir_all(&dest_dir)?;
+    }
+    std::fs::rename(&staging_dir, &dest_dir)?;
+    // create shims
+    let shims = package_shims(frate_dir, package)?;
+    let previous = Receipt::load(frate_dir, &package.name).ok().flatten();
+    for shim in previous.map(|receipt| receipt.shims).unwrap_or_default() {
+        if !shims.iter().any(|(name, _)| *name == shim) {
+            remove_shim(shims_dir.join(shim))?;
+        }
+    }
+    for (name, target_path) in &shims {
+        let shim_path = shims_dir.join(name);
+        match shim_mode {
+            ShimMode::Symlink => {
+                let environment = Environment::for_packages(frate_dir, std::slice::from_ref(package));
+                create_shim_with_env(target_path, &shim_path, &environment)?;
+            }
+            ShimMode::Binary => {
+                let config = ShimConfig::for_package(package, frate_dir, target_path);
+                create_binary_shim(&find_shim_binary()?, &shim_path, &config)?;
+            }
+        }
+    }
+    let mut receipt = Receipt::for_package(package).with_files(&dest_dir)?;
+    receipt.shims = shims.into_iter().map(|(name, _)| name).collect();
+    receipt.save(frate_dir)?;
+    reporter.report(Event::Installed { name: package.name.clone() });
     Ok(())
 }
+/// Returns the shims of an extracted package as pairs of shim name and binary: one for the
+/// main binary, named after it, and one for every entry of [`LockedPackage::bins`].
+///
+/// # Errors
+/// Returns [`Error::BinaryNotFound`] if the main binary or a binary listed in `bins` does not exist.
+fn package_shims(frate_dir: &Path, package: &LockedPackage) -> Result<Vec<(String, PathBuf)>> {
+    let main = get_binary(frate_dir, &package.name)?
+        .ok_or_else(|| Error::BinaryNotFound(package.name.clone()))?;
+    let main_stem = main
+        .file_stem()
+        .ok_or_else(|| Error::BinaryNotFound(main.display().to_string()))?
+        .to_string_lossy()
+        .into_owned();
+    let mut shims = Vec::new();
+    if !package.bins.contains_key(&main_stem) {
+        shims.push((main_stem, main));
+    }
+    for (binary, shim) in &package.bins {
+        let target = find_package_binary(frate_dir, &package.name, binary)?
+            .ok_or_else(|| Error::BinaryNotFound(format!("{} in {}", binary, package.name)))?;
+        shims.push((shim.clone(), target));
+    }
+    Ok(shims)
+}
 /// Uninstalls all installed packages by removing `.frate/bin` and `.frate/shims` directories
-/// and recreating them empty.
+/// and recreating them empty. All receipts are removed as well.
+///
+/// # Arguments
+///
+/// * `frate_dir` - Path to the `.frate` directory.
+/// * `reporter` - Receives progress events.
 ///
 /// # Errors
 ///
 /// Returns an error if the directories cannot be removed or recreated.
-pub fn uninstall_packages() -> Result<()> {
-    println!("{} {}", "Uninstalling".bold().yellow(), "all packages");
-    let frate_dir = get_frate_dir()?;
+pub fn uninstall_packages(frate_dir: &Path, reporter: &dyn Reporter) -> Result<()> {
+    reporter.report(Event::Uninstalling { name: None });
 
     std::fs::remove_dir_all(frate_dir.join("bin"))?;
     std::fs::remove_dir_all(frate_dir.join("shims"))?;
 
     std::fs::create_dir_all(frate_dir.join("bin"))?;
     std::fs::create_dir_all(frate_dir.join("shims"))?;
-    println!("        {}", "Done".bold().green());
+    let receipts = frate_dir.join("receipts");
+    if receipts.exists() {
+        std::fs::remove_dir_all(receipts)?;
+    }
+    reporter.report(Event::Uninstalled { name: None });
     Ok(())
 }
-/// Uninstalls a single package by removing its directory under `.frate/bin/{name}`
-/// and deleting its corresponding shim in `.frate/shims/{name}`.
+/// Uninstalls a single package by removing its directory under `.frate/bin/{name}`,
+/// deleting the shims recorded in its receipt and `.frate/shims/{name}`, and removing its receipt.
 ///
 /// # Arguments
 ///
+/// * `frate_dir` - Path to the `.frate` directory.
 /// * `name` - Name of the package to uninstall.
+/// * `reporter` - Receives progress events.
 ///
 /// # Errors
 ///
@@ -114,39 +210,27 @@ pub fn uninstall_packages() -> Result<()> {
 /// # Example
 ///
 /// ```no_run
-/// use frate::uninstall_package;
+/// use std::path::Path;
+/// use frate::{uninstall_package, Silent};
 ///
-/// uninstall_package("example").unwrap();
+/// uninstall_package(Path::new(".frate"), "example", &Silent).unwrap();
 /// ```
-pub fn uninstall_package(name: &str) -> Result<()> {
-    println!("{} {}", "Uninstalling".bold().yellow(), name);
-    let cwd = std::env::current_dir()?;
-    let frate_dir = cwd.join(".frate");
+pub fn uninstall_package(frate_dir: &Path, name: &str, reporter: &dyn Reporter) -> Result<()> {
+    reporter.report(Event::Uninstalling { name: Some(name.to_string()) });
     let bin_dir = frate_dir.join("bin");
-    let shims_dir = frate_dir.join("shims");
-    #[cfg(target_os = "windows")]
-    {
-        let bin_path = bin_dir.join(name);
-        if bin_path.exists() {
-            std::fs::remove_dir_all(bin_path)?;
-        }
-        let shim_path = shims_dir.join(format!("{name}.bat"));
-        if shim_path.exists() {
-            std::fs::remove_file(shim_path)?;
-        }
+    let bin_path = bin_dir.join(name);
+    if bin_path.exists() {
+        std::fs::remove_dir_all(bin_path)?;
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        let bin_path = bin_dir.join(name);
-        if bin_path.exists() {
-            std::fs::remove_dir_all(bin_path)?;
-        }
-        let shim_path = shims_dir.join(name);
-        if shim_path.exists() {
-            std::fs::remove_file(shim_path)?;
+    let shims_dir = frate_dir.join("shims");
+    if let Some(receipt) = Receipt::load(frate_dir, name)? {
+        for shim in receipt.shims {
+            remove_shim(shims_dir.join(shim))?;
         }
     }
-    println!("        {}", "Done".bold().green());
+    remove_shim(shims_dir.join(name))?;
+    Receipt::remove(frate_dir, name)?;
+    reporter.report(Event::Uninstalled { name: Some(name.to_string()) });
     Ok(())
 }
 /// Downloads an archive from a given URL, verifies its SHA-256 hash, and extracts it to the given directory.
@@ -154,9 +238,11 @@ pub fn uninstall_package(name: &str) -> Result<()> {
 ///
 /// # Arguments
 ///
+/// * `fetcher` - Used to download the archive.
 /// * `url` - The URL of the archive to download.
 /// * `dest_dir` - Target directory for extraction.
 /// * `expected_hash` - Expected SHA-256 hash (hex-encoded) to verify integrity.
+/// * `reporter` - Receives progress events.
 ///
 /// # Errors
 ///
@@ -165,25 +251,23 @@ pub fn uninstall_package(name: &str) -> Result<()> {
 /// - the hash doesn't match,
 /// - the archive type is unsupported,
 /// - or extraction fails.
-pub fn download_and_extract(url: &str, dest_dir: &str, expected_hash: &str) -> Result<()> {
-    let expected_hash = crate::util::format_hash(expected_hash);
-    println!(" {} {}", "Downloading".bold().green(), url);
-    let response = reqwest::blocking::get(url)?;
-    if !response.status().is_success() {
-        bail!(" {} {}: {}", "Failed to download".bold().red(), url, response.status());
-    }
-    let bytes = response.bytes()?;
-
-    // Check hash
-    let mut hasher = sha2::Sha256::new();
-    hasher.update(&bytes);
-    let actual_hash = hex::encode(hasher.finalize());
-
-    if actual_hash != expected_hash {
-        bail!(" {}\n  expected: {}\n  got: {}", "Hash mismatch:".bold().red(), expected_hash, actual_hash);
-    }
+pub fn download_and_extract(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    dest_dir: &str,
+    expected_hash: &str,
+    reporter: &dyn Reporter
+) -> Result<()> {
+    reporter.report(Event::Downloading { url: url.to_string() });
+    let bytes = fetcher.fetch(url)?
+        .ok_or_else(|| Error::Network(format!("failed to download {}: not found", url)))?;
+    verify_archive(&bytes, expected_hash, url)?;
 
-    println!("  {} {} to {}", "Extracting".bold().green(), url, dest_dir);
+    reporter.report(Event::Extracting {
+        source: url.to_string(),
+        dest: dest_dir.into(),
+        cached: false,
+    });
     if url.ends_with(".zip") {
         let reader = Cursor::new(&bytes);
         let mut zip = zip::ZipArchive::new(reader)?;
@@ -195,36 +279,87 @@ pub fn download_and_extract(url: &str, dest_dir: &str, expected_hash: &str) -> R
         archive.unpack(dest_dir)?;
     }
     else {
-        bail!("Unsupported archive type: {}", url.split(crate::util::PATH_SEPARATOR).last().unwrap_or(url));
+        return Err(Error::UnsupportedArchive(
+            url.split(crate::util::PATH_SEPARATOR).last().unwrap_or(url).to_string()
+        ));
     }
     if !is_cached(url)? {
-        println!("     {}", "Caching".bold().green());
-        cache_archive(url, bytes.as_ref())?;
+        reporter.report(Event::Caching { url: url.to_string() });
+        cache_archive(url, bytes.as_ref(), reporter)?;
     }
     Ok(())
 }
 
-pub fn extract_cached<P: AsRef<Path>>(
-    cached_path: P,
-    dest_dir: P,
-    expected_hash: &str
-) -> Result<()> {
+/// Returns the archive of `package`: from `vendor_dir` if it is [vendored](crate::vendor) there,
+/// else from the global cache, else downloaded with `fetcher`. The archive is checked against
+/// the hash of `package` in every case.
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be read or downloaded, or its hash does not match.
+pub fn fetch_archive(
+    package: &LockedPackage,
+    vendor_dir: Option<&Path>,
+    fetcher: &dyn Fetcher,
+    reporter: &dyn Reporter
+) -> Result<Vec<u8>> {
+    let local = match vendor_dir.and_then(|dir| vendored_archive(dir, package)) {
+        Some(path) => Some(path),
+        None => get_cached_archive(&package.source)?,
+    };
+    let (bytes, archive) = match local {
+        Some(path) => (std::fs::read(&path)?, path.display().to_string()),
+        None => {
+            reporter.report(Event::Downloading { url: package.source.clone() });
+            let bytes = fetcher.fetch(&package.source)?
+                .ok_or_else(|| Error::Network(format!("failed to download {}: not found", package.source)))?;
+            (bytes, package.source.clone())
+        }
+    };
+    verify_archive(&bytes, &package.hash, &archive)?;
+    Ok(bytes)
+}
+/// Checks the SHA-256 hash of the archive `bytes` against `expected_hash`, with or without
+/// a `sha256:` prefix. `archive` names the archive in the error.
+///
+/// # Errors
+///
+/// Returns [`Error::HashMismatch`] if the hashes differ.
+pub fn verify_archive(bytes: &[u8], expected_hash: &str, archive: &str) -> Result<()> {
     let expected_hash = crate::util::format_hash(expected_hash);
-    let archive_bytes = std::fs::read(&cached_path)?;
     let mut hasher = sha2::Sha256::new();
-    hasher.update(&archive_bytes);
+    hasher.update(bytes);
     let actual_hash = hex::encode(hasher.finalize());
     if actual_hash != expected_hash {
-        bail!(
-            " {}\n  expected: {}\n  got: {}\n  for: {}",
-            "Hash mismatch:".bold().red(),
-            expected_hash,
-            actual_hash,
-            cached_path.as_ref().display()
-        );
+        return Err(Error::HashMismatch {
+            archive: archive.to_string(),
+            expected: expected_hash,
+            actual: actual_hash,
+        });
     }
+    Ok(())
+}
+
+/// Extracts an archive from the global cache or the vendor directory after verifying its SHA-256 hash.
+///
+/// # Errors
+///
+/// Returns an error if the hash doesn't match, the archive type is unsupported,
+/// or extraction fails.
+pub fn extract_cached<P: AsRef<Path>>(
+    cached_path: P,
+    dest_dir: P,
+    expected_hash: &str,
+    reporter: &dyn Reporter
+) -> Result<()> {
+    let archive_bytes = std::fs::read(&cached_path)?;
+    verify_archive(&archive_bytes, expected_hash, &cached_path.as_ref().display().to_string())?;
     let cached_path_str = cached_path.as_ref().to_string_lossy();
-    verbose!("  {} FROM CACHE {} to {}", "Extracting".bold().green(), cached_path.as_ref().display(), dest_dir.as_ref().display());
+    reporter.report(Event::Extracting {
+        source: cached_path.as_ref().display().to_string(),
+        dest: dest_dir.as_ref().to_path_buf(),
+        cached: true,
+    });
     if cached_path_str.ends_with(".zip") {
         let reader = Cursor::new(archive_bytes);
         let mut zip = zip::ZipArchive::new(reader)?;
@@ -236,19 +371,7 @@ pub fn extract_cached<P: AsRef<Path>>(
         archive.unpack(dest_dir)?;
     }
     else {
-        bail!("Unsupported archive type: {}",
-            dest_dir
-            .as_ref()
-            .display()
-            .to_string()
-            .split(crate::util::PATH_SEPARATOR)
-            .last()
-            .unwrap_or(
-                dest_dir.as_ref().display()
-                .to_string().split('.').next_back()
-                .unwrap_or(dest_dir.as_ref().display().to_string().as_str())
-            )
-        );
+        return Err(Error::UnsupportedArchive(cached_path_str.to_string()));
     }
     Ok(())
 }
@@ -7,29 +7,79 @@
 //! without needing system-wide privileges.
 //!
 //! This library is built for the `frate` CLI, but you can also reuse it as a backend in other tools.
+//! The entry point for embedders is [`Project`], which resolves all paths relative to a project root
+//! and reports progress through a [`Reporter`] instead of printing to stdout.
 //!
 //! ## Modules Overview
+//! - [`error`] – The library [`Error`] type
+//! - [`fetch`] – The [`Fetcher`] trait through which all network access goes
+//! - [`project`] – The [`Project`] context: manifest, lockfile and settings of one project
+//! - [`report`] – Progress events and the [`Reporter`] trait
+//! - [`settings`] – Project settings from the `[settings]` table of `frate.toml`
 //! - [`toml`] – Parsing and serialization of `frate.toml` manifest files
+//! - [`cfg`] – `cfg(...)` expressions for platform-specific dependencies
+//! - [`workspace`] – Workspaces of several projects sharing one lockfile
+//! - [`import`] – Reading tool versions pinned by asdf, mise and aqua (`frate import`)
+//! - [`scripts`] – Project scripts from the `[scripts]` table (`frate run <script>`)
 //! - [`lock`] – Lockfile structure and sync logic (`frate.lock`)
 //! - [`registry`] – Handling registry sources and resolving tool versions
 //! - [`installer`] – Installing, caching, and managing tool binaries
+//! - [`receipt`] – Records of installed packages (`.frate/receipts`)
+//! - [`vendor`] – Copying locked archives into the project for offline installs (`frate vendor`)
+//! - [`verify`] – Checking installed files against the digests in their receipts
+//! - [`doctor`] – Health checks for a project (`frate doctor`)
+//! - [`env`] – Shell activation code for `frate env`
+//! - [`export`] – Install instructions for Dockerfiles, shell scripts and CI (`frate export`)
+//! - [`bundle`] – Portable bundles of a project's tools for offline installs (`frate bundle`)
 //! - [`shims`] – Creating proxy shims to forward tool invocations
 //! - [`util`] – Shared utilities (paths, logging, hashing, etc.)
 //! - [`global`] – Global state and configuration (e.g., cache directory)
 
 
+pub mod error;
+pub mod fetch;
+pub mod project;
+pub mod report;
+pub mod settings;
 pub mod toml;
+pub mod cfg;
+pub mod workspace;
+pub mod import;
+pub mod scripts;
 pub mod lock;
 pub mod registry;
 pub mod util;
 pub mod installer;
+pub mod receipt;
+pub mod vendor;
+pub mod verify;
+pub mod doctor;
+pub mod env;
+pub mod export;
+pub mod bundle;
 pub mod shims;
 pub mod global;
 
+pub use error::Error;
+pub use fetch::*;
+pub use project::*;
+pub use report::*;
+pub use settings::*;
+pub use env::*;
+pub use export::*;
+pub use bundle::*;
 pub use shims::*;
 pub use installer::*;
+pub use receipt::*;
+pub use vendor::*;
+pub use verify::*;
+pub use doctor::*;
+pub use import::*;
+pub use scripts::*;
 pub use lock::*;
 pub use registry::*;
 pub use toml::*;
+pub use cfg::*;
+pub use workspace::*;
 pub use util::*;
 pub use global::cache::*;
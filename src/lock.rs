@@ -1,21 +1,29 @@
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fs;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
+use crate::fetch::Fetcher;
 use crate::registry::resolve_dependency;
+use crate::report::{Event, Reporter};
 use crate::toml::FrateToml;
-use anyhow::Result;
-use colored::Colorize;
+use crate::error::Result;
+
+/// The comment written at the top of every `frate.lock`.
+pub const LOCK_HEADER: &str = "# This file is generated by frate. Do not edit it by hand.\n# Run `frate sync` to update it.\n\n";
 
 /// Represents the contents of a `frate.lock` file.
 /// It contains an exact snapshot of all locked packages used in the project.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+///
+/// Packages are written sorted by name, with their fields always in the same order,
+/// so the file only changes where a package changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct FrateLock {
     /// A list of all locked packages with resolved versions and hashes.
     pub packages: Vec<LockedPackage>,
 }
 /// Represents a single locked package, including its resolved version and source.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct LockedPackage {
     /// Name of the package.
     pub name: String,
@@ -25,6 +33,17 @@ pub struct LockedPackage {
     pub source: String,
     /// SHA-256 hash of the downloaded artifact.
     pub hash: String,
+    /// Environment variables the package needs. Values may contain the `{install_dir}` placeholder.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", deserialize_with = "crate::env::deserialize_env_vars")]
+    pub env: BTreeMap<String, String>,
+    /// Directories to add to `PATH` besides the shims. May contain the `{install_dir}` placeholder;
+    /// relative directories are [relative to it](crate::env::anchor_path).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Shim names by binary name, from `[tools.<name>.bins]` of `frate.toml`.
+    /// Not resolved from the registry; see [`ToolConfig::bins`](crate::ToolConfig::bins).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bins: BTreeMap<String, String>,
 }
 
 impl Display for LockedPackage {
@@ -33,7 +52,51 @@ impl Display for LockedPackage {
     }
 }
 
+impl LockedPackage {
+    /// Returns the names of the fields that differ between `self` and `other`, in field order.
+    pub fn changed_fields(&self, other: &LockedPackage) -> Vec<&'static str> {
+        let fields = [
+            ("name", self.name != other.name),
+            ("version", self.version != other.version),
+            ("source", self.source != other.source),
+            ("hash", self.hash != other.hash),
+            ("env", self.env != other.env),
+            ("paths", self.paths != other.paths),
+            ("bins", self.bins != other.bins),
+        ];
+        fields.into_iter().filter(|(_, changed)| *changed).map(|(field, _)| field).collect()
+    }
+}
+
+/// The differences between two lockfiles, see [`FrateLock::diff`].
+/// Each list is sorted by package name.
+#[derive(Debug, Default, PartialEq)]
+pub struct LockDiff {
+    /// Packages only in the new lockfile.
+    pub added: Vec<LockedPackage>,
+    /// Packages only in the old lockfile.
+    pub removed: Vec<LockedPackage>,
+    /// Packages in both lockfiles that differ, as `(old, new)`.
+    pub changed: Vec<(LockedPackage, LockedPackage)>,
+}
+
+impl LockDiff {
+    /// Returns true if the lockfiles lock the same packages.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
 impl FrateLock {
+    /// Loads the lockfile from disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
     /// Loads the lockfile from disk or returns an empty lockfile if it doesn't exist or is invalid.
     ///
     /// # Arguments
@@ -52,7 +115,8 @@ impl FrateLock {
             FrateLock { packages: vec![]}
         }
     }
-    /// Saves the lockfile to disk in a pretty TOML format.
+    /// Saves the lockfile to disk in a pretty TOML format, with packages sorted by name
+    /// and [`LOCK_HEADER`] at the top.
     ///
     /// # Arguments
     ///
@@ -62,42 +126,114 @@ impl FrateLock {
     ///
     /// Returns an error if the file cannot be created or written to.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()>{
-        let content = toml::to_string_pretty(&self)?;
+        let content = self.to_toml_string()?;
         if !path.as_ref().exists() {
             fs::File::create(&path)?;
         }
         fs::write(path, content)?;
         Ok(())
     }
+    /// Returns the content [`FrateLock::save`] writes: the packages sorted by name, after [`LOCK_HEADER`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile cannot be serialized.
+    pub fn to_toml_string(&self) -> Result<String> {
+        let mut sorted = self.clone();
+        sorted.sort();
+        Ok(format!("{}{}", LOCK_HEADER, toml::to_string_pretty(&sorted)?))
+    }
+    /// Sorts the packages by name.
+    pub fn sort(&mut self) {
+        self.packages.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    /// Compares the lockfile with a newer version of it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use frate::{FrateLock, LockedPackage};
+    ///
+    /// let package = |version: &str| LockedPackage { name: "just".into(), version: version.into(), ..Default::default() };
+    /// let old = FrateLock { packages: vec![package("1.42.0")] };
+    /// let new = FrateLock { packages: vec![package("1.43.0")] };
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.changed[0].1.version, "1.43.0");
+    /// assert!(diff.added.is_empty() && diff.removed.is_empty());
+    /// ```
+    pub fn diff(&self, new: &FrateLock) -> LockDiff {
+        let old: BTreeMap<&str, &LockedPackage> = self.packages.iter().map(|p| (p.name.as_str(), p)).collect();
+        let new: BTreeMap<&str, &LockedPackage> = new.packages.iter().map(|p| (p.name.as_str(), p)).collect();
+        let mut diff = LockDiff::default();
+        for (name, package) in &new {
+            match old.get(name) {
+                None => diff.added.push((*package).clone()),
+                Some(previous) if previous != package => diff.changed.push(((*previous).clone(), (*package).clone())),
+                Some(_) => {},
+            }
+        }
+        diff.removed = old.iter()
+            .filter(|(name, _)| !new.contains_key(*name))
+            .map(|(_, package)| (*package).clone())
+            .collect();
+        diff
+    }
+    /// Removes a package from the lockfile.
+    ///
+    /// If the package is not locked, nothing happens.
+    pub fn remove(&mut self, name: &str) {
+        self.packages.retain(|package| package.name != name);
+    }
     /// Synchronizes the lockfile with the current state of the `frate.toml`.
     ///
-    /// Resolves all dependencies to exact versions, including download source and hash,
-    /// and writes them to `self.packages`.
+    /// Resolves all dependencies [used on the current platform](FrateToml::current_dependencies)
+    /// that are not locked yet to exact versions, including download source and hash, and adds
+    /// them to `self.packages`. Each dependency is resolved against its own registry if it names one.
     ///
     /// # Arguments
     ///
     /// * `toml` - Reference to the parsed `frate.toml`.
+    /// * `fetcher` - Used to download registry entries.
+    /// * `registry` - Base URL of the default registry to resolve against.
+    /// * `reporter` - Receives an [`Event::ResolveFailed`] for every optional dependency that cannot be resolved.
     ///
     /// # Errors
     ///
-    /// Returns an error if resolution fails for all dependencies.
+    /// Returns the error of the first dependency that is not optional and cannot be resolved.
+    /// Packages resolved before it are kept in `self.packages`.
     pub fn sync(
-        &mut self, toml: &FrateToml
+        &mut self,
+        toml: &FrateToml,
+        fetcher: &dyn Fetcher,
+        registry: &str,
+        reporter: &dyn Reporter
     ) -> Result<Vec<LockedPackage>> {
         let mut added = Vec::new();
-        for (name, version_req) in &toml.dependencies {
-            let resolved = match resolve_dependency(name, version_req) {
+        for (name, dependency) in &toml.current_dependencies() {
+            if self.packages.iter().any(|p| p.name == *name) {
+                continue;
+            }
+            let resolved = toml.registry_url(dependency, registry)
+                .and_then(|registry| resolve_dependency(fetcher, registry, name, dependency.version()));
+            let resolved = match resolved {
                 Ok(resolved) => resolved,
-                Err(e) => {
-                    eprintln!("{} {}", "Failed to resolve dependency".red(), e.to_string().red());
+                Err(e) if dependency.is_optional() => {
+                    reporter.report(Event::ResolveFailed { name: name.clone(), error: e.to_string() });
                     continue;
                 },
+                Err(e) => {
+                    self.sort();
+                    return Err(e);
+                },
             };
             let locked = LockedPackage {
                 name: resolved.name,
                 version: resolved.version,
                 source: resolved.url,
                 hash: resolved.hash,
+                env: resolved.env,
+                paths: resolved.paths,
+                bins: BTreeMap::new(),
             };
             if self.packages.iter().any(|p| p.name == locked.name) {
                 continue;
@@ -105,6 +241,7 @@ impl FrateLock {
             added.push(locked.clone());
             self.packages.push(locked);
         }
+        self.sort();
         Ok(added)
     }
 }
@@ -134,6 +271,7 @@ mod tests {
                 version: "1.2.3".to_string(),
                 source: "https://example.com".to_string(),
                 hash: "abc123".to_string(),
+                ..Default::default()
             }],
         };
 
@@ -143,6 +281,38 @@ mod tests {
         assert_eq!(loaded.packages[0].name, "example");
     }
 
+    #[test]
+    fn test_save_sorts_packages_and_writes_header() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("frate.lock");
+        let package = |name: &str| LockedPackage { name: name.to_string(), ..Default::default() };
+        let lock = FrateLock { packages: vec![package("zoxide"), package("bat"), package("just")] };
+        lock.save(&path).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(LOCK_HEADER));
+        let names: Vec<String> = FrateLock::load(&path).unwrap().packages.into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["bat", "just", "zoxide"]);
+    }
+
+    #[test]
+    fn test_diff() {
+        let package = |name: &str, version: &str, hash: &str| LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            hash: hash.to_string(),
+            ..Default::default()
+        };
+        let old = FrateLock { packages: vec![package("a", "1.0.0", "x"), package("b", "1.0.0", "x"), package("c", "1.0.0", "x")] };
+        let new = FrateLock { packages: vec![package("c", "1.0.0", "y"), package("b", "1.0.0", "x"), package("d", "2.0.0", "x")] };
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, [package("d", "2.0.0", "x")]);
+        assert_eq!(diff.removed, [package("a", "1.0.0", "x")]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].0.changed_fields(&diff.changed[0].1), ["hash"]);
+        assert!(old.diff(&old).is_empty());
+    }
+
     #[test]
     fn test_load_or_default_returns_empty_on_invalid_toml() {
         let dir = tempdir().unwrap();
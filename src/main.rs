@@ -1,11 +1,49 @@
 mod cli;
+mod console;
 mod execute;
 
+use std::process::ExitCode;
 use clap::Parser;
+use colored::Colorize;
 use crate::cli::Cli;
-use anyhow::Result;
 
-fn main() -> Result<()>{
+fn main() -> ExitCode {
     let cli = Cli::parse();
-    execute::execute(cli)
+    match execute::execute(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{} {:#}", "error:".bold().red(), e);
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// Maps an error to the process exit code.
+///
+/// Every [`frate::Error`] variant has its own code, so scripts can tell failures apart.
+/// Errors that don't originate from the library exit with `1`. Code `2` is used by clap
+/// for invalid command line arguments.
+fn exit_code(e: &anyhow::Error) -> u8 {
+    let Some(e) = e.chain().find_map(|e| e.downcast_ref::<frate::Error>()) else {
+        return 1;
+    };
+    match e {
+        frate::Error::ManifestNotFound(_) => 3,
+        frate::Error::RegistryNotFound(_) => 4,
+        frate::Error::VersionNotFound { .. } => 5,
+        frate::Error::NotLocked(_) => 6,
+        frate::Error::InvalidVersion(_) => 7,
+        frate::Error::DependencyExists(_) => 8,
+        frate::Error::HashMismatch { .. } => 9,
+        frate::Error::UnsupportedArchive(_) => 10,
+        frate::Error::BinaryNotFound(_) => 11,
+        frate::Error::Network(_) => 12,
+        frate::Error::Io(_) => 13,
+        frate::Error::Parse(_) => 14,
+        frate::Error::ScriptNotFound(_) => 15,
+        frate::Error::ScriptCycle(_) => 16,
+        frate::Error::DependencyNotFound(_) => 17,
+        frate::Error::VersionConflict(_) => 18,
+        frate::Error::Other(_) => 1,
+    }
 }
@@ -0,0 +1,924 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::bundle::{Bundle, BundleManifest};
+use crate::env::Environment;
+use crate::error::{Error, Result};
+use crate::fetch::{Fetcher, Offline, ReqwestFetcher};
+use crate::global::cache::{cache_archive, get_cached_archive, is_cached};
+use crate::import::{map_tool_name, ImportReport, SkippedPin, ToolPin};
+use crate::installer::{fetch_archive, install_package, verify_archive, uninstall_package, uninstall_packages};
+use crate::receipt::Receipt;
+use crate::cfg::TargetInfo;
+use crate::registry::{fetch_index, resolve_dependency_for_target};
+use crate::shims::{create_binary_shim, create_shim, find_shim, find_shim_binary, read_shim, shim_config_path, write_env_scripts, ShimConfig};
+use crate::lock::{FrateLock, LockedPackage};
+use crate::report::{Event, Reporter};
+use crate::settings::{Settings, ShimMode};
+use crate::toml::{Dependency, FrateToml};
+use crate::vendor::{vendor_packages, VendorManifest};
+use crate::verify::{verify_package, Verification};
+use crate::workspace::{VersionConflict, Workspace};
+use crate::util::{current_target_triple, ensure_frate_dirs, find_installed_paths, get_locked, is_installed, is_valid_version};
+
+/// A `frate` project rooted at a directory containing a `frate.toml`.
+///
+/// `Project` bundles the manifest, the lockfile and the effective settings, and resolves
+/// every path relative to its root instead of the process working directory.
+/// Operations never print; progress is reported through a [`Reporter`].
+/// Network access goes through a [`Fetcher`], which defaults to [`ReqwestFetcher`]
+/// and can be replaced with [`Project::with_fetcher`].
+///
+/// # Example
+///
+/// ```no_run
+/// use frate::{Project, Silent};
+///
+/// let mut project = Project::load("path/to/project").unwrap();
+/// project.sync(&Silent).unwrap();
+/// project.install(None, &Silent).unwrap();
+/// ```
+pub struct Project {
+    root: PathBuf,
+    dir: PathBuf,
+    manifest: FrateToml,
+    workspace: Option<Workspace>,
+    lock: FrateLock,
+    settings: Settings,
+    fetcher: Box<dyn Fetcher>,
+}
+
+impl fmt::Debug for Project {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Project")
+            .field("root", &self.root)
+            .field("dir", &self.dir)
+            .field("manifest", &self.manifest)
+            .field("workspace", &self.workspace)
+            .field("lock", &self.lock)
+            .field("settings", &self.settings)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The status of a single dependency, as returned by [`Project::list`].
+#[derive(Debug, Clone)]
+pub struct ToolStatus {
+    /// Name of the tool.
+    pub name: String,
+    /// Version requested in `frate.toml`.
+    pub version: String,
+    /// The lockfile entry, if the tool is locked.
+    pub locked: Option<LockedPackage>,
+    /// Whether the locked archive is present in the global cache.
+    pub cached: bool,
+    /// Whether the tool is installed in `.frate/bin`.
+    pub installed: bool,
+}
+
+impl Project {
+    /// Loads the project rooted at `root`.
+    ///
+    /// Reads `frate.toml` and, if present, `frate.lock`. The settings are taken from the
+    /// manifest's `[settings]` table and can be overridden with [`Project::settings_mut`].
+    ///
+    /// If `root` is the root or a member of a [`Workspace`], the project uses the lockfile,
+    /// `.frate` directory and settings of the workspace root, and the dependencies of all members.
+    ///
+    /// # Errors
+    /// Returns an error if `frate.toml` is missing or cannot be parsed, or a manifest of the
+    /// workspace cannot be loaded.
+    pub fn load<P: AsRef<Path>>(root: P) -> Result<Project> {
+        let dir = root.as_ref().to_path_buf();
+        let manifest_path = dir.join("frate.toml");
+        if !manifest_path.exists() {
+            return Err(Error::ManifestNotFound(dir));
+        }
+        let manifest = FrateToml::load(&manifest_path)?;
+        let workspace = match manifest.workspace {
+            Some(_) => Some(Workspace::load(&dir, &manifest)?),
+            None => Workspace::find(&dir)?,
+        };
+        let (root, settings) = match &workspace {
+            Some(workspace) => (workspace.root.clone(), workspace.manifests[0].1.settings.clone()),
+            None => (dir.clone(), manifest.settings.clone()),
+        };
+        let lock = FrateLock::load_or_default(root.join("frate.lock"));
+        Ok(Project { root, dir, manifest, workspace, lock, settings, fetcher: Box::new(ReqwestFetcher::new()) })
+    }
+    /// Loads the project containing `start`: the nearest ancestor of `start`, including
+    /// `start` itself, that contains a `frate.toml`.
+    ///
+    /// # Errors
+    /// Returns [`Error::ManifestNotFound`] if no ancestor contains a `frate.toml`,
+    /// or an error if it cannot be parsed.
+    pub fn discover<P: AsRef<Path>>(start: P) -> Result<Project> {
+        let start = start.as_ref();
+        match start.ancestors().find(|dir| dir.join("frate.toml").is_file()) {
+            Some(root) => Project::load(root),
+            None => Err(Error::ManifestNotFound(start.to_path_buf())),
+        }
+    }
+    /// Initializes a new project in `root`.
+    ///
+    /// Creates the `.frate` directory structure, the activation script and a default
+    /// `frate.toml` named after the directory.
+    ///
+    /// # Errors
+    /// Returns an error if the directory name cannot be determined or file operations fail.
+    pub fn init<P: AsRef<Path>>(root: P) -> Result<Project> {
+        let root = root.as_ref().to_path_buf();
+        let name = root.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| Error::Other(format!("Invalid directory name: {}", root.display())))?;
+        ensure_frate_dirs(&root)?;
+        let manifest = FrateToml::default(name);
+        manifest.save(root.join("frate.toml"))?;
+
+        let settings = manifest.settings.clone();
+        let project = Project {
+            dir: root.clone(),
+            root,
+            manifest,
+            workspace: None,
+            lock: FrateLock { packages: vec![] },
+            settings,
+            fetcher: Box::new(ReqwestFetcher::new()),
+        };
+        project.write_activate()?;
+        Ok(project)
+    }
+    /// Replaces the [`Fetcher`] used for all network access of this project.
+    pub fn with_fetcher<F: Fetcher + 'static>(mut self, fetcher: F) -> Project {
+        self.fetcher = Box::new(fetcher);
+        self
+    }
+    /// Returns the [`Fetcher`] used for network access.
+    pub fn fetcher(&self) -> &dyn Fetcher {
+        self.fetcher.as_ref()
+    }
+    /// Returns the project root, which holds `frate.lock` and `.frate`.
+    /// For a member of a workspace, this is the workspace root.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    /// Returns the directory of the project's own `frate.toml`. Differs from [`Project::root`]
+    /// for members of a workspace.
+    pub fn manifest_dir(&self) -> &Path {
+        &self.dir
+    }
+    /// Returns the workspace the project belongs to, if any.
+    pub fn workspace(&self) -> Option<&Workspace> {
+        self.workspace.as_ref()
+    }
+    /// Returns the project's own parsed `frate.toml`.
+    pub fn manifest(&self) -> &FrateToml {
+        &self.manifest
+    }
+    /// Returns the manifest that is locked and installed: the project's own manifest, or in a
+    /// workspace, the [merged](crate::merge_manifests) manifests of the root and all members.
+    pub fn effective_manifest(&self) -> Cow<'_, FrateToml> {
+        match &self.workspace {
+            Some(workspace) => Cow::Owned(workspace.merged(&self.dir, &self.manifest)),
+            None => Cow::Borrowed(&self.manifest),
+        }
+    }
+    /// Returns the tools that members of the workspace request in different versions.
+    /// Empty outside of workspaces.
+    pub fn conflicts(&self) -> Vec<VersionConflict> {
+        match &self.workspace {
+            Some(workspace) => workspace.conflicts(&self.dir, &self.manifest),
+            None => Vec::new(),
+        }
+    }
+    /// Returns a mutable reference to the manifest. Call [`Project::save_manifest`] to persist changes.
+    pub fn manifest_mut(&mut self) -> &mut FrateToml {
+        &mut self.manifest
+    }
+    /// Returns the current lockfile. Empty if no `frate.lock` exists yet.
+    pub fn lock(&self) -> &FrateLock {
+        &self.lock
+    }
+    /// Returns a mutable reference to the lockfile. Call [`Project::save_lock`] to persist changes.
+    pub fn lock_mut(&mut self) -> &mut FrateLock {
+        &mut self.lock
+    }
+    /// Returns the effective settings.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+    /// Returns a mutable reference to the effective settings.
+    /// Changes only affect this instance and are not written to `frate.toml`.
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+    /// Returns the path to the project's own `frate.toml`.
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir.join("frate.toml")
+    }
+    /// Returns the path to `frate.lock`.
+    pub fn lock_path(&self) -> PathBuf {
+        self.root.join("frate.lock")
+    }
+    /// Returns the path to the `.frate` directory.
+    pub fn frate_dir(&self) -> PathBuf {
+        self.root.join(".frate")
+    }
+    /// Returns the path to the vendor directory, [`Settings::vendor_dir`] below the project root.
+    pub fn vendor_dir(&self) -> PathBuf {
+        self.root.join(&self.settings.vendor_dir)
+    }
+    /// Writes the manifest back to `frate.toml`.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save_manifest(&self) -> Result<()> {
+        self.manifest.save(self.manifest_path())
+    }
+    /// Writes the lockfile back to `frate.lock`.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be written.
+    pub fn save_lock(&self) -> Result<()> {
+        self.lock.save(self.lock_path())
+    }
+    /// Writes the activation scripts and the tool environment into the `.frate` directory.
+    /// See [`write_activate_scripts`](crate::shims::write_activate_scripts) and
+    /// [`write_env_scripts`].
+    ///
+    /// # Errors
+    /// Returns an error if a script can't be written.
+    pub fn write_activate(&self) -> Result<()> {
+        std::fs::create_dir_all(self.frate_dir())?;
+        crate::shims::write_activate_scripts(&self.frate_dir())?;
+        write_env_scripts(&self.frate_dir(), &self.packages())
+    }
+    /// Synchronizes `frate.lock` with the manifest and saves it.
+    ///
+    /// Returns the packages that were newly added to the lockfile.
+    ///
+    /// # Errors
+    /// Returns an error if resolving or saving the lockfile fails.
+    pub fn sync(&mut self, reporter: &dyn Reporter) -> Result<Vec<LockedPackage>> {
+        let conflicts = self.conflicts();
+        if !conflicts.is_empty() {
+            let lines: Vec<String> = conflicts.iter().map(|conflict| format!("  {}", conflict)).collect();
+            return Err(Error::VersionConflict(lines.join("\n")));
+        }
+        let manifest = self.effective_manifest().into_owned();
+        let added = self.lock.sync(&manifest, self.fetcher.as_ref(), &self.settings.registry, reporter)?;
+        self.lock.save(self.lock_path())?;
+        self.create_pending_shims()?;
+        Ok(added)
+    }
+    /// Removes the dependency `name` from `frate.toml` and `frate.lock`, and uninstalls it
+    /// if it is installed. Both files are saved. In a workspace, the tool stays locked and
+    /// installed while another member still declares it.
+    ///
+    /// # Errors
+    /// Returns [`Error::DependencyNotFound`] if `frate.toml` does not declare `name`, or an error
+    /// if saving or uninstalling fails.
+    pub fn remove(&mut self, name: &str, reporter: &dyn Reporter) -> Result<()> {
+        if !self.manifest.declares(name) {
+            return Err(Error::DependencyNotFound(name.to_string()));
+        }
+        self.manifest.remove(name);
+        self.save_manifest()?;
+        if self.effective_manifest().declares(name) {
+            // Another member of the workspace still uses the tool.
+            return Ok(());
+        }
+        self.lock.remove(name);
+        self.save_lock()?;
+        if is_installed(&self.frate_dir(), name) {
+            uninstall_package(&self.frate_dir(), name, reporter)?;
+            write_env_scripts(&self.frate_dir(), &self.packages())?;
+        }
+        Ok(())
+    }
+    /// Changes the version of the dependency `name` in `frate.toml` and locks it again.
+    /// `frate.toml` and `frate.lock` are only saved once the new version is resolved; if that
+    /// fails, both are left unchanged. Installed files are not touched; run [`Project::install`]
+    /// to update them.
+    ///
+    /// Returns the new lock entry, or `None` if `name` is an [optional](Project::is_optional)
+    /// dependency whose new version could not be resolved.
+    ///
+    /// # Errors
+    /// Returns an error if the version is invalid or cannot be resolved, `frate.toml` does not
+    /// declare `name`, or saving fails.
+    pub fn update(&mut self, name: &str, version: &str, reporter: &dyn Reporter) -> Result<Option<LockedPackage>> {
+        let previous = (self.manifest.clone(), self.lock.clone());
+        self.manifest.set_version(name, version)?;
+        self.lock.remove(name);
+        let added = match self.sync(reporter) {
+            Ok(added) => added,
+            Err(e) => {
+                (self.manifest, self.lock) = previous;
+                return Err(e);
+            }
+        };
+        self.save_manifest()?;
+        Ok(added.into_iter().find(|package| package.name == name))
+    }
+    /// Copies the archives of all locked packages into the [vendor directory](Project::vendor_dir),
+    /// so that installs work without network access. See [`vendor_packages`].
+    ///
+    /// If `dir` is given and differs from the configured directory, it is saved as `vendor-dir`
+    /// in `frate.toml` first, so that installs find it. It is relative to the project root.
+    /// In a workspace, settings are read from the root, so it is saved in the root's `frate.toml`.
+    ///
+    /// # Errors
+    /// Returns an error if an archive cannot be obtained or does not match its lock hash,
+    /// or writing fails.
+    pub fn vendor(&mut self, dir: Option<&str>, reporter: &dyn Reporter) -> Result<VendorManifest> {
+        if let Some(dir) = dir.filter(|dir| *dir != self.settings.vendor_dir) {
+            self.settings.vendor_dir = dir.to_string();
+            match self.workspace.as_mut().filter(|workspace| workspace.root != self.dir) {
+                Some(workspace) => {
+                    let (root, manifest) = &mut workspace.manifests[0];
+                    manifest.settings.vendor_dir = dir.to_string();
+                    manifest.save(root.join("frate.toml"))?;
+                }
+                None => {
+                    self.manifest.settings.vendor_dir = dir.to_string();
+                    self.save_manifest()?;
+                }
+            }
+        }
+        vendor_packages(&self.lock.packages, &self.vendor_dir(), self.fetcher.as_ref(), reporter)
+    }
+    /// Writes a [`Bundle`] of `frate.toml`, `frate.lock` and the archives of the tools
+    /// [`frate install`](Project::export_packages) needs on each of `targets` to `path`.
+    ///
+    /// Archives are taken from the vendor directory or the global cache where possible, and
+    /// downloaded otherwise. In a workspace, the bundled `frate.toml` declares the dependencies
+    /// of all members.
+    ///
+    /// # Errors
+    /// Returns an error if a tool cannot be resolved for a target, an archive cannot be
+    /// obtained or does not match its hash, or the bundle cannot be written.
+    pub fn create_bundle(&self, path: &Path, targets: &[String], reporter: &dyn Reporter) -> Result<BundleManifest> {
+        let frate_toml = match &self.workspace {
+            Some(_) => {
+                let mut manifest = self.effective_manifest().into_owned();
+                manifest.workspace = None;
+                manifest.to_toml_string()?
+            }
+            None => std::fs::read_to_string(self.manifest_path())?,
+        };
+        let mut bundle = Bundle::new(frate_toml, self.lock.clone());
+        for target in targets {
+            for package in self.export_packages(target)? {
+                let bytes = fetch_archive(&package, Some(&self.vendor_dir()), self.fetcher(), reporter)?;
+                bundle.add_archive(target, &package, bytes)?;
+            }
+        }
+        bundle.write(path)?;
+        Ok(bundle.manifest)
+    }
+    /// Installs the [`Bundle`] at `bundle` into `dir` without network access, and returns
+    /// the installed project.
+    ///
+    /// Writes the bundled `frate.toml` and the `frate.lock` for the current platform to `dir`,
+    /// copies the archives for the current platform into the global cache, and installs them.
+    ///
+    /// # Errors
+    /// Returns an error if the bundle cannot be read or has no archives for the current
+    /// platform, `dir` already has a different `frate.toml`, an archive does not match its
+    /// hash, or installation fails.
+    pub fn install_bundle(bundle: &Path, dir: &Path, reporter: &dyn Reporter) -> Result<Project> {
+        let triple = current_target_triple();
+        let bundle = Bundle::read(bundle)?;
+        let archives = bundle.archives_for(&triple);
+        if archives.is_empty() && !bundle.frate_lock.packages.is_empty() {
+            return Err(Error::Other(format!(
+                "the bundle has no archives for {} (bundled targets: {})",
+                triple,
+                bundle.manifest.targets.join(", ")
+            )));
+        }
+        let manifest_path = dir.join("frate.toml");
+        if manifest_path.exists() && std::fs::read_to_string(&manifest_path)? != bundle.frate_toml {
+            return Err(Error::Other(format!("{} differs from the frate.toml in the bundle", manifest_path.display())));
+        }
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&manifest_path, &bundle.frate_toml)?;
+        bundle.lock_for(&triple).save(dir.join("frate.lock"))?;
+        for archive in archives {
+            let bytes = &bundle.files[&archive.file];
+            verify_archive(bytes, &archive.hash, &archive.file)?;
+            let cached = match get_cached_archive(&archive.source)? {
+                Some(path) => verify_archive(&std::fs::read(path)?, &archive.hash, &archive.file).is_ok(),
+                None => false,
+            };
+            if !cached {
+                reporter.report(Event::Caching { url: archive.source.clone() });
+                cache_archive(&archive.source, bytes, reporter)?;
+            }
+        }
+        let project = Project::load(dir)?.with_fetcher(Offline);
+        project.install(None, reporter)?;
+        project.write_activate()?;
+        Ok(project)
+    }
+    /// Adds tools pinned by another version manager to `frate.toml` with [`FrateToml::add`],
+    /// and saves it. Tool names are [mapped](map_tool_name) to entries of the project's registry.
+    ///
+    /// Pins without a registry entry, with a version that is not exact, or for tools already
+    /// in `frate.toml` are returned as [skipped](ImportReport::skipped).
+    ///
+    /// # Errors
+    /// Returns an error if the registry index cannot be fetched or saving fails.
+    pub fn import(&mut self, pins: &[ToolPin]) -> Result<ImportReport> {
+        let index = fetch_index(self.fetcher.as_ref(), &self.settings.registry)?;
+        let mut report = ImportReport::default();
+        for pin in pins {
+            let skip = |reason: String| SkippedPin { pin: pin.clone(), reason };
+            let Some(name) = map_tool_name(&pin.tool, &index) else {
+                report.skipped.push(skip("no matching tool in the registry".to_string()));
+                continue;
+            };
+            if self.manifest.declares(&name) || report.added.iter().any(|(added, _)| *added == name) {
+                report.skipped.push(skip(format!("{} is already in frate.toml", name)));
+                continue;
+            }
+            match self.manifest.add(&name, &pin.version) {
+                Ok(()) => report.added.push((name, pin.version.clone())),
+                Err(e) => report.skipped.push(skip(e.to_string())),
+            }
+        }
+        if !report.added.is_empty() {
+            self.save_manifest()?;
+        }
+        Ok(report)
+    }
+    /// Returns true if `name` is locked, and either not installed or installed from a different
+    /// archive than the one in `frate.lock`, according to its [`Receipt`](crate::Receipt).
+    pub fn needs_install(&self, name: &str) -> bool {
+        let Some(package) = get_locked(name, &self.lock) else {
+            return false;
+        };
+        let frate_dir = self.frate_dir();
+        if !is_installed(&frate_dir, name) {
+            return true;
+        }
+        matches!(Receipt::load(&frate_dir, name), Ok(Some(receipt)) if receipt.hash != package.hash)
+    }
+    /// Creates binary shims for locked packages that are not installed yet, so that calling them
+    /// installs the package. Does nothing unless `shim-mode = "binary"` and lazy installs are
+    /// [enabled](Settings::lazy_install_enabled).
+    ///
+    /// # Errors
+    /// Returns an error if `frate-shim` cannot be found or a shim cannot be written.
+    pub fn create_pending_shims(&self) -> Result<()> {
+        if self.settings.shim_mode != ShimMode::Binary || !self.settings.lazy_install_enabled() {
+            return Ok(());
+        }
+        let frate_dir = self.frate_dir();
+        let shims_dir = frate_dir.join("shims");
+        for package in self.packages() {
+            if is_installed(&frate_dir, &package.name) || self.is_optional(&package.name) {
+                continue;
+            }
+            let names: Vec<&String> = match package.bins.is_empty() {
+                true => vec![&package.name],
+                false => package.bins.values().collect(),
+            };
+            for name in names {
+                let shim_path = shims_dir.join(name);
+                if shim_config_path(&shim_path).exists() {
+                    continue;
+                }
+                std::fs::create_dir_all(&shims_dir)?;
+                // The binary is only known after extracting; `frate-shim` looks it up after installing.
+                let target = frate_dir.join("bin").join(&package.name);
+                let config = ShimConfig::for_package(&package, &frate_dir, &target);
+                create_binary_shim(&find_shim_binary()?, &shim_path, &config)?;
+            }
+        }
+        Ok(())
+    }
+    /// Installs a single locked package by name, or all locked packages that are not
+    /// [optional](Project::is_optional) if `name` is `None`.
+    ///
+    /// # Errors
+    /// Returns an error if the package is not locked or installation fails.
+    pub fn install(&self, name: Option<&str>, reporter: &dyn Reporter) -> Result<()> {
+        let frate_dir = ensure_frate_dirs(&self.root)?;
+        match name {
+            Some(name) => {
+                let package = self.package(name)
+                    .ok_or_else(|| Error::NotLocked(name.to_string()))?;
+                install_package(&package, &frate_dir, self.settings.shim_mode, Some(&self.vendor_dir()), self.fetcher(), reporter)?;
+            }
+            None => return self.install_groups(&[], &[], reporter),
+        }
+        write_env_scripts(&frate_dir, &self.packages())
+    }
+    /// Installs the locked packages [selected](Project::select_packages) by `groups` and `without`.
+    ///
+    /// # Errors
+    /// Returns an error if a group in `groups` does not exist or installation fails.
+    pub fn install_groups(&self, groups: &[String], without: &[String], reporter: &dyn Reporter) -> Result<()> {
+        let frate_dir = ensure_frate_dirs(&self.root)?;
+        for package in self.select_packages(groups, without)? {
+            install_package(&package, &frate_dir, self.settings.shim_mode, Some(&self.vendor_dir()), self.fetcher(), reporter)?;
+        }
+        write_env_scripts(&frate_dir, &self.packages())
+    }
+    /// Returns the names of all groups declared in `frate.toml`.
+    pub fn groups(&self) -> BTreeSet<String> {
+        self.effective_manifest().current_dependencies().values()
+            .flat_map(|dependency| dependency.groups().iter().cloned())
+            .collect()
+    }
+    /// Returns the locked packages in any of `groups` that are in none of `without`.
+    ///
+    /// If `groups` is empty, all packages that are not [optional](Project::is_optional) are
+    /// selected instead. Naming a group selects its optional packages as well. Packages that
+    /// `frate.toml` only declares for other platforms are never selected.
+    ///
+    /// # Errors
+    /// Returns an error if a group in `groups` is not declared by any dependency.
+    pub fn select_packages(&self, groups: &[String], without: &[String]) -> Result<Vec<LockedPackage>> {
+        let declared = self.groups();
+        if let Some(unknown) = groups.iter().find(|group| !declared.contains(*group)) {
+            return Err(Error::Other(format!("unknown group `{}`; add it to `groups` of a dependency", unknown)));
+        }
+        let manifest = self.effective_manifest();
+        let dependencies = manifest.current_dependencies();
+        Ok(self.packages().into_iter()
+            .filter(|package| {
+                if manifest.declares(&package.name) && !dependencies.contains_key(&package.name) {
+                    return false;
+                }
+                let member_of = dependencies.get(&package.name).map(Dependency::groups).unwrap_or_default();
+                let selected = match groups.is_empty() {
+                    true => !self.is_optional(&package.name),
+                    false => member_of.iter().any(|group| groups.contains(group)),
+                };
+                selected && !member_of.iter().any(|group| without.contains(group))
+            })
+            .collect())
+    }
+    /// Returns the packages `frate install` would install on the platform `triple`, for
+    /// [`export`](crate::export::export).
+    ///
+    /// For the current platform these are the [selected](Project::select_packages) locked packages.
+    /// For other platforms, the dependencies declared for `triple` are resolved against the
+    /// registry for it, at the version locked for the current platform where there is one.
+    ///
+    /// # Errors
+    /// Returns [`Error::NotLocked`] if a dependency for another platform has neither a lock
+    /// entry nor an exact version, or an error if it cannot be resolved.
+    pub fn export_packages(&self, triple: &str) -> Result<Vec<LockedPackage>> {
+        if triple == current_target_triple() {
+            return self.select_packages(&[], &[]);
+        }
+        let manifest = self.effective_manifest();
+        let mut packages = Vec::new();
+        for (name, dependency) in manifest.dependencies_for(&TargetInfo::from_triple(triple)) {
+            if dependency.is_optional() {
+                continue;
+            }
+            let version = match get_locked(&name, &self.lock) {
+                Some(locked) => locked.version.split('-').next().unwrap_or_default().to_string(),
+                None if is_valid_version(dependency.version()) => dependency.version().to_string(),
+                None => return Err(Error::NotLocked(name)),
+            };
+            let registry = manifest.registry_url(&dependency, &self.settings.registry)?;
+            let resolved = resolve_dependency_for_target(self.fetcher.as_ref(), registry, &name, &version, triple)?;
+            let mut package = LockedPackage {
+                name: resolved.name,
+                version: resolved.version,
+                source: resolved.url,
+                hash: resolved.hash,
+                env: resolved.env,
+                paths: resolved.paths,
+                ..Default::default()
+            };
+            if let Some(tool) = manifest.tools.get(&name) {
+                package.env.extend(tool.env.clone());
+                package.paths.extend(tool.paths.clone());
+                package.bins = tool.bins.clone();
+            }
+            for bin in dependency.bins() {
+                package.bins.entry(bin.clone()).or_insert_with(|| bin.clone());
+            }
+            packages.push(package);
+        }
+        Ok(packages)
+    }
+    /// Installs every locked package that is not installed yet and not [optional](Project::is_optional).
+    ///
+    /// Returns the names of the installed packages.
+    ///
+    /// # Errors
+    /// Returns an error if installation fails.
+    pub fn install_missing(&self, reporter: &dyn Reporter) -> Result<Vec<String>> {
+        let frate_dir = self.frate_dir();
+        let mut installed = Vec::new();
+        for package in self.packages() {
+            if !is_installed(&frate_dir, &package.name) && !self.is_optional(&package.name) {
+                let frate_dir = ensure_frate_dirs(&self.root)?;
+                install_package(&package, &frate_dir, self.settings.shim_mode, Some(&self.vendor_dir()), self.fetcher(), reporter)?;
+                installed.push(package.name.clone());
+            }
+        }
+        if !installed.is_empty() {
+            write_env_scripts(&frate_dir, &self.packages())?;
+        }
+        Ok(installed)
+    }
+    /// Uninstalls a single package by name, or all packages if `name` is `None`.
+    ///
+    /// # Errors
+    /// Returns an error if removing files fails.
+    pub fn uninstall(&self, name: Option<&str>, reporter: &dyn Reporter) -> Result<()> {
+        match name {
+            Some(name) => uninstall_package(&self.frate_dir(), name, reporter),
+            None => uninstall_packages(&self.frate_dir(), reporter),
+        }
+    }
+    /// Returns the status of every dependency declared in `frate.toml` for the current platform,
+    /// sorted by name.
+    pub fn list(&self) -> Vec<ToolStatus> {
+        let frate_dir = self.frate_dir();
+        let mut tools: Vec<ToolStatus> = self.effective_manifest().current_dependencies().iter()
+            .map(|(name, dependency)| {
+                let locked = get_locked(name, &self.lock);
+                let cached = locked.as_ref()
+                    .map(|locked| {
+                        is_cached(&format!("{}-{}", locked.name, locked.version)).unwrap_or(false)
+                    })
+                    .unwrap_or(false);
+                ToolStatus {
+                    name: name.clone(),
+                    version: dependency.version().to_string(),
+                    locked,
+                    cached,
+                    installed: is_installed(&frate_dir, name),
+                }
+            })
+            .collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+    /// Returns the paths of the installed binary and shim of a tool, if they exist.
+    ///
+    /// `name` is either the name of a package or of a shim, such as a name from
+    /// `[tools.<name>.bins]`; for a shim, its binary is returned.
+    ///
+    /// # Errors
+    /// Returns an error if the installation directory cannot be read.
+    pub fn which(&self, name: &str) -> Result<(Option<PathBuf>, Option<PathBuf>)> {
+        let frate_dir = self.frate_dir();
+        if get_locked(name, &self.lock).is_some() || frate_dir.join("bin").join(name).exists() {
+            return find_installed_paths(&frate_dir, name);
+        }
+        let shims_dir = frate_dir.join("shims");
+        for package in &self.lock.packages {
+            let Some(receipt) = Receipt::load(&frate_dir, &package.name)? else {
+                continue;
+            };
+            if !receipt.shims.iter().any(|shim| shim == name) {
+                continue;
+            }
+            let shim = find_shim(&shims_dir, name);
+            let exe = match &shim {
+                Some(shim) => read_shim(shim)?.map(|target| shims_dir.join(target)),
+                None => None,
+            };
+            return Ok((exe.filter(|exe| exe.exists()), shim));
+        }
+        Ok((None, None))
+    }
+    /// Returns the locked packages with the `[tools.<name>]` configuration of `frate.toml`
+    /// applied: manifest variables override registry variables of the same name,
+    /// manifest `PATH` directories come after those from the registry, and shim names are taken
+    /// from the manifest.
+    pub fn packages(&self) -> Vec<LockedPackage> {
+        let manifest = self.effective_manifest();
+        let dependencies = manifest.current_dependencies();
+        self.lock.packages.iter()
+            .map(|package| {
+                let mut package = package.clone();
+                if let Some(tool) = manifest.tools.get(&package.name) {
+                    package.env.extend(tool.env.clone());
+                    package.paths.extend(tool.paths.clone());
+                    package.bins = tool.bins.clone();
+                }
+                if let Some(dependency) = dependencies.get(&package.name) {
+                    for bin in dependency.bins() {
+                        package.bins.entry(bin.clone()).or_insert_with(|| bin.clone());
+                    }
+                }
+                package
+            })
+            .collect()
+    }
+    /// Returns true if `name` is declared as `optional` in `frate.toml`. Optional packages are
+    /// only installed when named explicitly.
+    pub fn is_optional(&self, name: &str) -> bool {
+        self.effective_manifest().current_dependencies().get(name).is_some_and(|dependency| dependency.is_optional())
+    }
+    /// Returns a single locked package with its `[tools.<name>]` configuration applied.
+    /// See [`Project::packages`].
+    pub fn package(&self, name: &str) -> Option<LockedPackage> {
+        self.packages().into_iter().find(|package| package.name == name)
+    }
+    /// Returns the environment that activates the project in a shell: `.frate/shims` and
+    /// the extra directories of all tools on `PATH`, and the variables of all tools.
+    pub fn environment(&self) -> Environment {
+        let mut environment = Environment::for_packages(&self.frate_dir(), &self.packages());
+        environment.paths.insert(0, self.frate_dir().join("shims"));
+        environment
+    }
+    /// Returns the environment of the packages in `groups`, or [`Project::environment`] if
+    /// `groups` is empty.
+    ///
+    /// Instead of `.frate/shims`, the environment puts `.frate/groups/<groups>` in `PATH`,
+    /// which is recreated with links to the shims of the selected packages only.
+    ///
+    /// # Errors
+    /// Returns an error if a group does not exist or the directory cannot be written.
+    pub fn group_environment(&self, groups: &[String]) -> Result<Environment> {
+        if groups.is_empty() {
+            return Ok(self.environment());
+        }
+        let packages = self.select_packages(groups, &[])?;
+        let frate_dir = self.frate_dir();
+        let shims_dir = frate_dir.join("shims");
+        let group_dir = frate_dir.join("groups").join(groups.join("+"));
+        if group_dir.exists() {
+            std::fs::remove_dir_all(&group_dir)?;
+        }
+        std::fs::create_dir_all(&group_dir)?;
+        for package in &packages {
+            let names = match Receipt::load(&frate_dir, &package.name)? {
+                Some(receipt) if !receipt.shims.is_empty() => receipt.shims,
+                _ => vec![package.name.clone()],
+            };
+            for name in names {
+                if let Some(shim) = find_shim(&shims_dir, &name) {
+                    create_shim(std::path::absolute(&shim)?, group_dir.join(&name))?;
+                }
+            }
+        }
+        let mut environment = Environment::for_packages(&frate_dir, &packages);
+        environment.paths.insert(0, group_dir);
+        Ok(environment)
+    }
+    /// Returns a [`Command`] that runs the installed binary of a tool, with the
+    /// [environment](Project::environment) of the project applied.
+    ///
+    /// The command inherits stdin, stdout and stderr, like [`Command::new`].
+    ///
+    /// # Errors
+    /// Returns [`Error::BinaryNotFound`] if the tool is not installed.
+    pub fn command(&self, name: &str) -> Result<Command> {
+        let (exe_path, _) = self.which(name)?;
+        let exe_path = exe_path.ok_or_else(|| Error::BinaryNotFound(name.to_string()))?;
+        let mut command = Command::new(exe_path);
+        self.environment().apply(&mut command)?;
+        Ok(command)
+    }
+    /// Checks the installed files of a single package, or of all installed locked packages
+    /// if `name` is `None`, against the digests recorded at install time.
+    ///
+    /// # Errors
+    /// Returns [`Error::NotLocked`] if `name` is neither locked nor installed, or an error if
+    /// a receipt cannot be read or an installed file cannot be hashed.
+    pub fn verify(&self, name: Option<&str>) -> Result<Vec<Verification>> {
+        let frate_dir = self.frate_dir();
+        let mut names: Vec<&str> = match name {
+            Some(name) if get_locked(name, &self.lock).is_none() && !is_installed(&frate_dir, name) => {
+                return Err(Error::NotLocked(name.to_string()));
+            }
+            Some(name) => vec![name],
+            None => self.lock.packages.iter()
+                .map(|package| package.name.as_str())
+                .filter(|name| is_installed(&frate_dir, name))
+                .collect(),
+        };
+        names.sort();
+        names.into_iter().map(|name| verify_package(&frate_dir, name)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::report::Silent;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_missing_manifest() {
+        let dir = tempdir().unwrap();
+        assert!(matches!(Project::load(dir.path()), Err(Error::ManifestNotFound(_))));
+    }
+
+    #[test]
+    fn test_discover() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("myproj");
+        Project::init(&root).unwrap();
+        let nested = root.join("src").join("nested");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(Project::discover(&nested).unwrap().root(), root);
+        assert!(matches!(Project::discover(dir.path()), Err(Error::ManifestNotFound(_))));
+    }
+
+    #[test]
+    fn test_init_and_load() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("myproj");
+        std::fs::create_dir(&root).unwrap();
+
+        let project = Project::init(&root).unwrap();
+        assert_eq!(project.manifest().project.name, "myproj");
+        assert!(project.frate_dir().join("bin").exists());
+        assert!(project.frate_dir().join("shims").exists());
+
+        let loaded = Project::load(&root).unwrap();
+        assert_eq!(loaded.root(), root.as_path());
+        assert!(loaded.lock().packages.is_empty());
+        assert_eq!(loaded.settings(), &Settings::default());
+    }
+
+    #[test]
+    fn test_list_reports_unlocked_tools() {
+        let dir = tempdir().unwrap();
+        let mut project = Project::init(dir.path()).unwrap();
+        project.manifest_mut().add("tool-b", "2.0.0").unwrap();
+        project.manifest_mut().add("tool-a", "1.0.0").unwrap();
+        project.save_manifest().unwrap();
+
+        let project = Project::load(dir.path()).unwrap();
+        let tools = project.list();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0].name, "tool-a");
+        assert!(tools[0].locked.is_none());
+        assert!(!tools[0].installed);
+    }
+
+    #[test]
+    fn test_install_unlocked_package_fails() {
+        let dir = tempdir().unwrap();
+        let project = Project::init(dir.path()).unwrap();
+        assert!(matches!(project.install(Some("missing"), &Silent), Err(Error::NotLocked(_))));
+    }
+
+    #[test]
+    fn test_tool_config_overrides_registry_environment() {
+        let dir = tempdir().unwrap();
+        let mut project = Project::init(dir.path()).unwrap();
+        project.lock_mut().packages.push(LockedPackage {
+            name: "tool".to_string(),
+            env: BTreeMap::from([
+                ("A".to_string(), "registry".to_string()),
+                ("B".to_string(), "registry".to_string()),
+            ]),
+            paths: vec!["{install_dir}/bin".to_string()],
+            ..Default::default()
+        });
+        project.manifest_mut().tools = toml::from_str("[tool]\nenv = { A = \"manifest\" }\npaths = [\"extra\"]\n").unwrap();
+
+        let package = project.package("tool").unwrap();
+        assert_eq!(package.env["A"], "manifest");
+        assert_eq!(package.env["B"], "registry");
+        assert_eq!(package.paths, vec!["{install_dir}/bin", "extra"]);
+        let environment = project.environment();
+        assert_eq!(environment.paths[0], project.frate_dir().join("shims"));
+        assert_eq!(environment.vars["A"], "manifest");
+    }
+
+    #[test]
+    fn test_select_packages_by_group() {
+        let dir = tempdir().unwrap();
+        let mut project = Project::init(dir.path()).unwrap();
+        project.manifest_mut().dependencies = toml::from_str(concat!(
+            "lint = { version = \"1.0.0\", groups = [\"ci\"] }\n",
+            "docs = { version = \"1.0.0\", groups = [\"docs\"], optional = true }\n",
+            "both = { version = \"1.0.0\", groups = [\"ci\", \"docs\"] }\n",
+            "plain = \"1.0.0\"\n",
+        )).unwrap();
+        for name in ["lint", "docs", "both", "plain"] {
+            project.lock_mut().packages.push(LockedPackage { name: name.to_string(), ..Default::default() });
+        }
+        let names = |groups: &[&str], without: &[&str]| -> Vec<String> {
+            let groups: Vec<String> = groups.iter().map(ToString::to_string).collect();
+            let without: Vec<String> = without.iter().map(ToString::to_string).collect();
+            project.select_packages(&groups, &without).unwrap().into_iter().map(|p| p.name).collect()
+        };
+        assert_eq!(names(&[], &[]), ["lint", "both", "plain"]);
+        assert_eq!(names(&["ci"], &[]), ["lint", "both"]);
+        assert_eq!(names(&["docs"], &[]), ["docs", "both"]);
+        assert_eq!(names(&[], &["docs"]), ["lint", "plain"]);
+        assert!(project.select_packages(&["missing".to_string()], &[]).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+use crate::error::Result;
+use crate::lock::LockedPackage;
+
+/// A record of an installed package, written to `.frate/receipts/<name>.toml` on install.
+///
+/// Receipts describe what is actually installed, which may differ from `frate.lock`
+/// after the lockfile was changed but `frate install` was not run yet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Receipt {
+    /// Name of the package.
+    pub name: String,
+    /// Installed version, as recorded in `frate.lock` at install time.
+    pub version: String,
+    /// URL the archive was downloaded from.
+    pub source: String,
+    /// SHA-256 hash of the installed archive.
+    pub hash: String,
+    /// Names of the shims created for the package in `.frate/shims`, without extension.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shims: Vec<String>,
+    /// SHA-256 digest of every installed file, keyed by its path relative to
+    /// `.frate/bin/<name>` with `/` as separator. Symlinks are recorded as `symlink:<target>`
+    /// instead, see [`hash_files`].
+    /// Empty for receipts written before digests were recorded.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl Receipt {
+    /// Creates a receipt for a package installed from the given lock entry.
+    pub fn for_package(package: &LockedPackage) -> Receipt {
+        Receipt {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            source: package.source.clone(),
+            hash: package.hash.clone(),
+            shims: Vec::new(),
+            files: BTreeMap::new(),
+        }
+    }
+    /// Records the digests of all files in `install_dir`.
+    ///
+    /// # Errors
+    /// Returns an error if the directory cannot be walked or a file cannot be read.
+    pub fn with_files(mut self, install_dir: &Path) -> Result<Receipt> {
+        self.files = hash_files(install_dir)?;
+        Ok(self)
+    }
+    /// Returns the path of the receipt of `name` inside the `.frate` directory.
+    pub fn path(frate_dir: &Path, name: &str) -> PathBuf {
+        frate_dir.join("receipts").join(format!("{}.toml", name))
+    }
+    /// Loads the receipt of `name`. Returns `None` if the package has no receipt.
+    ///
+    /// # Errors
+    /// Returns an error if the receipt exists but cannot be read or parsed.
+    pub fn load(frate_dir: &Path, name: &str) -> Result<Option<Receipt>> {
+        let path = Receipt::path(frate_dir, name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(toml::from_str(&content)?))
+    }
+    /// Writes the receipt to `.frate/receipts/<name>.toml`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, frate_dir: &Path) -> Result<()> {
+        let path = Receipt::path(frate_dir, &self.name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    /// Removes the receipt of `name`, if it exists.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but cannot be removed.
+    pub fn remove(frate_dir: &Path, name: &str) -> Result<()> {
+        let path = Receipt::path(frate_dir, name);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the SHA-256 digest of every file below `dir`, keyed by its relative path with `/` as separator.
+///
+/// Symlinks are not followed: they are recorded as `symlink:<target>`, so links to directories
+/// and dangling links can be recorded and a changed link target is detected.
+///
+/// # Errors
+/// Returns an error if the directory cannot be walked, or a file or link cannot be read.
+pub fn hash_files(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(dir).follow_links(false) {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        let key = relative.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if entry.path_is_symlink() {
+            let target = std::fs::read_link(entry.path())?;
+            files.insert(key, format!("symlink:{}", target.to_string_lossy()));
+            continue;
+        }
+        let bytes = std::fs::read(entry.path())?;
+        files.insert(key, hex::encode(Sha256::digest(&bytes)));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_load_remove() {
+        let dir = tempdir().unwrap();
+        let receipt = Receipt {
+            name: "tool".to_string(),
+            version: "1.0.0".to_string(),
+            source: "https://example.com/tool.tar.gz".to_string(),
+            hash: "abc".to_string(),
+            shims: vec!["tool".to_string()],
+            files: BTreeMap::from([("bin/tool".to_string(), "def".to_string())]),
+        };
+        receipt.save(dir.path()).unwrap();
+        assert_eq!(Receipt::load(dir.path(), "tool").unwrap(), Some(receipt));
+
+        Receipt::remove(dir.path(), "tool").unwrap();
+        assert_eq!(Receipt::load(dir.path(), "tool").unwrap(), None);
+    }
+
+    #[test]
+    fn test_hash_files() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub").join("a"), b"a").unwrap();
+        let files = hash_files(dir.path()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files["sub/a"], hex::encode(Sha256::digest(b"a")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hash_files_records_symlinks() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("lib")).unwrap();
+        std::os::unix::fs::symlink("lib", dir.path().join("lib64")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.path().join("dangling")).unwrap();
+        let files = hash_files(dir.path()).unwrap();
+        assert_eq!(files["lib64"], "symlink:lib");
+        assert_eq!(files["dangling"], "symlink:missing");
+    }
+}
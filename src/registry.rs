@@ -1,7 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use serde::Deserialize;
-use crate::util::expand_version;
-use anyhow::{bail, Result};
+use semver::{Version, VersionReq};
+use crate::util::{current_target_triple, is_valid_version};
+use crate::error::{Error, Result};
+use crate::fetch::Fetcher;
 
 /// A tool as defined in the frate registry.
 ///
@@ -13,7 +15,14 @@ pub struct RegistryTool {
     /// The GitHub repository of the tool, e.g. "user/repo".
     pub repo: String,
     /// A map of version identifiers to their release information.
-    pub releases: HashMap<String, ReleaseInfo>
+    pub releases: HashMap<String, ReleaseInfo>,
+    /// Environment variables the tool needs, e.g. `GOROOT`.
+    /// Values may contain the `{install_dir}` placeholder.
+    #[serde(default, deserialize_with = "crate::env::deserialize_env_vars")]
+    pub env: BTreeMap<String, String>,
+    /// Directories to add to `PATH` besides the shims, e.g. `{install_dir}/go/bin`.
+    #[serde(default)]
+    pub paths: Vec<String>,
 }
 
 /// Metadata for a specific release of a tool.
@@ -50,6 +59,10 @@ pub struct ResolvedDependency {
     pub url: String,
     /// SHA-256 hash of the binary archive.
     pub hash: String,
+    /// Environment variables declared by the registry entry.
+    pub env: BTreeMap<String, String>,
+    /// Extra `PATH` directories declared by the registry entry.
+    pub paths: Vec<String>,
 }
 /// Resolves a tool version by looking it up in the registry.
 ///
@@ -58,8 +71,11 @@ pub struct ResolvedDependency {
 ///
 /// # Arguments
 ///
+/// * `fetcher` – Used to download the registry entry.
+/// * `registry` – Base URL of the registry (see [`Settings::registry`](crate::Settings::registry)).
 /// * `tool_name` – The name of the tool to resolve (e.g., `"ripgrep"`).
-/// * `version` – The version string to resolve. Can be a short version like `"1.2.3"` or a fully qualified triple like `"1.2.3-x86_64-unknown-linux-musl"`.
+/// * `version` – The version string to resolve. Can be a short version like `"1.2.3"`, a fully qualified triple like `"1.2.3-x86_64-unknown-linux-musl"`,
+///   or a semver requirement like `"^1.2"`, which resolves to the newest matching release for the current platform.
 ///
 /// # Errors
 ///
@@ -68,19 +84,40 @@ pub struct ResolvedDependency {
 /// # Example
 ///
 /// ```no_run
-/// use frate::resolve_dependency;
+/// use frate::{resolve_dependency, ReqwestFetcher, DEFAULT_REGISTRY};
 ///
-/// let dep = resolve_dependency("ripgrep", "14.0.0").unwrap();
+/// let dep = resolve_dependency(&ReqwestFetcher::new(), DEFAULT_REGISTRY, "ripgrep", "14.0.0").unwrap();
 /// assert!(dep.url.ends_with(".tar.gz") || dep.url.ends_with(".zip"));
 /// ```
 pub fn resolve_dependency(
+    fetcher: &dyn Fetcher,
+    registry: &str,
     tool_name: &str,
     version: &str
 ) -> Result<ResolvedDependency> {
-    let tool = fetch_registry(tool_name)?;
-    
-    let full_version = expand_version(version);
-    
+    resolve_dependency_for_target(fetcher, registry, tool_name, version, &current_target_triple())
+}
+
+/// Resolves a tool version like [`resolve_dependency`], but for the platform `triple`
+/// instead of the current one.
+///
+/// # Errors
+///
+/// Returns an error if the tool or the requested version cannot be found or fetched.
+pub fn resolve_dependency_for_target(
+    fetcher: &dyn Fetcher,
+    registry: &str,
+    tool_name: &str,
+    version: &str,
+    triple: &str
+) -> Result<ResolvedDependency> {
+    let tool = fetch_registry(fetcher, registry, tool_name)?;
+
+    let full_version = match select_version(&tool, version, triple) {
+        Some(selected) => selected,
+        None => format!("{}-{}", version, triple),
+    };
+
     let release = tool.releases.get(&full_version)
         .or_else(|| {
             if full_version.contains("musl") {
@@ -95,24 +132,52 @@ pub fn resolve_dependency(
                 None
             }
         })
-        .ok_or(anyhow::anyhow!("tool version not found in registry: {}", full_version))?;
+        .ok_or_else(|| Error::VersionNotFound {
+            name: tool_name.to_string(),
+            version: full_version.clone(),
+        })?;
 
     let resolved = ResolvedDependency {
         name: tool.name,
         version: full_version.to_string(),
         url: release.url.clone(),
         hash: release.hash.clone(),
+        env: tool.env,
+        paths: tool.paths,
     };
     Ok(resolved)
 }
 
+/// If `version` is a semver requirement rather than an exact version, returns the newest
+/// version of `tool` that matches it and has a release for `triple` (or its gnu/musl
+/// counterpart), with the target triple appended.
+fn select_version(tool: &RegistryTool, version: &str, triple: &str) -> Option<String> {
+    if is_valid_version(version) {
+        return None;
+    }
+    let requirement = VersionReq::parse(version).ok()?;
+    let alternative = if triple.contains("musl") { triple.replace("musl", "gnu") } else { triple.replace("gnu", "musl") };
+    tool.releases.keys()
+        .filter_map(|key| {
+            let base = key.strip_suffix(&format!("-{}", triple))
+                .or_else(|| key.strip_suffix(&format!("-{}", alternative)))?;
+            let parsed = Version::parse(base).ok()?;
+            requirement.matches(&parsed).then_some(parsed)
+        })
+        .max()
+        .map(|selected| format!("{}-{}", selected, triple))
+}
+
 /// Fetches a tool's metadata from the frate registry.
 ///
-/// This loads a JSON file hosted in the GitHub frate registry under:
+/// This loads the JSON file `<registry>/tools/<tool>.json`. For the default registry
+/// this is hosted in the GitHub frate registry under:
 /// `https://github.com/konni332/frate-registry/tools/<tool>.json`
 ///
 /// # Arguments
 ///
+/// * `fetcher` – Used to download the registry entry.
+/// * `registry` – Base URL of the registry.
 /// * `tool_name` – The name of the tool to fetch (e.g., `"ripgrep"`).
 ///
 /// # Returns
@@ -122,18 +187,125 @@ pub fn resolve_dependency(
 /// # Errors
 ///
 /// Returns an error if the registry cannot be fetched or parsed.
-pub fn fetch_registry(tool_name: &str) -> Result<RegistryTool> {
-    let url = format!(
-        "https://raw.githubusercontent.com/konni332/frate-registry/refs/heads/master/tools/{}.json",
-        tool_name
-    );
-    let response = reqwest::blocking::get(&url)?;
-
-    if !response.status().is_success() {
-        bail!("Failed to fetch {} from registry", tool_name);
-    }
-    let body = response.text()?;
-    let tool: RegistryTool = serde_json::from_str(&body)?;
+pub fn fetch_registry(fetcher: &dyn Fetcher, registry: &str, tool_name: &str) -> Result<RegistryTool> {
+    let url = format!("{}/tools/{}.json", registry.trim_end_matches('/'), tool_name);
+    let body = fetcher.fetch(&url)?
+        .ok_or_else(|| Error::RegistryNotFound(tool_name.to_string()))?;
+    let tool: RegistryTool = serde_json::from_slice(&body)?;
     Ok(tool)
 }
 
+/// An entry of the registry index, listing a registered tool.
+#[derive(Debug, Deserialize)]
+pub struct IndexEntry {
+    /// The name of the tool.
+    pub name: String,
+    /// The GitHub repository of the tool.
+    pub repo: String,
+}
+
+/// The registry index (`<registry>/registry.json`), listing all registered tools.
+#[derive(Debug, Deserialize)]
+pub struct RegistryIndex {
+    /// All tools available in the registry.
+    pub registered: Vec<IndexEntry>,
+}
+
+/// Fetches the index of all tools available in the registry.
+///
+/// # Errors
+///
+/// Returns an error if the index cannot be fetched or parsed.
+pub fn fetch_index(fetcher: &dyn Fetcher, registry: &str) -> Result<RegistryIndex> {
+    let url = format!("{}/registry.json", registry.trim_end_matches('/'));
+    let body = fetcher.fetch(&url)?
+        .ok_or_else(|| Error::Network(format!("registry index not found: {}", url)))?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::testing::InMemory;
+    use crate::util::expand_version;
+
+    fn registry() -> InMemory {
+        let tool = format!(
+            r#"{{
+                "name": "tool",
+                "repo": "https://github.com/example/tool",
+                "releases": {{
+                    "1.0.0-{triple}": {{ "url": "https://example.com/tool.tar.gz", "hash": "abc" }},
+                    "1.2.0-{triple}": {{ "url": "https://example.com/tool-1.2.0.tar.gz", "hash": "def" }},
+                    "0.9.0-{triple}": {{ "url": "https://example.com/tool-0.9.0.tar.gz", "hash": "ghi" }}
+                }}
+            }}"#,
+            triple = current_target_triple()
+        );
+        let mut files = HashMap::new();
+        files.insert("https://registry.test/tools/tool.json".to_string(), tool.into_bytes());
+        InMemory(files)
+    }
+
+    #[test]
+    fn test_resolve_dependency() {
+        let resolved = resolve_dependency(&registry(), "https://registry.test/", "tool", "1.0.0").unwrap();
+        assert_eq!(resolved.name, "tool");
+        assert_eq!(resolved.url, "https://example.com/tool.tar.gz");
+        assert_eq!(resolved.hash, "abc");
+    }
+
+    #[test]
+    fn test_resolve_version_requirement() {
+        let resolved = resolve_dependency(&registry(), "https://registry.test", "tool", "^1.0").unwrap();
+        assert_eq!(resolved.version, expand_version("1.2.0"));
+        assert_eq!(resolved.hash, "def");
+        let result = resolve_dependency(&registry(), "https://registry.test", "tool", ">=3");
+        assert!(matches!(result, Err(Error::VersionNotFound { .. })));
+    }
+
+    #[test]
+    fn test_resolve_for_target() {
+        let mut registry = registry();
+        let tool = r#"{
+            "name": "tool",
+            "repo": "https://github.com/example/tool",
+            "releases": {
+                "1.0.0-aarch64-apple-darwin": { "url": "https://example.com/tool-mac.tar.gz", "hash": "mac" },
+                "1.0.0-x86_64-unknown-linux-musl": { "url": "https://example.com/tool-musl.tar.gz", "hash": "musl" }
+            }
+        }"#;
+        registry.0.insert("https://registry.test/tools/tool.json".to_string(), tool.as_bytes().to_vec());
+        let resolved = resolve_dependency_for_target(&registry, "https://registry.test", "tool", "^1", "aarch64-apple-darwin").unwrap();
+        assert_eq!((resolved.version.as_str(), resolved.hash.as_str()), ("1.0.0-aarch64-apple-darwin", "mac"));
+        let resolved = resolve_dependency_for_target(&registry, "https://registry.test", "tool", "1.0.0", "x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(resolved.hash, "musl");
+    }
+
+    #[test]
+    fn test_resolve_invalid_env_name() {
+        let mut registry = registry();
+        let tool = r#"{
+            "name": "tool",
+            "repo": "https://github.com/example/tool",
+            "releases": {},
+            "env": { "X; rm -rf ~": "1" }
+        }"#;
+        registry.0.insert("https://registry.test/tools/tool.json".to_string(), tool.as_bytes().to_vec());
+        let result = resolve_dependency(&registry, "https://registry.test", "tool", "1.0.0");
+        assert!(matches!(result, Err(Error::Parse(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn test_resolve_unknown_tool() {
+        let result = resolve_dependency(&registry(), "https://registry.test", "other", "1.0.0");
+        assert!(matches!(result, Err(Error::RegistryNotFound(_))));
+    }
+
+    #[test]
+    fn test_resolve_unknown_version() {
+        let result = resolve_dependency(&registry(), "https://registry.test", "tool", "2.0.0");
+        assert!(matches!(result, Err(Error::VersionNotFound { .. })));
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+/// A progress event emitted by library operations such as installing or syncing.
+///
+/// The library never prints on its own; every status message is handed to a [`Reporter`],
+/// which decides whether and how to display it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The global cache directory did not exist and was created.
+    CacheCreated { path: PathBuf },
+    /// An archive is being downloaded.
+    Downloading { url: String },
+    /// An archive is being extracted into `dest`. `cached` is true if it came from the global cache
+    /// or the vendor directory.
+    Extracting { source: String, dest: PathBuf, cached: bool },
+    /// A downloaded archive is being stored in the global cache.
+    Caching { url: String },
+    /// A package was installed successfully.
+    Installed { name: String },
+    /// A package is being uninstalled. `None` means all packages.
+    Uninstalling { name: Option<String> },
+    /// An uninstallation finished. `None` means all packages.
+    Uninstalled { name: Option<String> },
+    /// A dependency from `frate.toml` could not be resolved against the registry.
+    ResolveFailed { name: String, error: String },
+    /// The archive of a package was copied to the vendor directory as `path`.
+    Vendored { name: String, path: PathBuf },
+}
+
+/// Receives progress [`Event`]s from library operations.
+///
+/// Implemented for any `Fn(Event)`, so a closure can be passed wherever a reporter is expected.
+///
+/// # Example
+///
+/// ```no_run
+/// use frate::{Event, Project};
+///
+/// let project = Project::load(".").unwrap();
+/// project.install(None, &|event: Event| eprintln!("{:?}", event)).unwrap();
+/// ```
+pub trait Reporter {
+    /// Handles a single event.
+    fn report(&self, event: Event);
+}
+
+/// A [`Reporter`] that discards all events.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Silent;
+
+impl Reporter for Silent {
+    fn report(&self, _event: Event) {}
+}
+
+impl<F: Fn(Event)> Reporter for F {
+    fn report(&self, event: Event) {
+        self(event)
+    }
+}
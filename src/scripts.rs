@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::env::Environment;
+
+/// A script from the `[scripts]` table of `frate.toml`.
+///
+/// A script is either a plain command line, or a table with the command line and
+/// the names of scripts that must run before it:
+///
+/// ```toml
+/// [scripts]
+/// fmt = "just fmt"
+/// lint = { run = "rg TODO src && just check", depends = ["fmt"] }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Script {
+    /// A command line, e.g. `"just check"`.
+    Command(String),
+    /// A command line with dependencies.
+    Detailed {
+        /// The command line.
+        run: String,
+        /// Scripts that run before this one.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        depends: Vec<String>,
+    },
+}
+
+impl Script {
+    /// Returns the command line of the script.
+    pub fn run(&self) -> &str {
+        match self {
+            Script::Command(run) => run,
+            Script::Detailed { run, .. } => run,
+        }
+    }
+    /// Returns the names of the scripts that run before this one.
+    pub fn depends(&self) -> &[String] {
+        match self {
+            Script::Command(_) => &[],
+            Script::Detailed { depends, .. } => depends,
+        }
+    }
+}
+
+/// Returns the scripts to run for `name`, in order.
+///
+/// Dependencies run first, then the `pre<name>` hook if it exists, then the script itself.
+/// Every script runs at most once, even if several scripts depend on it.
+///
+/// # Errors
+/// Returns [`Error::ScriptNotFound`] if `name` or a dependency does not exist,
+/// and [`Error::ScriptCycle`] if scripts depend on each other.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use frate::{plan_script, Script};
+///
+/// let scripts = BTreeMap::from([
+///     ("prelint".to_string(), Script::Command("just fmt".to_string())),
+///     ("lint".to_string(), Script::Command("just check".to_string())),
+/// ]);
+/// let plan = plan_script(&scripts, "lint").unwrap();
+/// assert_eq!(plan, vec!["prelint", "lint"]);
+/// ```
+pub fn plan_script(scripts: &BTreeMap<String, Script>, name: &str) -> Result<Vec<String>> {
+    let mut plan = Vec::new();
+    let mut visiting = Vec::new();
+    visit(scripts, name, &mut visiting, &mut plan)?;
+    Ok(plan)
+}
+
+fn visit(
+    scripts: &BTreeMap<String, Script>,
+    name: &str,
+    visiting: &mut Vec<String>,
+    plan: &mut Vec<String>,
+) -> Result<()> {
+    if plan.iter().any(|planned| planned == name) {
+        return Ok(());
+    }
+    if visiting.iter().any(|visited| visited == name) {
+        visiting.push(name.to_string());
+        return Err(Error::ScriptCycle(visiting.join(" -> ")));
+    }
+    let script = scripts.get(name).ok_or_else(|| Error::ScriptNotFound(name.to_string()))?;
+    visiting.push(name.to_string());
+    for dependency in script.depends() {
+        visit(scripts, dependency, visiting, plan)?;
+    }
+    let hook = format!("pre{}", name);
+    if scripts.contains_key(&hook) {
+        visit(scripts, &hook, visiting, plan)?;
+    }
+    visiting.pop();
+    plan.push(name.to_string());
+    Ok(())
+}
+
+/// Returns a [`Command`] that runs a script line in the system shell (`sh -c` or `cmd /C`),
+/// in `project_root`, with `environment` applied.
+///
+/// `args` are appended to the script line.
+///
+/// # Errors
+/// Returns an error if the new `PATH` cannot be built.
+pub fn script_command(
+    project_root: &Path,
+    environment: &Environment,
+    run: &str,
+    args: &[String],
+) -> Result<Command> {
+    #[cfg(windows)]
+    let command = {
+        let mut line = run.to_string();
+        for arg in args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(line);
+        command
+    };
+    #[cfg(not(windows))]
+    let command = {
+        let mut command = Command::new("sh");
+        if args.is_empty() {
+            command.arg("-c").arg(run);
+        }
+        else {
+            // Passing the arguments as positional parameters keeps their boundaries intact.
+            command.arg("-c").arg(format!("{} \"$@\"", run)).arg("sh").args(args);
+        }
+        command
+    };
+    let mut command = command;
+    command.current_dir(project_root);
+    environment.apply(&mut command)?;
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(entries: &[(&str, &str, &[&str])]) -> BTreeMap<String, Script> {
+        entries.iter()
+            .map(|(name, run, depends)| {
+                let script = Script::Detailed {
+                    run: run.to_string(),
+                    depends: depends.iter().map(|d| d.to_string()).collect(),
+                };
+                (name.to_string(), script)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_dependencies_and_hooks() {
+        let scripts = scripts(&[
+            ("build", "just build", &["fmt"]),
+            ("fmt", "just fmt", &[]),
+            ("prebuild", "echo pre", &["fmt"]),
+            ("test", "just test", &["build", "fmt"]),
+        ]);
+        assert_eq!(plan_script(&scripts, "test").unwrap(), vec!["fmt", "prebuild", "build", "test"]);
+    }
+
+    #[test]
+    fn test_plan_errors() {
+        let scripts = scripts(&[
+            ("a", "", &["b"]),
+            ("b", "", &["a"]),
+            ("c", "", &["missing"]),
+        ]);
+        assert!(matches!(plan_script(&scripts, "a"), Err(Error::ScriptCycle(cycle)) if cycle == "a -> b -> a"));
+        assert!(matches!(plan_script(&scripts, "c"), Err(Error::ScriptNotFound(name)) if name == "missing"));
+    }
+
+    #[test]
+    fn test_parse_scripts() {
+        let parsed: BTreeMap<String, Script> = toml::from_str(
+            "fmt = \"just fmt\"\nlint = { run = \"just check\", depends = [\"fmt\"] }\n"
+        ).unwrap();
+        assert_eq!(parsed["fmt"], Script::Command("just fmt".to_string()));
+        assert_eq!(parsed["lint"].run(), "just check");
+        assert_eq!(parsed["lint"].depends(), ["fmt".to_string()]);
+    }
+}
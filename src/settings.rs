@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Base URL of the default frate registry.
+pub const DEFAULT_REGISTRY: &str = "https://raw.githubusercontent.com/konni332/frate-registry/refs/heads/master";
+
+/// Default of [`Settings::vendor_dir`].
+pub const DEFAULT_VENDOR_DIR: &str = "vendor/frate";
+
+/// Project-level settings, read from the optional `[settings]` table in `frate.toml`.
+///
+/// ```toml
+/// [settings]
+/// registry = "https://example.com/frate-registry"
+/// shim-mode = "binary"
+/// lazy-install = false
+/// vendor-dir = "third_party/frate"
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Settings {
+    /// Base URL of the registry used to resolve tools. Tool metadata is fetched from
+    /// `<registry>/tools/<name>.json`.
+    pub registry: String,
+    /// How shims in `.frate/shims` are created.
+    #[serde(skip_serializing_if = "ShimMode::is_default")]
+    pub shim_mode: ShimMode,
+    /// Whether binary shims install their package on first use if it is locked but not
+    /// installed, or installed in a different version than locked. Can also be disabled
+    /// with `FRATE_LAZY_INSTALL=0`, e.g. in CI.
+    #[serde(skip_serializing_if = "is_true")]
+    pub lazy_install: bool,
+    /// Directory, relative to the project root, that `frate vendor` copies the locked archives to.
+    /// Installs use an archive from there before the global cache and the network.
+    #[serde(skip_serializing_if = "is_default_vendor_dir")]
+    pub vendor_dir: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            registry: DEFAULT_REGISTRY.to_string(),
+            shim_mode: ShimMode::default(),
+            lazy_install: true,
+            vendor_dir: DEFAULT_VENDOR_DIR.to_string(),
+        }
+    }
+}
+
+/// How a tool's shim in `.frate/shims` forwards to its binary.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShimMode {
+    /// A symlink to the binary on Unix, a `.bat` script on Windows. Tools with environment
+    /// variables or extra `PATH` directories get a wrapper script instead.
+    #[default]
+    Symlink,
+    /// A copy (or hardlink) of the `frate-shim` executable, which reads the target, arguments
+    /// and environment from a `<name>.shim` file next to it. See [`ShimConfig`](crate::ShimConfig).
+    Binary,
+}
+
+impl ShimMode {
+    fn is_default(&self) -> bool {
+        *self == ShimMode::default()
+    }
+}
+
+impl Settings {
+    /// Returns `true` if all settings have their default values.
+    /// Used to omit an empty `[settings]` table when saving `frate.toml`.
+    pub fn is_default(&self) -> bool {
+        *self == Settings::default()
+    }
+    /// Returns `true` if shims should install missing packages: [`Settings::lazy_install`]
+    /// is set and `FRATE_LAZY_INSTALL` is not `0` or `false`.
+    pub fn lazy_install_enabled(&self) -> bool {
+        let disabled = std::env::var("FRATE_LAZY_INSTALL")
+            .is_ok_and(|value| value == "0" || value.eq_ignore_ascii_case("false"));
+        self.lazy_install && !disabled
+    }
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn is_default_vendor_dir(value: &str) -> bool {
+    value == DEFAULT_VENDOR_DIR
+}
@@ -1,12 +1,16 @@
-use std::path::Path;
-use anyhow::Result;
-#[cfg(windows)]
-use crate::is_power_shell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use crate::env::{anchor_path, expand_install_dir, posix_quote, powershell_quote, Environment, INSTALL_DIR};
+use crate::error::{Error, Result};
+use crate::lock::LockedPackage;
 
 /// Creates a platform-specific "shim" to forward execution to a target binary.
 ///
 /// On Unix systems, this creates a symbolic link (`symlink`) at `shim_path` pointing to `target`.
 /// On Windows, it creates a `.bat` script at `shim_path` (with a `.bat` extension) that calls the `target`.
+/// An existing shim at `shim_path` is replaced.
 ///
 /// # Arguments
 ///
@@ -31,6 +35,7 @@ pub fn create_shim<P: AsRef<Path>>(
     target: P,
     shim_path: P
 ) -> Result<()> {
+    remove_shim(shim_path.as_ref())?;
     #[cfg(unix)]
     {
         use std::os::unix::fs::symlink;
@@ -48,156 +53,559 @@ pub fn create_shim<P: AsRef<Path>>(
     Ok(())
 }
 
-
-/// Writes a Unix shell script to activate the Frate environment.
-///
-/// The script sets the `PATH` to include the `./.frate/shims` directory,
-/// prints a message, and launches a new interactive shell.
+/// Creates a shim that sets up `environment` before running `target`.
 ///
-/// The script is written to `./.frate/activate` and marked as executable.
+/// If the environment is empty, this is the same as [`create_shim`]. Otherwise a script
+/// is written instead of a symlink: a `sh` script on Unix, a `.bat` script on Windows.
 ///
 /// # Errors
 ///
-/// Returns an error if writing the file or setting the executable bit fails.
+/// Returns an error if the shim cannot be written.
+pub fn create_shim_with_env<P: AsRef<Path>>(
+    target: P,
+    shim_path: P,
+    environment: &Environment
+) -> Result<()> {
+    if environment.is_empty() {
+        return create_shim(target, shim_path);
+    }
+    let target = target.as_ref().display().to_string();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut script = String::from("#!/bin/sh\n# Generated by frate.\n");
+        for (name, value) in &environment.vars {
+            script.push_str(&format!("export {}={}\n", name, posix_quote(value)));
+        }
+        for path in environment.paths.iter().rev() {
+            script.push_str(&format!("PATH={}:\"$PATH\"\n", posix_quote(&path.display().to_string())));
+        }
+        script.push_str(&format!("export PATH\nexec {} \"$@\"\n", posix_quote(&target)));
+        let shim_path = shim_path.as_ref();
+        remove_shim(shim_path)?;
+        std::fs::write(shim_path, script)?;
+        std::fs::set_permissions(shim_path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(windows)]
+    {
+        let mut script = String::from("@echo off\r\nsetlocal\r\n");
+        for (name, value) in &environment.vars {
+            script.push_str(&format!("set \"{}={}\"\r\n", name, value.replace('%', "%%")));
+        }
+        for path in environment.paths.iter().rev() {
+            script.push_str(&format!("set \"PATH={};%PATH%\"\r\n", path.display()));
+        }
+        script.push_str(&format!("call \"{}\" %*\r\n", target));
+        remove_shim(shim_path.as_ref())?;
+        std::fs::write(shim_path.as_ref().with_extension("bat"), script)?;
+    }
+    Ok(())
+}
+
+/// The configuration of a binary shim, read by `frate-shim` from the `<name>.shim` file
+/// next to it (see [`shim_config_path`]).
 ///
-/// # Example
+/// Relative paths are relative to the directory of the shim, so the `.frate` directory can be
+/// moved without breaking its shims. `{install_dir}` in [`ShimConfig::env`] and
+/// [`ShimConfig::paths`] expands to [`ShimConfig::install_dir`].
 ///
-/// ```no_run
-/// #[cfg(target_family = "unix")]
-/// {
-/// use frate::write_unix_activate;
-/// write_unix_activate().unwrap();
-/// }
+/// ```toml
+/// target = "../bin/just/just"
+/// install-dir = "../bin/just"
+/// package = "just"
+/// paths = ["{install_dir}/extra"]
+///
+/// [env]
+/// JUST_HOME = "{install_dir}"
 /// ```
-#[cfg(target_family = "unix")]
-pub fn write_unix_activate() -> std::io::Result<()> {
-    let content = r#"#!/bin/sh
-    export PATH="$(pwd)/.frate/shims:$PATH"
-    echo "Frate shell activated. Type 'exit' to leave."
-    exec "$SHELL"
-    "#;
-
-    std::fs::write("./.frate/activate", content)?;
-    std::process::Command::new("chmod")
-        .arg("+x")
-        .arg("./.frate/activate")
-        .status()?; // safer than `.output()` here
-    Ok(())
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ShimConfig {
+    /// The executable to run.
+    pub target: PathBuf,
+    /// The install directory of the tool. Defaults to the directory of the target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_dir: Option<PathBuf>,
+    /// The locked package the shim belongs to. If set, `frate-shim` installs the package
+    /// before running it when it is missing or outdated (see [`Settings::lazy_install`](crate::Settings::lazy_install)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// Arguments passed to the target before the arguments of the shim.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Directories prepended to `PATH`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Environment variables to set.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
 }
 
-/// Writes a Windows batch or PowerShell script to activate the Frate environment.
-///
-/// If PowerShell is detected, a `.ps1` script is written. Otherwise, a `.bat` file is created.
-/// Both scripts add `.\.frate\shims` to the `PATH` and show an activation message.
+impl ShimConfig {
+    /// Creates the configuration of the shim in `.frate/shims` for `package`, whose binary is `target`.
+    pub fn for_package(package: &LockedPackage, frate_dir: &Path, target: &Path) -> ShimConfig {
+        // Shims live in `.frate/shims`, so `..` is the `.frate` directory.
+        let relative = |path: &Path| match path.strip_prefix(frate_dir) {
+            Ok(path) => Path::new("..").join(path),
+            Err(_) => path.to_path_buf(),
+        };
+        ShimConfig {
+            target: relative(target),
+            install_dir: Some(relative(&frate_dir.join("bin").join(&package.name))),
+            package: Some(package.name.clone()),
+            args: Vec::new(),
+            paths: package.paths.iter().map(|path| anchor_path(path)).collect(),
+            env: package.env.clone(),
+        }
+    }
+    /// Reads a shim configuration.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ShimConfig> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+    /// Writes the shim configuration to `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+    /// Returns the [`Command`] that runs the target, for a shim located in `shim_dir`.
+    ///
+    /// # Errors
+    /// Returns an error if the new `PATH` cannot be built.
+    pub fn command(&self, shim_dir: &Path) -> Result<Command> {
+        let target = shim_dir.join(&self.target);
+        let install_dir = match &self.install_dir {
+            Some(install_dir) => shim_dir.join(install_dir),
+            None => target.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        let environment = Environment {
+            paths: self.paths.iter()
+                .map(|path| shim_dir.join(expand_install_dir(path, &install_dir)))
+                .collect(),
+            vars: self.env.iter()
+                .map(|(name, value)| (name.clone(), expand_install_dir(value, &install_dir)))
+                .collect(),
+        };
+        let mut command = Command::new(target);
+        command.args(&self.args);
+        environment.apply(&mut command)?;
+        Ok(command)
+    }
+}
+
+/// Returns the path of the configuration file of the binary shim at `shim_path`:
+/// `<name>.shim` in the same directory, without the `.exe` extension on Windows.
+pub fn shim_config_path<P: AsRef<Path>>(shim_path: P) -> PathBuf {
+    let shim_path = shim_path.as_ref();
+    let name = shim_path.file_name().unwrap_or_default().to_string_lossy();
+    let name = name.strip_suffix(std::env::consts::EXE_SUFFIX).unwrap_or(&name);
+    shim_path.with_file_name(format!("{}.shim", name))
+}
+
+/// Creates a binary shim: a hardlink to `shim_binary` (or a copy, if hardlinking fails) at
+/// `shim_path`, with `config` written next to it. On Windows, `.exe` is appended to `shim_path`.
+/// An existing shim at `shim_path` is replaced. If `shim_binary` is the shim itself, only
+/// its configuration is rewritten.
 ///
 /// # Errors
 ///
-/// Returns an error if writing the activation script fails.
+/// Returns an error if the shim or its configuration cannot be written.
 ///
 /// # Example
 ///
 /// ```no_run
-/// #[cfg(target_family = "windows")]
-/// {
-/// use frate::write_windows_activate;
-/// write_windows_activate().unwrap();
-/// }
+/// use std::path::Path;
+/// use frate::{create_binary_shim, find_shim_binary, ShimConfig};
+///
+/// let config = ShimConfig { target: "../bin/just/just".into(), ..Default::default() };
+/// create_binary_shim(&find_shim_binary().unwrap(), Path::new(".frate/shims/just"), &config).unwrap();
 /// ```
-#[cfg(target_family = "windows")]
-pub fn write_windows_activate() -> std::io::Result<()> {
-    let shim_path = r#"%CD%\.frate\shims"#;
-
-    if is_power_shell() {
-        let content = format!(
-            r#"$env:PATH = "{};${{env:PATH}}"
-            Write-Host "Frate shell activated. Type 'exit' to leave.""#,
-            shim_path
-        );
-        std::fs::write(".frate\\activate.ps1", content)?;
-    } else {
-        let content = format!(
-            r#"@echo off
-            set "PATH={};%PATH%"
-            echo Frate shell activated. Type "exit" to leave.
-            cmd
-            "#,
-            shim_path
-        );
-        std::fs::write(".frate\\activate.bat", content)?;
+pub fn create_binary_shim(shim_binary: &Path, shim_path: &Path, config: &ShimConfig) -> Result<()> {
+    let mut executable = shim_path.as_os_str().to_owned();
+    executable.push(std::env::consts::EXE_SUFFIX);
+    if std::path::absolute(shim_binary)? == std::path::absolute(&executable)? {
+        return config.save(shim_config_path(&executable));
     }
-    Ok(())
+    remove_shim(shim_path)?;
+    if std::fs::hard_link(shim_binary, &executable).is_err() {
+        std::fs::copy(shim_binary, &executable)?;
+    }
+    config.save(shim_config_path(&executable))
 }
 
-/// Starts a new interactive shell with the Frate shims path prepended to the `PATH`.
+/// Returns the path of the `frate-shim` executable used for binary shims.
 ///
-/// On Windows:
-/// - Attempts to launch PowerShell or fallback to `powershell.exe`.
-/// - Prepends `.frate\shims` to the `PATH` and starts a session with a message.
+/// Uses `FRATE_SHIM_BIN` if it is set. Otherwise, if the current executable is a binary
+/// shim itself, returns it, and else looks next to the current executable and in its parent directory.
 ///
-/// On Unix:
-/// - Uses the shell defined in `$SHELL` or falls back to `/bin/sh`.
-/// - Prepends `.frate/shims` to the `PATH` and starts an interactive shell.
+/// # Errors
+///
+/// Returns [`Error::BinaryNotFound`] if `frate-shim` cannot be found.
+pub fn find_shim_binary() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("FRATE_SHIM_BIN") {
+        return Ok(PathBuf::from(path));
+    }
+    let name = format!("frate-shim{}", std::env::consts::EXE_SUFFIX);
+    let exe = std::env::current_exe()?;
+    if shim_config_path(&exe).exists() {
+        return Ok(exe);
+    }
+    exe.ancestors()
+        .skip(1)
+        .take(2)
+        .map(|dir| dir.join(&name))
+        .find(|path| path.exists())
+        .ok_or_else(|| Error::BinaryNotFound("frate-shim".to_string()))
+}
+
+/// Removes the shim at `shim_path`, whichever way it was created: the symlink, script or
+/// executable, its `.bat` variant on Windows, and the configuration of a binary shim.
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - The current working directory cannot be determined.
-/// - The shell process cannot be spawned or fails.
+/// Returns an error if a file exists but cannot be removed.
+pub fn remove_shim<P: AsRef<Path>>(shim_path: P) -> Result<()> {
+    let shim_path = shim_path.as_ref();
+    let mut executable = shim_path.as_os_str().to_owned();
+    executable.push(std::env::consts::EXE_SUFFIX);
+    let candidates = [
+        shim_path.to_path_buf(),
+        PathBuf::from(executable),
+        shim_path.with_extension("bat"),
+        shim_config_path(shim_path),
+    ];
+    for path in candidates {
+        if path.symlink_metadata().is_ok() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path of the shim called `name` in `shims_dir`, whichever way it was created,
+/// or `None` if there is none.
+pub fn find_shim(shims_dir: &Path, name: &str) -> Option<PathBuf> {
+    let shim_path = shims_dir.join(name);
+    let mut executable = shim_path.as_os_str().to_owned();
+    executable.push(std::env::consts::EXE_SUFFIX);
+    let mut bat = shim_path.as_os_str().to_owned();
+    bat.push(".bat");
+    [shim_path, PathBuf::from(executable), PathBuf::from(bat)]
+        .into_iter()
+        .find(|path| path.symlink_metadata().is_ok_and(|meta| meta.is_file() || meta.is_symlink()))
+}
+
+/// Returns the executable a shim forwards to. Works for all shims created by
+/// [`create_shim`], [`create_shim_with_env`] and [`create_binary_shim`]. The target of a binary
+/// shim is returned as written in its configuration, so it may be relative to the shim.
 ///
-/// # Example
+/// Returns `None` if `shim_path` is not a shim.
 ///
-/// ```no_run
-/// use frate::run_shell_with_frate_path;
-/// run_shell_with_frate_path().unwrap();
-/// ```
-pub fn run_shell_with_frate_path() -> std::io::Result<()> {
+/// # Errors
+///
+/// Returns an error if the shim cannot be read.
+pub fn read_shim<P: AsRef<Path>>(shim_path: P) -> Result<Option<std::path::PathBuf>> {
+    let shim_path = shim_path.as_ref();
+    let config_path = shim_config_path(shim_path);
+    if config_path.exists() && config_path != shim_path {
+        return Ok(Some(ShimConfig::load(config_path)?.target));
+    }
+    #[cfg(unix)]
+    {
+        if shim_path.symlink_metadata()?.file_type().is_symlink() {
+            return Ok(Some(std::fs::read_link(shim_path)?));
+        }
+        let Ok(script) = std::fs::read_to_string(shim_path) else {
+            return Ok(None);
+        };
+        if !script.starts_with("#!/bin/sh\n# Generated by frate.") {
+            return Ok(None);
+        }
+        let target = script.lines()
+            .find_map(|line| line.strip_prefix("exec '")?.strip_suffix("' \"$@\""))
+            .map(|target| std::path::PathBuf::from(target.replace("'\\''", "'")));
+        Ok(target)
+    }
     #[cfg(windows)]
     {
-        use std::process::Command;
+        if shim_path.extension().and_then(|ext| ext.to_str()) != Some("bat") {
+            return Ok(None);
+        }
+        let script = std::fs::read_to_string(shim_path)?;
+        let target = script.lines()
+            .find(|line| line.starts_with("call \""))
+            .and_then(|line| line.split('"').nth(1))
+            .map(std::path::PathBuf::from);
+        Ok(target)
+    }
+}
 
-    let frate_shims = format!(
-        "{}\\.frate\\shims",
-        std::env::current_dir()?.display()
-    );
+const ACTIVATE_SH: &str = r#"# Generated by frate. Source this file from bash, zsh or sh, do not run it:
+#     . .frate/activate
+# Run `deactivate` to undo.
+if [ -n "${BASH_SOURCE:-}" ]; then
+    _frate_script="$BASH_SOURCE"
+elif [ -n "${ZSH_VERSION:-}" ]; then
+    eval '_frate_script="${(%):-%x}"'
+else
+    # Plain sh cannot tell which file is being sourced.
+    _frate_script='{fallback}'
+fi
+_frate_dir="$(cd "$(dirname "$_frate_script")" && pwd)"
+unset _frate_script
+if [ -n "${FRATE_ROOT:-}" ] && command -v deactivate >/dev/null 2>&1; then
+    deactivate
+fi
 
-    let path = std::env::var("PATH").unwrap_or_default();
-    let new_path = format!("{frate_shims};{path}");
+deactivate () {
+    if [ -n "${_FRATE_OLD_PATH+x}" ]; then
+        PATH="$_FRATE_OLD_PATH"
+        export PATH
+        unset _FRATE_OLD_PATH
+    fi
+    if [ -n "${_FRATE_ENV_VARS:-}" ]; then
+        eval "unset $_FRATE_ENV_VARS"
+    fi
+    unset FRATE_ROOT _FRATE_ENV_VARS
+    hash -r 2>/dev/null
+    unset -f deactivate
+}
 
-    // Check if PowerShell exists
-    let powershell = if Command::new("pwsh").output().is_ok() {
-        "pwsh"
-    } else {
-        "powershell"
-    };
+FRATE_ROOT="$(dirname "$_frate_dir")"
+_FRATE_OLD_PATH="$PATH"
+PATH="$_frate_dir/shims:$PATH"
+export FRATE_ROOT PATH
+if [ -f "$_frate_dir/env.sh" ]; then
+    . "$_frate_dir/env.sh"
+fi
+unset _frate_dir
+hash -r 2>/dev/null
+"#;
 
-    Command::new(powershell)
-        .args([
-            "-NoExit",
-            "-Command",
-            &format!(
-                "$env:PATH='{}'; Write-Host 'Frate shell activated. Type \"exit\" to leave.'",
-                new_path.replace('\\', "\\\\") // Escape backslashes
-            ),
-        ])
-        .spawn()?
-        .wait()?;
+const ACTIVATE_FISH: &str = r#"# Generated by frate. Source this file from fish:
+#     source .frate/activate.fish
+# Run `deactivate` to undo.
+set -l frate_dir (string replace -r '/[^/]*$' '' -- (status filename))
+string match -q '/*' -- $frate_dir; or set frate_dir $PWD/$frate_dir
+if set -q FRATE_ROOT; and functions -q deactivate
+    deactivate
+end
+
+function deactivate
+    if set -q _FRATE_OLD_PATH
+        set -gx PATH $_FRATE_OLD_PATH
+        set -e _FRATE_OLD_PATH
+    end
+    for var in $_FRATE_ENV_VARS
+        set -e $var
+    end
+    set -e FRATE_ROOT _FRATE_ENV_VARS
+    functions -e deactivate
+end
+
+set -gx FRATE_ROOT (string replace -r '/[^/]*$' '' -- $frate_dir)
+set -g _FRATE_OLD_PATH $PATH
+set -gx PATH $frate_dir/shims $PATH
+if test -f $frate_dir/env.fish
+    source $frate_dir/env.fish
+end
+"#;
+
+const ACTIVATE_PS1: &str = r#"# Generated by frate. Dot-source this file from PowerShell:
+#     . .frate/activate.ps1
+# Run `deactivate` to undo.
+if ($env:FRATE_ROOT -and (Test-Path Function:deactivate)) {
+    deactivate
+}
+
+function global:deactivate {
+    if (Test-Path Variable:global:_FRATE_OLD_PATH) {
+        $env:PATH = $global:_FRATE_OLD_PATH
+        Remove-Variable -Name _FRATE_OLD_PATH -Scope Global
+    }
+    if (Test-Path Variable:global:_FRATE_ENV_VARS) {
+        foreach ($var in $global:_FRATE_ENV_VARS) {
+            Remove-Item "Env:$var" -ErrorAction SilentlyContinue
+        }
+        Remove-Variable -Name _FRATE_ENV_VARS -Scope Global
     }
+    Remove-Item Env:FRATE_ROOT -ErrorAction SilentlyContinue
+    Remove-Item Function:deactivate
+}
+
+$env:FRATE_ROOT = Split-Path -Parent $PSScriptRoot
+$global:_FRATE_OLD_PATH = $env:PATH
+$env:PATH = (Join-Path $PSScriptRoot 'shims') + [IO.Path]::PathSeparator + $env:PATH
+if (Test-Path (Join-Path $PSScriptRoot 'env.ps1')) {
+    . (Join-Path $PSScriptRoot 'env.ps1')
+}
+"#;
 
+const ACTIVATE_BAT: &str = "@echo off\r
+rem Generated by frate. Run .frate\\activate.bat in cmd, and .frate\\deactivate.bat to undo.\r
+if defined _FRATE_OLD_PATH set \"PATH=%_FRATE_OLD_PATH%\"\r
+for %%i in (\"%~dp0..\") do set \"FRATE_ROOT=%%~fi\"\r
+set \"_FRATE_OLD_PATH=%PATH%\"\r
+set \"PATH=%~dp0shims;%PATH%\"\r
+if exist \"%~dp0env.bat\" call \"%~dp0env.bat\"\r
+";
+
+const DEACTIVATE_BAT: &str = "@echo off\r
+rem Generated by frate. Undoes .frate\\activate.bat.\r
+if defined _FRATE_OLD_PATH set \"PATH=%_FRATE_OLD_PATH%\"\r
+set _FRATE_OLD_PATH=\r
+if defined _FRATE_ENV_VARS for %%v in (%_FRATE_ENV_VARS%) do set \"%%v=\"\r
+set _FRATE_ENV_VARS=\r
+set FRATE_ROOT=\r
+";
+
+/// Writes the activation scripts into the `.frate` directory:
+/// `activate` for sh, bash and zsh, `activate.fish`, `activate.ps1`, and
+/// `activate.bat`/`deactivate.bat` for cmd.
+///
+/// The scripts are meant to be sourced into the current shell. They find the project from
+/// their own location, so they work from any working directory and after the project is moved,
+/// prepend `.frate/shims` to the `PATH`, set `FRATE_ROOT` to the project root, and define a
+/// `deactivate` function that restores the previous `PATH`. Plain `sh` cannot determine the
+/// location of a sourced file, so there `activate` falls back to the path it was written to.
+/// On Unix, `activate` is made executable.
+///
+/// # Errors
+///
+/// Returns an error if writing a script fails.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::path::Path;
+/// use frate::write_activate_scripts;
+/// write_activate_scripts(Path::new(".frate")).unwrap();
+/// ```
+pub fn write_activate_scripts(frate_dir: &Path) -> Result<()> {
+    let fallback = std::path::absolute(frate_dir.join("activate"))?;
+    let sh = ACTIVATE_SH.replace("{fallback}", &fallback.display().to_string().replace('\'', "'\\''"));
+    std::fs::write(frate_dir.join("activate"), sh)?;
     #[cfg(unix)]
     {
-        use std::process::Command;
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(frate_dir.join("activate"), std::fs::Permissions::from_mode(0o755))?;
+    }
+    std::fs::write(frate_dir.join("activate.fish"), ACTIVATE_FISH)?;
+    std::fs::write(frate_dir.join("activate.ps1"), ACTIVATE_PS1)?;
+    std::fs::write(frate_dir.join("activate.bat"), ACTIVATE_BAT)?;
+    std::fs::write(frate_dir.join("deactivate.bat"), DEACTIVATE_BAT)?;
+    Ok(())
+}
 
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
-        let frate_shims = format!("{}/.frate/shims", std::env::current_dir()?.display());
-        let current_path = std::env::var("PATH").unwrap_or_default();
-        let new_path = format!("{frate_shims}:{}", current_path);
+/// Writes the environment of `packages` into the `.frate` directory, where the activation
+/// scripts pick it up: `env.sh`, `env.fish`, `env.ps1` and `env.bat`.
+///
+/// The `{install_dir}` placeholder is written relative to `FRATE_ROOT`, so the files keep
+/// working when the project is moved. If no package declares environment variables or
+/// extra `PATH` directories, existing files are removed.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be written or removed.
+pub fn write_env_scripts(frate_dir: &Path, packages: &[LockedPackage]) -> Result<()> {
+    let files = ["env.sh", "env.fish", "env.ps1", "env.bat"];
+    if packages.iter().all(|package| package.env.is_empty() && package.paths.is_empty()) {
+        for file in files {
+            let path = frate_dir.join(file);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+        return Ok(());
+    }
+    let mut sh = String::from("# Generated by frate. Sourced by activate.\n");
+    let mut fish = String::from("# Generated by frate. Sourced by activate.fish.\n");
+    let mut ps1 = String::from("# Generated by frate. Sourced by activate.ps1.\n");
+    let mut bat = String::from("@echo off\r\nrem Generated by frate. Called by activate.bat.\r\n");
+    let mut names = Vec::new();
+    for package in packages {
+        let sh_dir = format!("$FRATE_ROOT/.frate/bin/{}", package.name);
+        let ps1_dir = format!("$env:FRATE_ROOT/.frate/bin/{}", package.name);
+        let bat_dir = format!("%FRATE_ROOT%\\.frate\\bin\\{}", package.name);
+        let sh_value = |v: &str| escape(v, &['\\', '"', '$', '`'], "\\").replace(INSTALL_DIR, &sh_dir);
+        let fish_value = |v: &str| escape(v, &['\\', '"', '$'], "\\").replace(INSTALL_DIR, &sh_dir);
+        let ps1_value = |v: &str| escape(v, &['`', '"', '$'], "`").replace(INSTALL_DIR, &ps1_dir);
+        let bat_value = |v: &str| v.replace('%', "%%").replace(INSTALL_DIR, &bat_dir);
+        for path in &package.paths {
+            let path = anchor_path(path);
+            sh.push_str(&format!("PATH=\"{}:$PATH\"\nexport PATH\n", sh_value(&path)));
+            fish.push_str(&format!("set -gx PATH \"{}\" $PATH\n", fish_value(&path)));
+            ps1.push_str(&format!("$env:PATH = \"{}\" + [IO.Path]::PathSeparator + $env:PATH\n", ps1_value(&path)));
+            bat.push_str(&format!("set \"PATH={};%PATH%\"\r\n", bat_value(&path)));
+        }
+        for (name, value) in &package.env {
+            sh.push_str(&format!("export {}=\"{}\"\n", name, sh_value(value)));
+            fish.push_str(&format!("set -gx {} \"{}\"\n", name, fish_value(value)));
+            ps1.push_str(&format!("$env:{} = \"{}\"\n", name, ps1_value(value)));
+            bat.push_str(&format!("set \"{}={}\"\r\n", name, bat_value(value)));
+            names.push(name.clone());
+        }
+    }
+    names.sort();
+    names.dedup();
+    sh.push_str(&format!("_FRATE_ENV_VARS={}\n", posix_quote(&names.join(" "))));
+    fish.push_str(&format!("set -g _FRATE_ENV_VARS {}\n", names.join(" ")));
+    let ps1_names: Vec<String> = names.iter().map(|name| powershell_quote(name)).collect();
+    ps1.push_str(&format!("$global:_FRATE_ENV_VARS = @({})\n", ps1_names.join(", ")));
+    bat.push_str(&format!("set \"_FRATE_ENV_VARS={}\"\r\n", names.join(" ")));
 
-        Command::new(&shell)
-            .arg("-i")
-            .env("PATH", new_path)
-            .spawn()?
-            .wait()?;
+    std::fs::write(frate_dir.join("env.sh"), sh)?;
+    std::fs::write(frate_dir.join("env.fish"), fish)?;
+    std::fs::write(frate_dir.join("env.ps1"), ps1)?;
+    std::fs::write(frate_dir.join("env.bat"), bat)?;
+    Ok(())
+}
+
+/// Prefixes every character of `s` contained in `special` with `escape`.
+fn escape(s: &str, special: &[char], escape: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(&c) {
+            escaped.push_str(escape);
+        }
+        escaped.push(c);
     }
+    escaped
+}
 
+/// Starts an interactive shell with `environment` applied, and waits for it to exit.
+///
+/// Uses `$SHELL` on Unix and PowerShell on Windows.
+///
+/// # Errors
+///
+/// Returns an error if the shell cannot be started.
+pub fn run_shell(environment: &Environment) -> Result<()> {
+    #[cfg(windows)]
+    let mut command = {
+        let powershell = if Command::new("pwsh").arg("-Version").output().is_ok() {
+            "pwsh"
+        } else {
+            "powershell"
+        };
+        let mut command = Command::new(powershell);
+        command.args(["-NoExit", "-Command", "Write-Host 'Frate shell activated. Type \"exit\" to leave.'"]);
+        command
+    };
+    #[cfg(unix)]
+    let mut command = {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        let mut command = Command::new(shell);
+        command.arg("-i");
+        command
+    };
+    environment.apply(&mut command)?;
+    command.spawn()?.wait()?;
     Ok(())
 }
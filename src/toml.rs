@@ -1,22 +1,218 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
-use anyhow::{bail, Result};
+use toml_edit::{DocumentMut, Item, Key, Value};
+use crate::error::{Error, Result};
+use crate::scripts::Script;
+use crate::settings::Settings;
+use crate::cfg::{target_key_matches, TargetInfo};
 use crate::util::is_valid_version;
 
 /// Represents the contents of a `frate.toml` file.
 ///
 /// This includes project metadata and a map of tool dependencies with pinned versions.
-#[derive(Deserialize, Serialize, Debug)]
+///
+/// A manifest loaded from disk remembers the original document, so [`FrateToml::save`] keeps
+/// comments, key order and whitespace of everything that was not changed.
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct FrateToml {
-    /// Metadata about the project using `frate`.
-    pub project: Project,
-    /// A map of tool names to version strings (e.g., `"just" => "1.42.0"`).
-    pub dependencies: HashMap<String, String>
+    /// Metadata about the project using `frate`. May be omitted in a workspace root.
+    #[serde(default)]
+    pub project: ProjectInfo,
+    /// A map of tool names to their [`Dependency`] (e.g., `"just" => "1.42.0"`).
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    /// The `[workspace]` table, which makes this manifest the root of a [`Workspace`](crate::Workspace).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceConfig>,
+    /// Named registries from the `[registries]` table, which dependencies can refer to
+    /// with `registry = "<name>"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub registries: BTreeMap<String, String>,
+    /// Optional project settings from the `[settings]` table.
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub settings: Settings,
+    /// Per-tool configuration from `[tools.<name>]` tables.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tools: BTreeMap<String, ToolConfig>,
+    /// Project scripts from the `[scripts]` table, run with `frate run <script>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, Script>,
+    /// Platform-specific dependencies from `[target.<key>.dependencies]` tables, keyed by
+    /// a `cfg(...)` expression (see [`Cfg`](crate::Cfg)) or a target triple.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub target: BTreeMap<String, TargetDependencies>,
+    /// The document the manifest was loaded from, edited in place by [`FrateToml::add`],
+    /// [`FrateToml::remove`] and [`FrateToml::set_version`].
+    #[serde(skip)]
+    document: Option<DocumentMut>,
+}
+/// A dependency from the `[dependencies]` table of `frate.toml`.
+///
+/// Either a version, or a table with the version and further options:
+///
+/// ```toml
+/// [dependencies]
+/// ripgrep = "14.1.1"
+/// just = { version = "^1.42", registry = "internal", bins = ["just"], optional = true, targets = ["linux"] }
+/// mdbook = { version = "0.4.40", groups = ["docs"] }
+/// ```
+///
+/// Both forms are kept when the manifest is saved.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Dependency {
+    /// An exact version, e.g. `"1.42.1"`.
+    Version(String),
+    /// A table with the version and options.
+    Detailed(DetailedDependency),
+}
+
+/// The table form of a [`Dependency`].
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DetailedDependency {
+    /// An exact version (`"1.42.1"`) or a semver requirement (`"^1.42"`). For a requirement,
+    /// the newest matching release for the current platform is locked.
+    pub version: String,
+    /// The registry to resolve the tool from: the name of an entry in `[registries]`, or a URL.
+    /// Defaults to [`Settings::registry`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Binaries of the tool that get a shim, in addition to its main binary.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bins: Vec<String>,
+    /// Optional tools are locked, but only installed when named explicitly.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub optional: bool,
+    /// Platforms the tool is used on. Each entry is an operating system (`"linux"`, `"macos"`,
+    /// `"windows"`), a family (`"unix"`), an architecture (`"x86_64"`) or a target triple.
+    /// Empty means all platforms.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// Groups the tool belongs to, e.g. `["ci", "docs"]`. `frate install --group <group>` and
+    /// `frate shell --group <group>` select the tools of a group.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+/// A `[target.<key>]` table of `frate.toml`.
+///
+/// ```toml
+/// [target.'cfg(target_os = "linux")'.dependencies]
+/// strace = "6.10.0"
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct TargetDependencies {
+    /// Dependencies only used on matching platforms, in the same form as `[dependencies]`.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+impl Dependency {
+    /// Returns the requested version or version requirement.
+    pub fn version(&self) -> &str {
+        match self {
+            Dependency::Version(version) => version,
+            Dependency::Detailed(detailed) => &detailed.version,
+        }
+    }
+    /// Returns the registry the dependency is resolved from, if it is not the default one.
+    pub fn registry(&self) -> Option<&str> {
+        match self {
+            Dependency::Version(_) => None,
+            Dependency::Detailed(detailed) => detailed.registry.as_deref(),
+        }
+    }
+    /// Returns the binaries that get a shim in addition to the main binary.
+    pub fn bins(&self) -> &[String] {
+        match self {
+            Dependency::Version(_) => &[],
+            Dependency::Detailed(detailed) => &detailed.bins,
+        }
+    }
+    /// Returns the groups the dependency belongs to.
+    pub fn groups(&self) -> &[String] {
+        match self {
+            Dependency::Version(_) => &[],
+            Dependency::Detailed(detailed) => &detailed.groups,
+        }
+    }
+    /// Returns true if the dependency is only installed when named explicitly.
+    pub fn is_optional(&self) -> bool {
+        matches!(self, Dependency::Detailed(detailed) if detailed.optional)
+    }
+    /// Returns true if the dependency is used on the current platform.
+    /// See [`DetailedDependency::targets`].
+    pub fn matches_current_target(&self) -> bool {
+        self.matches_target(&TargetInfo::current())
+    }
+    /// Returns true if the dependency is used on `target`. See [`DetailedDependency::targets`].
+    pub fn matches_target(&self, target: &TargetInfo) -> bool {
+        match self {
+            Dependency::Version(_) => true,
+            Dependency::Detailed(detailed) => {
+                detailed.targets.is_empty() || detailed.targets.iter().any(|name| target.matches(name))
+            }
+        }
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(version: &str) -> Self {
+        Dependency::Version(version.to_string())
+    }
+}
+
+/// Per-tool configuration from a `[tools.<name>]` table of `frate.toml`.
+///
+/// ```toml
+/// [tools.go.env]
+/// GOROOT = "{install_dir}/go"
+///
+/// [tools.go.bins]
+/// gofmt = "gofmt"
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ToolConfig {
+    /// Environment variables for the tool. They override variables of the same name
+    /// from the registry. Values may contain the `{install_dir}` placeholder; names must be
+    /// [valid](crate::env::is_valid_env_name).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", deserialize_with = "crate::env::deserialize_env_vars")]
+    pub env: BTreeMap<String, String>,
+    /// Directories to add to `PATH` in addition to those from the registry. May contain the
+    /// `{install_dir}` placeholder; relative directories are relative to the install directory
+    /// of the tool, e.g. `bin` is `{install_dir}/bin`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Shims to create, keyed by the file name of the binary without extension, with the
+    /// name of the shim as value, e.g. `python3 = "python"`. The main binary of the tool
+    /// always gets a shim, named after the binary unless it is renamed here.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bins: BTreeMap<String, String>,
+}
+/// The `[workspace]` table of `frate.toml`.
+///
+/// ```toml
+/// [workspace]
+/// members = ["services/*", "tools/cli"]
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct WorkspaceConfig {
+    /// Member directories, relative to the workspace root. A trailing `*` stands for every
+    /// subdirectory that contains a `frate.toml`.
+    #[serde(default)]
+    pub members: Vec<String>,
 }
+
 /// Basic metadata for a `frate` project.
-#[derive(Deserialize, Serialize, Debug)]
-pub struct Project {
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ProjectInfo {
     /// The name of the project.
     pub name: String,
     /// The version of the project (semantic versioning).
@@ -33,20 +229,31 @@ impl FrateToml {
     /// A `FrateToml` instance with empty dependencies.
     pub fn default(name: &str) -> FrateToml {
         FrateToml {
-            project: Project {
+            project: ProjectInfo {
                 name: String::from(name),
                 version: String::from("0.1.0"),
             },
-            dependencies: HashMap::new()
+            dependencies: BTreeMap::new(),
+            workspace: None,
+            registries: BTreeMap::new(),
+            settings: Settings::default(),
+            tools: BTreeMap::new(),
+            scripts: BTreeMap::new(),
+            target: BTreeMap::new(),
+            document: None,
         }
     }
-    /// Saves the `FrateToml` to the given file path in pretty TOML format.
+    /// Saves the `FrateToml` to the given file path.
+    ///
+    /// A manifest that was loaded from disk is written back with its original formatting:
+    /// only tables that changed since loading are rewritten, except `[dependencies]`, which
+    /// [`FrateToml::add`], [`FrateToml::remove`] and [`FrateToml::set_version`] edit entry by entry.
+    /// A new manifest is written in pretty TOML format.
     ///
     /// # Errors
     /// Returns an error if the file can't be written or serialization fails.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
-        let toml_str = toml::to_string_pretty(self)?;
-        std::fs::write(path, toml_str)?;
+        std::fs::write(path, self.to_toml_string()?)?;
         Ok(())
     }
     /// Loads a `FrateToml` from a file path.
@@ -55,10 +262,85 @@ impl FrateToml {
     /// Returns an error if the file can't be read or deserialized.
     pub fn load<P: AsRef<Path>>(path: P) -> Result<FrateToml> {
         let toml = std::fs::read_to_string(path)?;
-        toml::from_str(&toml).map_err(|e| e.into())
+        let mut manifest: FrateToml = toml::from_str(&toml)?;
+        for key in manifest.target.keys() {
+            target_key_matches(key, &TargetInfo::current())?;
+        }
+        manifest.document = Some(toml.parse()?);
+        Ok(manifest)
+    }
+    /// Returns the dependencies used on `target`: those of `[dependencies]` and of every
+    /// `[target.<key>.dependencies]` table whose key matches, without those whose
+    /// [`targets`](DetailedDependency::targets) exclude the platform. A platform-specific
+    /// entry replaces an entry of the same name in `[dependencies]`.
+    ///
+    /// Tables with an invalid `cfg(...)` key never match; [`FrateToml::load`] rejects them.
+    pub fn dependencies_for(&self, target: &TargetInfo) -> BTreeMap<String, Dependency> {
+        let mut dependencies = self.dependencies.clone();
+        for (key, table) in &self.target {
+            if target_key_matches(key, target).unwrap_or(false) {
+                dependencies.extend(table.dependencies.clone());
+            }
+        }
+        dependencies.retain(|_, dependency| dependency.matches_target(target));
+        dependencies
+    }
+    /// Returns the [dependencies](FrateToml::dependencies_for) used on the current platform.
+    pub fn current_dependencies(&self) -> BTreeMap<String, Dependency> {
+        self.dependencies_for(&TargetInfo::current())
+    }
+    /// Returns true if `name` is declared in `[dependencies]` or any `[target.<key>.dependencies]`
+    /// table, whether or not it is used on the current platform.
+    pub fn declares(&self, name: &str) -> bool {
+        self.dependencies.contains_key(name)
+            || self.target.values().any(|table| table.dependencies.contains_key(name))
+    }
+    /// Returns the content [`FrateToml::save`] writes, reusing the loaded document for all
+    /// tables that did not change.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn to_toml_string(&self) -> Result<String> {
+        let pretty = toml::to_string_pretty(self)?;
+        let Some(document) = &self.document else {
+            return Ok(pretty);
+        };
+        let mut document = document.clone();
+        let fresh: DocumentMut = pretty.parse()?;
+        let current: FrateToml = toml::from_str(&document.to_string())?;
+        let changed = [
+            ("project", current.project != self.project),
+            ("dependencies", current.dependencies != self.dependencies),
+            ("workspace", current.workspace != self.workspace),
+            ("registries", current.registries != self.registries),
+            ("settings", current.settings != self.settings),
+            ("tools", current.tools != self.tools),
+            ("scripts", current.scripts != self.scripts),
+            ("target", current.target != self.target),
+        ];
+        let mut next_position = max_position(document.as_item()) + 1;
+        for (key, _) in changed.iter().filter(|(_, changed)| *changed) {
+            let Some(mut item) = fresh.get(key).cloned() else {
+                document.remove(key);
+                continue;
+            };
+            let position = match document.get(key).and_then(Item::as_table).and_then(|table| table.position()) {
+                Some(position) => position,
+                None => {
+                    next_position += 1;
+                    next_position - 1
+                }
+            };
+            set_position(&mut item, position);
+            document.insert(key, item);
+        }
+        Ok(document.to_string())
     }
     /// Adds a new dependency to the `frate.toml` file.
     ///
+    /// In a loaded manifest, the dependency is inserted in alphabetical order, before the first
+    /// dependency whose name sorts after it.
+    ///
     /// # Arguments
     /// * `name` - The name of the tool.
     /// * `version` - A semver-compatible version string (e.g., `"1.0.2"`).
@@ -67,19 +349,166 @@ impl FrateToml {
     /// Returns an error if the version is invalid or the dependency already exists.
     pub fn add(&mut self, name: &str, version: &str) -> Result<()> {
         if !is_valid_version(version) {
-            bail!("Invalid version: {}", version);
+            return Err(Error::InvalidVersion(version.to_string()));
         }
         if self.dependencies.contains_key(name) {
-            bail!("Dependency {} already exists", name);
+            return Err(Error::DependencyExists(name.to_string()));
+        }
+        self.dependencies.insert(name.to_string(), Dependency::Version(version.to_string()));
+        if let Some(document) = &mut self.document {
+            insert_sorted(document, name, Value::from(version))?;
         }
-        self.dependencies.insert(name.to_string(), version.to_string());
         Ok(())
     }
-    /// Removes a dependency from the list.
+    /// Changes the version of an existing dependency, keeping its other options and formatting.
+    /// Dependencies that are only declared in a `[target.<key>.dependencies]` table are changed there.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the tool.
+    /// * `version` - A semver-compatible version string (e.g., `"1.0.2"`).
+    ///
+    /// # Errors
+    /// Returns an error if the version is invalid or the dependency does not exist.
+    pub fn set_version(&mut self, name: &str, version: &str) -> Result<()> {
+        if !is_valid_version(version) {
+            return Err(Error::InvalidVersion(version.to_string()));
+        }
+        let section = match self.dependencies.contains_key(name) {
+            true => None,
+            false => self.target.iter().find(|(_, table)| table.dependencies.contains_key(name)).map(|(key, _)| key.clone()),
+        };
+        let dependencies = match &section {
+            None => &mut self.dependencies,
+            Some(key) => &mut self.target.get_mut(key).expect("section exists").dependencies,
+        };
+        match dependencies.get_mut(name) {
+            Some(Dependency::Version(current)) => *current = version.to_string(),
+            Some(Dependency::Detailed(detailed)) => detailed.version = version.to_string(),
+            None => return Err(Error::DependencyNotFound(name.to_string())),
+        }
+        let Some(document) = &mut self.document else {
+            return Ok(());
+        };
+        let deps = match &section {
+            None => document.get_mut("dependencies"),
+            Some(key) => document.get_mut("target")
+                .and_then(|target| target.get_mut(key))
+                .and_then(|table| table.get_mut("dependencies")),
+        };
+        let Some(item) = deps.and_then(|deps| deps.get_mut(name)) else {
+            return Ok(());
+        };
+        match item {
+            Item::Value(value @ Value::String(_)) => replace_value(value, version),
+            Item::Value(Value::InlineTable(table)) => match table.get_mut("version") {
+                Some(value) => replace_value(value, version),
+                None => {
+                    table.insert("version", Value::from(version));
+                }
+            },
+            Item::Table(table) => match table.get_mut("version").and_then(Item::as_value_mut) {
+                Some(value) => replace_value(value, version),
+                None => {
+                    table.insert("version", toml_edit::value(version));
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+    /// Returns the URL of the registry `dependency` is resolved from: the entry of
+    /// `[registries]` its `registry` names, the URL it names, or `default`.
+    ///
+    /// # Errors
+    /// Returns [`Error::Other`] if the dependency names a registry that is neither in
+    /// `[registries]` nor a URL.
+    pub fn registry_url<'a>(&'a self, dependency: &'a Dependency, default: &'a str) -> Result<&'a str> {
+        match dependency.registry() {
+            None => Ok(default),
+            Some(name) => match self.registries.get(name) {
+                Some(url) => Ok(url),
+                None if name.contains("://") => Ok(name),
+                None => Err(Error::Other(format!("unknown registry `{}`; add it to [registries]", name))),
+            },
+        }
+    }
+    /// Removes a dependency from `[dependencies]` and all `[target.<key>.dependencies]` tables.
     ///
     /// If the dependency does not exist, nothing happens.
     pub fn remove(&mut self, name: &str) {
         self.dependencies.remove(name);
+        for table in self.target.values_mut() {
+            table.dependencies.remove(name);
+        }
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        if let Some(table) = document.get_mut("dependencies").and_then(Item::as_table_like_mut) {
+            table.remove(name);
+        }
+        let targets = document.get_mut("target").and_then(Item::as_table_like_mut);
+        for (_, target) in targets.into_iter().flat_map(|targets| targets.iter_mut()) {
+            if let Some(table) = target.get_mut("dependencies").and_then(Item::as_table_like_mut) {
+                table.remove(name);
+            }
+        }
+    }
+}
+
+/// Inserts `name = value` into the `[dependencies]` table of `document`, before the first
+/// entry whose name sorts after `name`. Entries after it are moved along with their comments.
+fn insert_sorted(document: &mut DocumentMut, name: &str, value: Value) -> Result<()> {
+    let deps = document.entry("dependencies").or_insert(toml_edit::table());
+    if let Some(table) = deps.as_inline_table_mut() {
+        let later: Vec<String> = table.iter().map(|(key, _)| key.to_string()).filter(|key| key.as_str() > name).collect();
+        let moved: Vec<(Key, Value)> = later.iter().filter_map(|key| table.remove_entry(key)).collect();
+        table.insert(name, value);
+        for (key, value) in moved {
+            table.insert_formatted(&key, value);
+        }
+        return Ok(());
+    }
+    let Some(table) = deps.as_table_mut() else {
+        return Err(Error::Parse("`dependencies` in frate.toml is not a table".to_string()));
+    };
+    let later: Vec<String> = table.iter()
+        .filter(|(key, item)| item.is_value() && *key > name)
+        .map(|(key, _)| key.to_string())
+        .collect();
+    let moved: Vec<(Key, Item)> = later.iter().filter_map(|key| table.remove_entry(key)).collect();
+    table.insert(name, Item::Value(value));
+    for (key, item) in moved {
+        table.insert_formatted(&key, item);
+    }
+    Ok(())
+}
+
+/// Replaces the string in `value` with `version`, keeping the surrounding whitespace and comments.
+fn replace_value(value: &mut Value, version: &str) {
+    let decor = value.decor().clone();
+    *value = Value::from(version);
+    *value.decor_mut() = decor;
+}
+
+/// Returns the highest table position within `item`, or `0` if it has none.
+fn max_position(item: &Item) -> isize {
+    match item.as_table() {
+        Some(table) => table.iter()
+            .map(|(_, item)| max_position(item))
+            .chain(table.position())
+            .max()
+            .unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// Places the table `item` and all tables nested in it at `position` of the document.
+fn set_position(item: &mut Item, position: isize) {
+    if let Some(table) = item.as_table_mut() {
+        table.set_position(position);
+        for (_, item) in table.iter_mut() {
+            set_position(item, position);
+        }
     }
 }
 
@@ -115,21 +544,21 @@ mod tests {
         let loaded = FrateToml::load(&file_path).unwrap();
         assert_eq!(loaded.project.name, "test");
         assert_eq!(loaded.project.version, "0.1.0");
-        assert_eq!(loaded.dependencies.get("tool").unwrap(), "1.2.3");
+        assert_eq!(loaded.dependencies.get("tool").unwrap().version(), "1.2.3");
     }
 
     #[test]
     fn test_add_valid() {
         let mut frate = FrateToml::default("x");
         frate.add("foo", "1.0.0").unwrap();
-        assert_eq!(frate.dependencies.get("foo").unwrap(), "1.0.0");
+        assert_eq!(frate.dependencies.get("foo").unwrap().version(), "1.0.0");
     }
 
     #[test]
     fn test_add_invalid_version() {
         let mut frate = FrateToml::default("x");
         let result = frate.add("foo", "bad.version");
-        assert!(result.is_err());
+        assert!(matches!(result, Err(Error::InvalidVersion(_))));
     }
 
     #[test]
@@ -137,7 +566,7 @@ mod tests {
         let mut frate = FrateToml::default("x");
         frate.add("foo", "1.0.0").unwrap();
         let result = frate.add("foo", "1.0.0");
-        assert!(result.is_err());
+        assert!(matches!(result, Err(Error::DependencyExists(_))));
     }
 
     #[test]
@@ -154,4 +583,151 @@ mod tests {
         // Should not panic or error
         assert!(frate.dependencies.is_empty());
     }
+
+    #[test]
+    fn test_load_rejects_invalid_env_name() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("frate.toml");
+        std::fs::write(&file_path, "[project]\nname = \"x\"\nversion = \"0.1.0\"\n\n[dependencies]\n\n[tools.go.env]\n\"X; rm -rf ~\" = \"1\"\n").unwrap();
+        let result = FrateToml::load(&file_path);
+        assert!(matches!(&result, Err(Error::Parse(message)) if message.contains("invalid environment variable name")), "{:?}", result);
+    }
+
+    #[test]
+    fn test_detailed_dependencies_round_trip() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("frate.toml");
+        std::fs::write(&file_path, concat!(
+            "[project]\nname = \"x\"\nversion = \"0.1.0\"\n\n",
+            "[dependencies]\n",
+            "ripgrep = \"14.1.1\"\n",
+            "just = { version = \"^1.42\", registry = \"internal\", bins = [\"just\"], optional = true, targets = [\"linux\"] }\n\n",
+            "[registries]\ninternal = \"https://frate.example.com\"\n",
+        )).unwrap();
+        let frate = FrateToml::load(&file_path).unwrap();
+        assert_eq!(frate.dependencies["ripgrep"], Dependency::Version("14.1.1".to_string()));
+        let just = &frate.dependencies["just"];
+        assert_eq!(just.version(), "^1.42");
+        assert!(just.is_optional());
+        assert_eq!(just.bins(), ["just".to_string()]);
+        assert_eq!(frate.registry_url(just, "default").unwrap(), "https://frate.example.com");
+
+        frate.save(&file_path).unwrap();
+        let reloaded = FrateToml::load(&file_path).unwrap();
+        assert_eq!(reloaded.dependencies, frate.dependencies);
+    }
+
+    #[test]
+    fn test_edits_preserve_formatting() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("frate.toml");
+        std::fs::write(&file_path, concat!(
+            "# Tools for this project\n",
+            "[project]\nname = \"x\"\nversion = \"0.1.0\"\n\n",
+            "[dependencies]\n",
+            "# search\n",
+            "fd = \"10.2.0\"\n",
+            "ripgrep = \"14.1.1\"   # keep in sync with CI\n",
+            "just = { version = \"1.42.0\", bins = [\"just\"] }\n\n",
+            "[scripts]\nlint = \"just lint\"\n",
+        )).unwrap();
+        let mut frate = FrateToml::load(&file_path).unwrap();
+        frate.add("bat", "0.25.0").unwrap();
+        frate.add("hyperfine", "1.19.0").unwrap();
+        frate.set_version("ripgrep", "14.1.2").unwrap();
+        frate.set_version("just", "1.43.0").unwrap();
+        frate.remove("fd");
+        frate.save(&file_path).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), concat!(
+            "# Tools for this project\n",
+            "[project]\nname = \"x\"\nversion = \"0.1.0\"\n\n",
+            "[dependencies]\n",
+            "bat = \"0.25.0\"\n",
+            "hyperfine = \"1.19.0\"\n",
+            "ripgrep = \"14.1.2\"   # keep in sync with CI\n",
+            "just = { version = \"1.43.0\", bins = [\"just\"] }\n\n",
+            "[scripts]\nlint = \"just lint\"\n",
+        ));
+    }
+
+    #[test]
+    fn test_save_rewrites_only_changed_tables() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("frate.toml");
+        std::fs::write(&file_path, concat!(
+            "[project]\nname = \"x\"   # the name\nversion = \"0.1.0\"\n\n",
+            "[dependencies]\n\n",
+            "[scripts]\nlint = \"just lint\"\n",
+        )).unwrap();
+        let mut frate = FrateToml::load(&file_path).unwrap();
+        frate.registries.insert("internal".to_string(), "https://frate.example.com".to_string());
+        frate.scripts.clear();
+        frate.save(&file_path).unwrap();
+
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        assert!(content.starts_with("[project]\nname = \"x\"   # the name\n"));
+        assert!(!content.contains("[scripts]"));
+        let reloaded = FrateToml::load(&file_path).unwrap();
+        assert_eq!(reloaded.registries, frate.registries);
+    }
+
+    #[test]
+    fn test_set_version_of_missing_dependency() {
+        let mut frate = FrateToml::default("x");
+        let result = frate.set_version("foo", "1.0.0");
+        assert!(matches!(result, Err(Error::DependencyNotFound(_))));
+    }
+
+    #[test]
+    fn test_target_dependencies() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("frate.toml");
+        std::fs::write(&file_path, concat!(
+            "[project]\nname = \"x\"\nversion = \"0.1.0\"\n\n",
+            "[dependencies]\nshared = \"1.0.0\"\n\n",
+            "[target.'cfg(target_os = \"linux\")'.dependencies]\n",
+            "strace = \"6.10.0\"   # linux only\n",
+            "shared = \"2.0.0\"\n\n",
+            "[target.aarch64-apple-darwin.dependencies]\nmas = \"1.8.0\"\n",
+        )).unwrap();
+        let mut frate = FrateToml::load(&file_path).unwrap();
+        let linux = frate.dependencies_for(&TargetInfo::from_triple("x86_64-unknown-linux-gnu"));
+        assert_eq!(linux.keys().collect::<Vec<_>>(), ["shared", "strace"]);
+        assert_eq!(linux["shared"].version(), "2.0.0");
+        let mac = frate.dependencies_for(&TargetInfo::from_triple("aarch64-apple-darwin"));
+        assert_eq!(mac.keys().collect::<Vec<_>>(), ["mas", "shared"]);
+        assert_eq!(mac["shared"].version(), "1.0.0");
+        assert!(frate.declares("mas"));
+
+        frate.set_version("strace", "6.11.0").unwrap();
+        frate.remove("mas");
+        frate.save(&file_path).unwrap();
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        assert!(content.contains("strace = \"6.11.0\"   # linux only\n"));
+        assert!(!content.contains("mas ="));
+    }
+
+    #[test]
+    fn test_invalid_target_key() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("frate.toml");
+        std::fs::write(&file_path, concat!(
+            "[project]\nname = \"x\"\nversion = \"0.1.0\"\n\n",
+            "[dependencies]\n\n",
+            "[target.'cfg(target_os = linux)'.dependencies]\nstrace = \"6.10.0\"\n",
+        )).unwrap();
+        assert!(matches!(FrateToml::load(&file_path), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn test_unknown_registry() {
+        let frate = FrateToml::default("x");
+        let dependency = Dependency::Detailed(DetailedDependency {
+            version: "1.0.0".to_string(),
+            registry: Some("missing".to_string()),
+            ..Default::default()
+        });
+        assert!(frate.registry_url(&dependency, "default").is_err());
+    }
 }
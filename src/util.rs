@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use crate::lock::{FrateLock, LockedPackage};
-use anyhow::{bail, Result};
+use crate::error::{Error, Result};
 use regex::Regex;
-use reqwest::blocking::Client;
-use semver::Version;
+use semver::{Version, VersionReq};
 use walkdir::WalkDir;
+use crate::fetch::Fetcher;
+use crate::receipt::Receipt;
 use crate::registry::ReleaseInfo;
+use crate::shims::find_shim;
+use crate::cfg::TargetInfo;
 
 #[cfg(target_os = "windows")]
 pub const PATH_SEPARATOR: &str = "\\";
@@ -62,6 +65,23 @@ pub fn expand_version(version: &str) -> String {
     format!("{}-{}", version, triple)
 
 }
+/// Returns true if `target` names the current platform: its operating system (`"linux"`,
+/// `"macos"`, `"windows"`), family (`"unix"`), architecture (`"x86_64"`) or target triple.
+pub fn matches_target(target: &str) -> bool {
+    TargetInfo::current().matches(target)
+}
+/// Returns true if a locked version (with or without target triple) satisfies `requested`,
+/// which is either an exact version or a semver requirement such as `"^1.42"`.
+pub fn version_satisfies(locked: &str, requested: &str) -> bool {
+    if is_valid_version(requested) {
+        return locked == expand_version(requested) || locked == requested;
+    }
+    let locked = locked.split('-').next().unwrap_or(locked);
+    match (Version::parse(locked), VersionReq::parse(requested)) {
+        (Ok(version), Ok(requirement)) => requirement.matches(&version),
+        _ => false,
+    }
+}
 /// Checks whether a package with the given name is listed in the `frate.lock`.
 pub fn is_locked(name: &str, lock: &FrateLock) -> bool {
     for package in &lock.packages {
@@ -80,18 +100,20 @@ pub fn get_locked(name: &str, lock: &FrateLock) -> Option<LockedPackage> {
     }
     None
 }
-/// Checks whether a package is installed by verifying the binary path exists.
-pub fn is_installed(name: &str) -> bool {
-    let (exe_path, _) = find_installed_paths(name).unwrap_or((None, None));
+/// Checks whether a package is installed in the given `.frate` directory
+/// by verifying the binary path exists.
+pub fn is_installed(frate_dir: &Path, name: &str) -> bool {
+    let (exe_path, _) = find_installed_paths(frate_dir, name).unwrap_or((None, None));
     exe_path.is_some()
 }
-/// Finds the paths of both the installed binary and shim for a given package.
+/// Finds the paths of both the installed binary and shim for a given package
+/// inside the given `.frate` directory.
 /// Returns a tuple of `Option<PathBuf>` for (binary, shim).
 pub fn find_installed_paths(
+    frate_dir: &Path,
     name: &str
 ) -> Result<(Option<PathBuf>, Option<PathBuf>)> {
-    let cwd = std::env::current_dir()?;
-    let exe_path = get_binary(name)?;
+    let exe_path = get_binary(frate_dir, name)?;
     let exe_path = match exe_path {
         Some(exe_path) => {
             exe_path
@@ -102,22 +124,21 @@ pub fn find_installed_paths(
     };
     let exe_found = exe_path.exists();
 
-    #[cfg(target_os = "windows")]
-    let shim_path = cwd.join(".frate").join("shims")
-        .join(name).with_extension("bat");
-    #[cfg(not(target_os = "windows"))]
-    let shim_path = cwd.join(".frate").join("shims").join(name);
-
-    let shim_found = shim_path.exists();
+    // Prefer the shims recorded at install time, since shims may be renamed.
+    let shims_dir = frate_dir.join("shims");
+    let mut shim_names = Receipt::load(frate_dir, name)
+        .ok()
+        .flatten()
+        .map(|receipt| receipt.shims)
+        .unwrap_or_default();
+    shim_names.push(name.to_string());
+    let shim_path = shim_names.iter().find_map(|shim| find_shim(&shims_dir, shim));
     Ok((
         match exe_found {
             true => Some(exe_path),
             false => None,
         },
-        match shim_found {
-            true => Some(shim_path),
-            false => None,
-        }
+        shim_path,
     ))
 }
 /// Returns the full path to the `.frate` directory in the current working directory.
@@ -158,12 +179,12 @@ pub fn is_valid_version(version: &str) -> bool {
     let version = version.split('-').next().unwrap();
     Version::parse(version).is_ok()
 }
-/// Searches for the binary file in the `.frate/bin/<name>` directory.
+/// Searches for the binary file in the `<frate_dir>/bin/<name>` directory.
 /// Picks the first executable that matches the tool name heuristically.
 ///
 /// Returns an error if no suitable binary is found.
-pub fn get_binary(name: &str) -> Result<Option<PathBuf>> {
-    let path = get_frate_bin_dir()?.join(name);
+pub fn get_binary(frate_dir: &Path, name: &str) -> Result<Option<PathBuf>> {
+    let path = frate_dir.join("bin").join(name);
     if !path.exists() {
         return Ok(None);
     }
@@ -180,7 +201,7 @@ pub fn get_binary(name: &str) -> Result<Option<PathBuf>> {
     }
 
     if candidates.is_empty() {
-        bail!("No executable found in '{}'", path.display());
+        return Err(Error::BinaryNotFound(path.display().to_string()));
     }
 
     let re = Regex::new(&format!(r"(?i)\b{}.*", regex::escape(name)))?;
@@ -194,9 +215,29 @@ pub fn get_binary(name: &str) -> Result<Option<PathBuf>> {
     });
     Ok(Some(candidates.remove(0)))
 }
+/// Searches `<frate_dir>/bin/<name>` for an executable whose file name without extension
+/// is `binary`. Returns `None` if there is none.
+///
+/// # Errors
+/// Returns an error if the directory cannot be walked.
+pub fn find_package_binary(frate_dir: &Path, name: &str, binary: &str) -> Result<Option<PathBuf>> {
+    let path = frate_dir.join("bin").join(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    for entry in WalkDir::new(&path) {
+        let entry = entry?;
+        let path = entry.path();
+        let stem = path.file_stem().unwrap_or_default();
+        if stem == binary && entry.file_type().is_file() && is_executable(path) {
+            return Ok(Some(path.to_path_buf()));
+        }
+    }
+    Ok(None)
+}
 /// Checks if a given path is an executable file on Unix.
 #[cfg(unix)]
-fn is_executable(path: &Path) -> bool {
+pub(crate) fn is_executable(path: &Path) -> bool {
     use std::os::unix::fs::PermissionsExt;
     std::fs::metadata(path)
         .map(|meta| meta.permissions().mode() & 0o111 != 0)
@@ -238,23 +279,22 @@ struct GitHubRepo {
     description: Option<String>,
 }
 
-pub fn fetch_description(url: &str) -> Result<Option<String>> {
+/// Fetches the description of a GitHub repository through the GitHub API.
+///
+/// Returns `None` if the repository has no description or does not exist.
+pub fn fetch_description(fetcher: &dyn Fetcher, url: &str) -> Result<Option<String>> {
     let api_url = convert_url_to_api_url(url)?;
-    let client = Client::new();
-    let resp = client
-        .get(&api_url)
-        .header("User-Agent", "frate")
-        .send()?
-        .error_for_status()?;
-
-    let repo_data: GitHubRepo = resp.json()?;
+    let Some(body) = fetcher.fetch(&api_url)? else {
+        return Ok(None);
+    };
+    let repo_data: GitHubRepo = serde_json::from_slice(&body)?;
     Ok(repo_data.description)
 }
 
 fn convert_url_to_api_url(url: &str) -> Result<String> {
     let parts: Vec<&str> = url.trim_end_matches('/').split('/').collect();
     if parts.len() < 2 {
-        bail!("Invalid URL: {}", url);
+        return Err(Error::Other(format!("Invalid URL: {}", url)));
     }
     let owner = parts[parts.len() - 2];
     let name = parts[parts.len() - 1];
@@ -337,12 +377,14 @@ mod tests {
                     version: "1.0.0".to_string(),
                     source: "".to_string(),
                     hash: "".to_string(),
+                    ..Default::default()
                 },
                 LockedPackage {
                     name: "tool-b".to_string(),
                     version: "2.0.0".to_string(),
                     source: "".to_string(),
                     hash: "".to_string(),
+                    ..Default::default()
                 },
             ],
         }
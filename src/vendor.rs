@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::Result;
+use crate::fetch::Fetcher;
+use crate::global::cache::archive_name;
+use crate::installer::{fetch_archive, verify_archive};
+use crate::lock::LockedPackage;
+use crate::report::{Event, Reporter};
+
+/// The name of the manifest `frate vendor` writes next to the vendored archives.
+pub const VENDOR_MANIFEST: &str = "vendor.toml";
+
+const VENDOR_HEADER: &str = "# This file is generated by `frate vendor`. Do not edit it by hand.\n\n";
+
+/// Lists the archives in a vendor directory, see [`vendor_packages`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct VendorManifest {
+    /// The vendored archives, sorted by package name.
+    #[serde(default)]
+    pub archives: Vec<VendoredArchive>,
+}
+
+/// An archive copied into a vendor directory.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct VendoredArchive {
+    /// Name of the package.
+    pub name: String,
+    /// Locked version of the package.
+    pub version: String,
+    /// File name of the archive in the vendor directory.
+    pub file: String,
+    /// The URL the archive was locked from.
+    pub source: String,
+    /// SHA-256 hash of the archive, as in `frate.lock`.
+    pub hash: String,
+}
+
+impl VendorManifest {
+    /// Loads the manifest of the vendor directory `dir`, or returns an empty manifest if
+    /// there is none.
+    ///
+    /// # Errors
+    /// Returns an error if the manifest exists but cannot be read or parsed.
+    pub fn load(dir: &Path) -> Result<VendorManifest> {
+        let path = dir.join(VENDOR_MANIFEST);
+        if !path.exists() {
+            return Ok(VendorManifest::default());
+        }
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+    /// Writes the manifest to the vendor directory `dir`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let content = format!("{}{}", VENDOR_HEADER, toml::to_string_pretty(self)?);
+        fs::write(dir.join(VENDOR_MANIFEST), content)?;
+        Ok(())
+    }
+}
+
+/// Returns the path of the vendored archive of `package` in `vendor_dir`, if it exists.
+/// The archive is not verified; [`install_package`](crate::install_package) checks it against
+/// the lock hash before extracting it.
+pub fn vendored_archive(vendor_dir: &Path, package: &LockedPackage) -> Option<PathBuf> {
+    let path = vendor_dir.join(archive_name(&package.source).ok()?);
+    path.is_file().then_some(path)
+}
+
+/// Copies the archives of `packages` into `vendor_dir` and writes a [`VendorManifest`] listing them.
+///
+/// Each archive is taken from the vendor directory itself if it is already there, else from the
+/// global cache, else downloaded, and checked against the hash in `frate.lock`. Archives that an
+/// earlier run vendored for packages that are no longer in `packages` are removed.
+///
+/// # Errors
+/// Returns [`Error::HashMismatch`](crate::Error::HashMismatch) if an archive does not match its
+/// lock hash, or an error if it cannot be downloaded or written.
+pub fn vendor_packages(
+    packages: &[LockedPackage],
+    vendor_dir: &Path,
+    fetcher: &dyn Fetcher,
+    reporter: &dyn Reporter
+) -> Result<VendorManifest> {
+    fs::create_dir_all(vendor_dir)?;
+    let previous = VendorManifest::load(vendor_dir)?;
+    let mut manifest = VendorManifest::default();
+    for package in packages {
+        let file = archive_name(&package.source)?.to_string();
+        let path = vendor_dir.join(&file);
+        let current = match path.is_file() {
+            true => verify_archive(&fs::read(&path)?, &package.hash, &file).is_ok(),
+            false => false,
+        };
+        if !current {
+            fs::write(&path, fetch_archive(package, None, fetcher, reporter)?)?;
+            reporter.report(Event::Vendored { name: package.name.clone(), path: path.clone() });
+        }
+        manifest.archives.push(VendoredArchive {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            file,
+            source: package.source.clone(),
+            hash: package.hash.clone(),
+        });
+    }
+    manifest.archives.sort_by(|a, b| a.name.cmp(&b.name));
+    for stale in &previous.archives {
+        if !manifest.archives.iter().any(|archive| archive.file == stale.file) {
+            let path = vendor_dir.join(&stale.file);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    manifest.save(vendor_dir)?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::error::Error;
+    use sha2::Digest;
+    use tempfile::tempdir;
+    use crate::fetch::testing::InMemory;
+    use crate::report::Silent;
+
+    fn package(name: &str, bytes: &[u8]) -> LockedPackage {
+        LockedPackage {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            source: format!("https://example.test/{}-vendor-test.tar.gz", name),
+            hash: format!("sha256:{}", hex::encode(sha2::Sha256::digest(bytes))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_vendor_packages() {
+        let dir = tempdir().unwrap();
+        let a = package("a", b"archive a");
+        let b = package("b", b"archive b");
+        let fetcher = InMemory(HashMap::from([
+            (a.source.clone(), b"archive a".to_vec()),
+            (b.source.clone(), b"archive b".to_vec()),
+        ]));
+
+        let manifest = vendor_packages(&[b.clone(), a.clone()], dir.path(), &fetcher, &Silent).unwrap();
+        assert_eq!(manifest.archives.iter().map(|archive| archive.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(VendorManifest::load(dir.path()).unwrap(), manifest);
+        assert_eq!(vendored_archive(dir.path(), &a), Some(dir.path().join("a-vendor-test.tar.gz")));
+
+        vendor_packages(std::slice::from_ref(&a), dir.path(), &InMemory(HashMap::new()), &Silent).unwrap();
+        assert!(vendored_archive(dir.path(), &b).is_none());
+
+        let mut tampered = b.clone();
+        tampered.hash = a.hash.clone();
+        let result = vendor_packages(&[tampered], dir.path(), &fetcher, &Silent);
+        assert!(matches!(result, Err(Error::HashMismatch { .. })));
+    }
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+use crate::error::Result;
+use crate::receipt::{hash_files, Receipt};
+
+/// The result of checking an installed package against the file digests in its [`Receipt`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Verification {
+    /// Name of the package.
+    pub name: String,
+    /// False if the package has no receipt or its receipt records no files,
+    /// so nothing could be checked.
+    pub recorded: bool,
+    /// Files whose content differs from the recorded digest.
+    pub modified: Vec<String>,
+    /// Recorded files that no longer exist.
+    pub missing: Vec<String>,
+    /// Files that exist but were not installed by frate.
+    pub unexpected: Vec<String>,
+}
+
+impl Verification {
+    /// Returns true if all recorded files are present and unchanged and no files were added.
+    pub fn is_intact(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+/// Compares the files in `.frate/bin/<name>` with the digests recorded at install time.
+///
+/// Paths in the result are relative to `.frate/bin/<name>` and use `/` as separator.
+///
+/// # Errors
+/// Returns an error if the receipt cannot be read or an installed file cannot be hashed.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::path::Path;
+/// use frate::verify_package;
+///
+/// let verification = verify_package(Path::new(".frate"), "just").unwrap();
+/// assert!(verification.is_intact());
+/// ```
+pub fn verify_package(frate_dir: &Path, name: &str) -> Result<Verification> {
+    let mut verification = Verification { name: name.to_string(), ..Default::default() };
+    let receipt = match Receipt::load(frate_dir, name)? {
+        Some(receipt) if !receipt.files.is_empty() => receipt,
+        _ => return Ok(verification),
+    };
+    verification.recorded = true;
+
+    let install_dir = frate_dir.join("bin").join(name);
+    let actual = if install_dir.exists() { hash_files(&install_dir)? } else { Default::default() };
+    for (path, digest) in &receipt.files {
+        match actual.get(path) {
+            None => verification.missing.push(path.clone()),
+            Some(actual) if actual != digest => verification.modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    verification.unexpected = actual.keys()
+        .filter(|path| !receipt.files.contains_key(*path))
+        .cloned()
+        .collect();
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock::LockedPackage;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_verify_package() {
+        let dir = tempdir().unwrap();
+        let install_dir = dir.path().join("bin").join("tool");
+        std::fs::create_dir_all(&install_dir).unwrap();
+        std::fs::write(install_dir.join("tool"), b"binary").unwrap();
+        std::fs::write(install_dir.join("README"), b"readme").unwrap();
+        let package = LockedPackage {
+            name: "tool".to_string(),
+            version: "1.0.0".to_string(),
+            source: String::new(),
+            hash: String::new(),
+            ..Default::default()
+        };
+        Receipt::for_package(&package).with_files(&install_dir).unwrap().save(dir.path()).unwrap();
+        assert!(verify_package(dir.path(), "tool").unwrap().is_intact());
+
+        std::fs::write(install_dir.join("tool"), b"tampered").unwrap();
+        std::fs::remove_file(install_dir.join("README")).unwrap();
+        std::fs::write(install_dir.join("extra"), b"extra").unwrap();
+        let verification = verify_package(dir.path(), "tool").unwrap();
+        assert!(verification.recorded);
+        assert_eq!(verification.modified, vec!["tool"]);
+        assert_eq!(verification.missing, vec!["README"]);
+        assert_eq!(verification.unexpected, vec!["extra"]);
+    }
+
+    #[test]
+    fn test_verify_without_receipt() {
+        let dir = tempdir().unwrap();
+        let verification = verify_package(dir.path(), "tool").unwrap();
+        assert!(!verification.recorded);
+        assert!(verification.is_intact());
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use crate::error::{Error, Result};
+use crate::toml::FrateToml;
+
+/// A workspace: a directory whose `frate.toml` has a `[workspace]` table, and the member
+/// projects it lists.
+///
+/// The members share the `frate.lock` and the `.frate` directory of the root, which lock and
+/// install the tools of all members together.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// The workspace root.
+    pub root: PathBuf,
+    /// The manifests of the root and of every member, root first, with their directory.
+    pub manifests: Vec<(PathBuf, FrateToml)>,
+}
+
+/// A tool that manifests of a workspace request in different versions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionConflict {
+    /// Name of the tool.
+    pub name: String,
+    /// The requested versions, with the directory of the requesting manifest relative to
+    /// the workspace root.
+    pub requests: Vec<(PathBuf, String)>,
+}
+
+impl Display for VersionConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let requests: Vec<String> = self.requests.iter()
+            .map(|(dir, version)| format!("{} ({})", version, dir.display()))
+            .collect();
+        write!(f, "{}: {}", self.name, requests.join(", "))
+    }
+}
+
+impl Workspace {
+    /// Loads the workspace rooted at `root`, whose `frate.toml` is `manifest`, and the manifests
+    /// of all its members.
+    ///
+    /// # Errors
+    /// Returns an error if `manifest` has no `[workspace]` table, or a member has no
+    /// `frate.toml` or it cannot be parsed.
+    pub fn load(root: &Path, manifest: &FrateToml) -> Result<Workspace> {
+        let config = manifest.workspace.as_ref()
+            .ok_or_else(|| Error::Other(format!("{} is not a workspace root", root.display())))?;
+        let mut manifests = vec![(root.to_path_buf(), manifest.clone())];
+        for member in &config.members {
+            for dir in member_dirs(root, member)? {
+                let path = dir.join("frate.toml");
+                if !path.is_file() {
+                    return Err(Error::ManifestNotFound(dir));
+                }
+                let manifest = FrateToml::load(&path)?;
+                manifests.push((dir, manifest));
+            }
+        }
+        Ok(Workspace { root: root.to_path_buf(), manifests })
+    }
+    /// Returns the workspace that `dir` is a member of: the nearest ancestor of `dir` with a
+    /// `[workspace]` table, if it lists `dir` as a member.
+    ///
+    /// # Errors
+    /// Returns an error if a manifest of the workspace cannot be loaded.
+    pub fn find(dir: &Path) -> Result<Option<Workspace>> {
+        let dir = std::path::absolute(dir)?;
+        for ancestor in dir.ancestors().skip(1) {
+            let path = ancestor.join("frate.toml");
+            if !path.is_file() {
+                continue;
+            }
+            let manifest = FrateToml::load(&path)?;
+            if manifest.workspace.is_none() {
+                continue;
+            }
+            let workspace = Workspace::load(ancestor, &manifest)?;
+            return Ok(workspace.is_member(&dir).then_some(workspace));
+        }
+        Ok(None)
+    }
+    /// Returns true if `dir` is the root or a member of the workspace.
+    pub fn is_member(&self, dir: &Path) -> bool {
+        self.manifests.iter().any(|(member, _)| same_dir(member, dir))
+    }
+    /// Returns the tools that manifests of the workspace request in different versions on the
+    /// current platform. `own` replaces the loaded manifest of the directory `dir`.
+    pub fn conflicts(&self, dir: &Path, own: &FrateToml) -> Vec<VersionConflict> {
+        let mut requests: BTreeMap<String, Vec<(PathBuf, String)>> = BTreeMap::new();
+        for (member, manifest) in self.with_own(dir, own) {
+            let relative = member.strip_prefix(&self.root).unwrap_or(member);
+            let relative = if relative.as_os_str().is_empty() { Path::new(".") } else { relative };
+            for (name, dependency) in manifest.current_dependencies() {
+                requests.entry(name).or_default().push((relative.to_path_buf(), dependency.version().to_string()));
+            }
+        }
+        requests.into_iter()
+            .filter(|(_, requests)| requests.iter().any(|(_, version)| *version != requests[0].1))
+            .map(|(name, requests)| VersionConflict { name, requests })
+            .collect()
+    }
+    /// Merges the manifests of the workspace, with `own` replacing the loaded manifest of the
+    /// directory `dir`. See [`merge_manifests`].
+    pub fn merged(&self, dir: &Path, own: &FrateToml) -> FrateToml {
+        merge_manifests(self.with_own(dir, own).map(|(_, manifest)| manifest))
+    }
+    fn with_own<'a>(&'a self, dir: &'a Path, own: &'a FrateToml) -> impl Iterator<Item = (&'a Path, &'a FrateToml)> {
+        self.manifests.iter().map(move |(member, manifest)| {
+            (member.as_path(), if same_dir(member, dir) { own } else { manifest })
+        })
+    }
+}
+
+/// Merges manifests into one that declares the dependencies of all of them used on the current
+/// platform, with the registries and `[tools]` tables of all of them.
+///
+/// For a tool declared more than once, the first declaration wins. The project, settings and
+/// scripts are taken from the first manifest.
+pub fn merge_manifests<'a>(manifests: impl IntoIterator<Item = &'a FrateToml>) -> FrateToml {
+    let mut manifests = manifests.into_iter();
+    let Some(first) = manifests.next() else {
+        return FrateToml::default("");
+    };
+    let mut merged = first.clone();
+    merged.dependencies = first.current_dependencies();
+    merged.target.clear();
+    for manifest in manifests {
+        for (name, dependency) in manifest.current_dependencies() {
+            merged.dependencies.entry(name).or_insert(dependency);
+        }
+        for (name, url) in &manifest.registries {
+            merged.registries.entry(name.clone()).or_insert_with(|| url.clone());
+        }
+        for (name, tool) in &manifest.tools {
+            merged.tools.entry(name.clone()).or_insert_with(|| tool.clone());
+        }
+    }
+    merged
+}
+
+/// Returns the directories `member` of the workspace at `root` stands for.
+fn member_dirs(root: &Path, member: &str) -> Result<Vec<PathBuf>> {
+    let Some(parent) = member.strip_suffix('*') else {
+        return Ok(vec![root.join(member)]);
+    };
+    let parent = root.join(parent.trim_end_matches('/'));
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(&parent)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|dir| dir.join("frate.toml").is_file())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(dir: &Path, content: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("frate.toml"), content).unwrap();
+    }
+
+    #[test]
+    fn test_find_and_merge() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, "[workspace]\nmembers = [\"services/*\"]\n\n[dependencies]\njust = \"1.42.0\"\n");
+        write(&root.join("services").join("api"), "[project]\nname = \"api\"\nversion = \"0.1.0\"\n\n[dependencies]\nripgrep = \"14.1.1\"\n");
+        write(&root.join("services").join("web"), "[project]\nname = \"web\"\nversion = \"0.1.0\"\n\n[dependencies]\njust = \"1.43.0\"\n");
+        std::fs::create_dir_all(root.join("services").join("docs")).unwrap();
+
+        let api = root.join("services").join("api");
+        let workspace = Workspace::find(&api).unwrap().unwrap();
+        assert_eq!(workspace.manifests.len(), 3);
+        let own = FrateToml::load(api.join("frate.toml")).unwrap();
+        let merged = workspace.merged(&api, &own);
+        assert_eq!(merged.dependencies.keys().collect::<Vec<_>>(), ["just", "ripgrep"]);
+        assert_eq!(merged.dependencies["just"].version(), "1.42.0");
+
+        let conflicts = workspace.conflicts(&api, &own);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].to_string(), format!("just: 1.42.0 (.), 1.43.0 ({})", Path::new("services").join("web").display()));
+        assert!(Workspace::find(&root.join("services").join("docs")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_missing_member() {
+        let dir = tempdir().unwrap();
+        write(dir.path(), "[workspace]\nmembers = [\"missing\"]\n");
+        let manifest = FrateToml::load(dir.path().join("frate.toml")).unwrap();
+        assert!(matches!(Workspace::load(dir.path(), &manifest), Err(Error::ManifestNotFound(_))));
+    }
+}
@@ -1,3 +1,5 @@
+mod support;
+
 use assert_cmd::Command;
 use std::fs;
 use tempfile::tempdir;
@@ -19,11 +21,25 @@ fn test_execute_init_creates_frate_toml() {
     assert!(content.contains("[dependencies]"));
 }
 
+#[test]
+fn test_missing_manifest_exit_code() {
+    let dir = tempdir().unwrap();
+
+    Command::cargo_bin("frate").unwrap()
+        .current_dir(dir.path())
+        .arg("list")
+        .assert()
+        .code(3);
+}
+
 #[cfg(test)]
 mod cli_integration_tests {
     use assert_cmd::Command;
+    use predicates::prelude::PredicateBooleanExt;
+    use predicates::str::contains;
     use tempfile::tempdir;
-    use frate::FrateToml;
+    use frate::{current_target_triple, FrateToml};
+    use crate::support::{self, Archive, Registry};
 
     #[test]
     fn test_execute_sync() {
@@ -73,101 +89,1139 @@ mod cli_integration_tests {
         assert!(output_str.contains("hello: 1.0.0"));
     }
 
-    #[cfg(not(ci_skip))]
     #[test]
     fn test_execute_install_and_uninstall_and_run_and_which_and_clean() {
+        let registry = Registry::start();
+        registry.add_tool("fixture-tool", "1.2.0", Archive::TarGz);
         let dir = tempdir().unwrap();
         let dir_path = dir.path();
-        let toml = FrateToml::default("tests");
-        toml.save(dir_path.join("frate.toml")).unwrap();
+        let cache = tempdir().unwrap();
+        let frate = |args: &[&str]| {
+            let mut cmd = support::frate(dir_path, cache.path());
+            cmd.args(args);
+            cmd
+        };
 
         // Clean all caches
-        Command::cargo_bin("frate").unwrap()
-            .arg("clean")
+        frate(&["clean"])
             .assert()
             .success();
 
         // Init und Add für Setup
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .arg("init")
+        frate(&["init"])
             .assert()
             .success();
+        let mut toml = FrateToml::load(dir_path.join("frate.toml")).unwrap();
+        toml.settings.registry = registry.url().to_string();
+        toml.save(dir_path.join("frate.toml")).unwrap();
 
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .args(&["add", "just@1.42.1"])
+        frate(&["add", "fixture-tool@1.2.0"])
             .assert()
             .success();
 
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
+        frate(&["sync"])
+            .assert()
+            .success()
+            .stdout(contains("fixture-tool"));
+
+        // Install specific package
+        frate(&["install", "--name", "fixture-tool"])
+            .assert()
+            .success()
+            .stdout(contains("Downloading"));
+        assert!(cache.path().join(format!("fixture-tool-1.2.0-{}.tar.gz", current_target_triple())).exists());
+
+        // Uninstall specific package
+        frate(&["uninstall", "--name", "fixture-tool"])
+            .assert()
+            .success();
+
+        // Install all packages, this time from the cache
+        frate(&["install"])
+            .assert()
+            .success()
+            .stdout(contains("Downloading").not());
+
+        frate(&["run", "fixture-tool", "--version", "two words"])
+            .assert()
+            .success()
+            .stdout(contains("fixture-tool 1.2.0"))
+            .stdout(contains("arg: --version"))
+            .stdout(contains("arg: two words"));
+
+        frate(&["which", "fixture-tool"])
+            .assert()
+            .success()
+            .stdout(contains("Binary"))
+            .stdout(contains("Shim"));
+
+        // Uninstall all packages
+        frate(&["uninstall"])
+            .assert()
+            .success();
+        frate(&["which", "fixture-tool"])
+            .assert()
+            .success()
+            .stdout(contains("No installed paths found"));
+
+        // Clean `fixture-tool` cache
+        frate(&["clean", "-n", "fixture-tool"])
+            .assert()
+            .success();
+        assert!(!cache.path().join(format!("fixture-tool-1.2.0-{}.tar.gz", current_target_triple())).exists());
+    }
+
+    #[test]
+    fn test_execute_install_zip() {
+        let registry = Registry::start();
+        registry.add_tool("zip-tool", "0.3.1", Archive::Zip);
+        let dir = support::project(&registry, &[("zip-tool", "0.3.1")]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
             .arg("sync")
             .assert()
             .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .args(["run", "zip-tool", "--", "-V"])
+            .assert()
+            .success()
+            .stdout(contains("zip-tool 0.3.1"));
+    }
 
-        // Install specific package
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .args(&["install", "--name","just"])
+    #[test]
+    fn test_execute_install_hash_mismatch() {
+        let registry = Registry::start();
+        registry.add_tool_with_wrong_hash("bad-tool", "1.0.0");
+        let dir = support::project(&registry, &[("bad-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
             .assert()
             .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .code(9)
+            .stderr(contains("hash mismatch"));
+        assert!(std::fs::read_dir(cache.path()).map(|d| d.count() == 0).unwrap_or(true));
+    }
 
+    #[test]
+    fn test_execute_install_missing_archive() {
+        let registry = Registry::start();
+        registry.add_tool_without_archive("gone-tool", "1.0.0");
+        let dir = support::project(&registry, &[("gone-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
 
-        // Uninstall specific package
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .args(&["uninstall", "--name", "just"])
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
             .assert()
             .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .code(12);
+    }
 
-        // Install all packages
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
+    #[test]
+    fn test_execute_sync_unknown_tool() {
+        let registry = Registry::start();
+        let dir = support::project(&registry, &[("no-such-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .code(4)
+            .stderr(contains("no-such-tool"));
+
+        let manifest = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap()
+            .replace("no-such-tool = \"1.0.0\"", "no-such-tool = { version = \"1.0.0\", optional = true }");
+        std::fs::write(dir.path().join("frate.toml"), manifest).unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success()
+            .stderr(contains("no-such-tool"));
+    }
+
+    #[test]
+    fn test_execute_search() {
+        let registry = Registry::start();
+        registry.add_tool("search-tool", "1.0.0", Archive::TarGz);
+        registry.add_tool("search-tool", "1.1.0", Archive::TarGz);
+        let dir = support::project(&registry, &[]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .args(["search", "search-tool"])
+            .assert()
+            .success()
+            .stdout(contains("1.1.0"));
+        support::frate(dir.path(), cache.path())
+            .args(["search", "unknown-tool"])
+            .assert()
+            .code(4);
+    }
+
+    #[test]
+    fn test_execute_registry() {
+        let registry = Registry::start();
+        registry.add_tool("listed-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("registry")
+            .assert()
+            .success()
+            .stdout(contains("listed-tool"));
+    }
+
+    #[test]
+    fn test_execute_doctor_fix() {
+        let registry = Registry::start();
+        registry.add_tool("doctor-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("doctor-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("doctor")
+            .assert()
+            .code(1)
+            .stdout(contains("doctor-tool is declared in frate.toml but not locked"));
+        support::frate(dir.path(), cache.path())
+            .args(["doctor", "--fix"])
+            .assert()
+            .success()
+            .stdout(contains("✘").not());
+        support::frate(dir.path(), cache.path())
+            .arg("doctor")
+            .assert()
+            .success()
+            .stdout(contains("all locked packages are installed"));
+    }
+
+    #[test]
+    fn test_execute_verify_and_repair() {
+        let registry = Registry::start();
+        registry.add_tool("verify-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("verify-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
             .arg("install")
             .assert()
             .success();
+        support::frate(dir.path(), cache.path())
+            .arg("verify")
+            .assert()
+            .success();
 
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .args(&["run", "just --version"])
+        let install_dir = dir.path().join(".frate").join("bin").join("verify-tool");
+        let binary = install_dir.join("verify-tool-1.0.0").join(if cfg!(windows) { "verify-tool.cmd" } else { "verify-tool" });
+        std::fs::write(&binary, "tampered").unwrap();
+        std::fs::write(install_dir.join("extra"), "extra").unwrap();
+        support::frate(dir.path(), cache.path())
+            .args(["verify", "verify-tool"])
+            .assert()
+            .code(1)
+            .stdout(contains("modified:").and(contains("unexpected: extra")));
+        support::frate(dir.path(), cache.path())
+            .args(["verify", "verify-tol"])
+            .assert()
+            .code(6);
+
+        support::frate(dir.path(), cache.path())
+            .args(["verify", "--repair"])
             .assert()
             .success();
+        support::frate(dir.path(), cache.path())
+            .arg("verify")
+            .assert()
+            .success();
+        assert!(!install_dir.join("extra").exists());
 
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .arg("which")
-            .arg("just")
+        // Without a lock entry there is no archive to reinstall from.
+        std::fs::write(&binary, "tampered").unwrap();
+        std::fs::write(dir.path().join("frate.lock"), "").unwrap();
+        support::frate(dir.path(), cache.path())
+            .args(["verify", "--repair", "verify-tool"])
+            .assert()
+            .code(1)
+            .stderr(contains("not in frate.lock and cannot be repaired: verify-tool"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_verify_with_symlinks() {
+        let registry = Registry::start();
+        registry.add_tool("link-tool", "1.0.0", Archive::TarGzWithSymlinks);
+        let dir = support::project(&registry, &[("link-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("verify")
             .assert()
             .success();
 
-        // Uninstall all packages
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .arg("uninstall")
+        let install_dir = dir.path().join(".frate").join("bin").join("link-tool").join("link-tool-1.0.0");
+        std::fs::remove_file(install_dir.join("lib64")).unwrap();
+        std::os::unix::fs::symlink("elsewhere", install_dir.join("lib64")).unwrap();
+        support::frate(dir.path(), cache.path())
+            .args(["verify", "link-tool"])
+            .assert()
+            .code(1)
+            .stdout(contains("modified:").and(contains("lib64")));
+    }
+
+    #[test]
+    fn test_execute_run_exit_code_and_stdin() {
+        let registry = Registry::start();
+        registry.add_tool("run-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("run-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .args(["run", "run-tool"])
+            .env("FIXTURE_EXIT", "42")
+            .assert()
+            .code(42)
+            .stdout(contains("run-tool 1.0.0"));
+        support::frate(dir.path(), cache.path())
+            .args(["run", "run-tool", "--", "--stdin"])
+            .write_stdin("from stdin\n")
+            .assert()
+            .success()
+            .stdout(contains("stdin: from stdin"));
+        support::frate(dir.path(), cache.path())
+            .args(["run", "missing-tool"])
+            .assert()
+            .code(11);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_run_script() {
+        let registry = Registry::start();
+        registry.add_tool("script-tool", "2.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("script-tool", "2.0.0")]);
+        let cache = tempdir().unwrap();
+        let mut toml = FrateToml::load(dir.path().join("frate.toml")).unwrap();
+        toml.scripts = toml::from_str(concat!(
+            "prepare = \"echo preparing\"\n",
+            "prelint = \"echo pre-hook\"\n",
+            "lint = { run = \"script-tool check\", depends = [\"prepare\"] }\n",
+            "fail = \"exit 3\"\n",
+        )).unwrap();
+        toml.save(dir.path().join("frate.toml")).unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
             .assert()
             .success();
+        // The tool is not installed yet; running a script installs it first.
+        support::frate(dir.path(), cache.path())
+            .args(["run", "lint", "extra arg"])
+            .assert()
+            .success()
+            .stdout(contains("preparing\npre-hook\nscript-tool 2.0.0\narg: check\narg: extra arg"));
+        support::frate(dir.path(), cache.path())
+            .args(["run", "fail"])
+            .assert()
+            .code(3);
+        support::frate(dir.path(), cache.path())
+            .args(["run", "--list"])
+            .assert()
+            .success()
+            .stdout(contains("lint: script-tool check").and(contains("depends on: prepare")));
+    }
 
-        // Clean `just` cache
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(dir_path)
-            .arg("clean")
-            .arg("-n")
-            .arg("just")
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_tool_environment() {
+        let registry = Registry::start();
+        registry.add_tool_with_env(
+            "env-var-tool",
+            "1.0.0",
+            &[("FIXTURE_EXIT", "3"), ("FIXTURE_HOME", "{install_dir}/share")],
+            &["{install_dir}/extra"],
+        );
+        let dir = support::project(&registry, &[("env-var-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        let mut toml = FrateToml::load(dir.path().join("frate.toml")).unwrap();
+        toml.scripts = toml::from_str("show = \"echo home=$FIXTURE_HOME path=$PATH\"\n").unwrap();
+        toml.save(dir.path().join("frate.toml")).unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
             .assert()
             .success();
+
+        let install_dir = dir.path().join(".frate").join("bin").join("env-var-tool");
+        // The shim sets the variables itself, so the tool exits with FIXTURE_EXIT.
+        Command::new(dir.path().join(".frate").join("shims").join("env-var-tool"))
+            .env_remove("FIXTURE_EXIT")
+            .assert()
+            .code(3)
+            .stdout(contains("env-var-tool 1.0.0"));
+        support::frate(dir.path(), cache.path())
+            .args(["run", "show"])
+            .assert()
+            .success()
+            .stdout(contains(format!("home={}/share", install_dir.display()))
+                .and(contains(format!("{}/extra", install_dir.display()))));
+        frate::Project::load(dir.path()).unwrap().write_activate().unwrap();
+        Command::new("bash")
+            .arg("-c")
+            .arg(". .frate/activate && echo \"home=$FIXTURE_HOME\" && deactivate && echo \"after=$FIXTURE_HOME\"")
+            .current_dir(dir.path())
+            .env_remove("FIXTURE_HOME")
+            .assert()
+            .success()
+            .stdout(contains(format!("home={}/share", install_dir.display())).and(contains("after=\n")));
+
+        // `[tools.<name>.env]` in frate.toml overrides the registry.
+        let mut toml = FrateToml::load(dir.path().join("frate.toml")).unwrap();
+        toml.tools = toml::from_str("[env-var-tool.env]\nFIXTURE_EXIT = \"4\"\n").unwrap();
+        toml.save(dir.path().join("frate.toml")).unwrap();
+        support::frate(dir.path(), cache.path())
+            .args(["run", "env-var-tool"])
+            .assert()
+            .code(4);
     }
 
     #[test]
-    fn test_execute_search() {
-        let dir = tempdir().unwrap();
-        let dir_path = dir.path();
+    fn test_binary_shims() {
+        let registry = Registry::start();
+        registry.add_tool_with_env("binary-shim-tool", "1.0.0", &[("FIXTURE_EXIT", "3")], &[]);
+        let dir = support::project(&registry, &[("binary-shim-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        let mut toml = FrateToml::load(dir.path().join("frate.toml")).unwrap();
+        toml.settings.shim_mode = frate::ShimMode::Binary;
+        toml.save(dir.path().join("frate.toml")).unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
 
-        Command::cargo_bin("frate").unwrap()
-            .current_dir(&dir_path)
-            .args(&["search", "ripgrep"])
+        let shims = dir.path().join(".frate").join("shims");
+        let shim = shims.join(format!("binary-shim-tool{}", std::env::consts::EXE_SUFFIX));
+        assert!(!shim.symlink_metadata().unwrap().file_type().is_symlink());
+        assert!(shims.join("binary-shim-tool.shim").exists());
+
+        // Shims keep working when the project is moved.
+        let moved = tempdir().unwrap();
+        let moved_root = moved.path().join("moved");
+        std::fs::rename(dir.path(), &moved_root).unwrap();
+        let shim = moved_root.join(".frate").join("shims").join(shim.file_name().unwrap());
+        Command::new(&shim)
+            .arg("two words")
+            .env_remove("FIXTURE_EXIT")
+            .assert()
+            .code(3)
+            .stdout(contains("binary-shim-tool 1.0.0\narg: two words"));
+        support::frate(&moved_root, cache.path())
+            .arg("doctor")
+            .assert()
+            .stdout(contains("all shims point at existing binaries"));
+
+        support::frate(&moved_root, cache.path())
+            .args(["uninstall", "--name", "binary-shim-tool"])
+            .assert()
+            .success();
+        assert!(!shim.exists());
+        assert!(!moved_root.join(".frate").join("shims").join("binary-shim-tool.shim").exists());
+        std::fs::rename(&moved_root, dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_lazy_install_from_shims() {
+        let registry = Registry::start();
+        registry.add_tool("lazy-tool", "1.0.0", Archive::TarGz);
+        registry.add_tool("lazy-tool", "1.1.0", Archive::TarGz);
+        registry.add_tool("lazy-new", "2.0.0", Archive::Zip);
+        let dir = support::project(&registry, &[("lazy-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        let set_dependency = |name: &str, version: &str| {
+            let mut toml = FrateToml::load(dir.path().join("frate.toml")).unwrap();
+            toml.settings.shim_mode = frate::ShimMode::Binary;
+            toml.dependencies.insert(name.to_string(), version.into());
+            toml.save(dir.path().join("frate.toml")).unwrap();
+            // Like pulling a lockfile that a teammate updated.
+            let lock_path = dir.path().join("frate.lock");
+            let mut lock = frate::FrateLock::load_or_default(&lock_path);
+            lock.remove(name);
+            lock.save(&lock_path).unwrap();
+            support::frate(dir.path(), cache.path())
+                .arg("sync")
+                .assert()
+                .success();
+        };
+        let shim = |name: &str| {
+            let mut cmd = Command::new(dir.path().join(".frate").join("shims").join(name));
+            cmd.env("FRATE_CACHE_DIR", cache.path()).env("NO_PROXY", "127.0.0.1");
+            cmd
+        };
+        set_dependency("lazy-tool", "1.0.0");
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+
+        // The locked version changed: the shim installs it before running.
+        set_dependency("lazy-tool", "1.1.0");
+        shim("lazy-tool")
+            .arg("--flag")
+            .assert()
+            .success()
+            .stdout("lazy-tool 1.1.0\narg: --flag\n")
+            .stderr(contains("installing lazy-tool"));
+        // Newly locked packages get a shim on sync that installs them.
+        set_dependency("lazy-new", "2.0.0");
+        support::frate(dir.path(), cache.path())
+            .arg("doctor")
+            .assert()
+            .stdout(contains("lazy-new is locked but not installed")
+                .and(contains("all shims point at existing binaries")));
+        shim("lazy-new")
+            .assert()
+            .success()
+            .stdout(contains("lazy-new 2.0.0"));
+        support::frate(dir.path(), cache.path())
+            .arg("doctor")
+            .assert()
+            .stdout(contains("all locked packages are installed"));
+
+        set_dependency("lazy-tool", "1.0.0");
+        shim("lazy-tool")
+            .env("FRATE_LAZY_INSTALL", "0")
+            .assert()
+            .success()
+            .stdout(contains("lazy-tool 1.1.0"));
+    }
+
+    #[test]
+    fn test_shim_aliases() {
+        let registry = Registry::start();
+        registry.add_tool("alias-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("alias-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        let shims = dir.path().join(".frate").join("shims");
+        let set_bins = |bins: &str| {
+            let mut toml = FrateToml::load(dir.path().join("frate.toml")).unwrap();
+            toml.tools = toml::from_str(&format!("[alias-tool.bins]\n{}\n", bins)).unwrap();
+            toml.save(dir.path().join("frate.toml")).unwrap();
+        };
+        set_bins("alias-tool = \"aliased\"");
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        assert!(frate::find_shim(&shims, "aliased").is_some());
+        assert!(frate::find_shim(&shims, "alias-tool").is_none());
+
+        support::frate(dir.path(), cache.path())
+            .args(["which", "aliased"])
+            .assert()
+            .success()
+            .stdout(contains("alias-tool").and(contains("Shim aliased")));
+        support::frate(dir.path(), cache.path())
+            .args(["run", "aliased", "x"])
+            .assert()
+            .success()
+            .stdout(contains("alias-tool 1.0.0\narg: x"));
+
+        // Renaming again replaces the old shim.
+        set_bins("alias-tool = \"renamed\"");
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        assert!(frate::find_shim(&shims, "aliased").is_none());
+        assert!(frate::find_shim(&shims, "renamed").is_some());
+        support::frate(dir.path(), cache.path())
+            .args(["uninstall", "--name", "alias-tool"])
+            .assert()
+            .success();
+        assert!(frate::find_shim(&shims, "renamed").is_none());
+
+        set_bins("missing = \"missing\"");
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .code(11);
+    }
+
+    #[test]
+    fn test_detailed_dependencies() {
+        let registry = Registry::start();
+        registry.add_tool("req-tool", "1.0.0", Archive::TarGz);
+        registry.add_tool("req-tool", "1.3.0", Archive::TarGz);
+        registry.add_tool("opt-tool", "1.0.0", Archive::TarGz);
+        let internal = Registry::start();
+        internal.add_tool("internal-tool", "0.5.0", Archive::Zip);
+        let dir = support::project(&registry, &[]);
+        let cache = tempdir().unwrap();
+        let manifest = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        let manifest = manifest.replace("[dependencies]\n", &format!(
+            concat!(
+                "[dependencies]\n",
+                "req-tool = {{ version = \"^1.0\" }}\n",
+                "internal-tool = {{ version = \"0.5.0\", registry = \"internal\" }}\n",
+                "opt-tool = {{ version = \"1.0.0\", optional = true }}\n",
+                "elsewhere-tool = {{ version = \"1.0.0\", targets = [\"plan9\"] }}\n\n",
+                "[registries]\n",
+                "internal = \"{}\"\n",
+            ),
+            internal.url()
+        ));
+        std::fs::write(dir.path().join("frate.toml"), manifest).unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        let lock = std::fs::read_to_string(dir.path().join("frate.lock")).unwrap();
+        assert!(lock.contains(&format!("1.3.0-{}", current_target_triple())));
+        assert!(lock.contains("internal-tool"));
+        assert!(!lock.contains("elsewhere-tool"));
+
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        let bin = dir.path().join(".frate").join("bin");
+        assert!(bin.join("req-tool").exists());
+        assert!(bin.join("internal-tool").exists());
+        assert!(!bin.join("opt-tool").exists());
+        support::frate(dir.path(), cache.path())
+            .arg("doctor")
+            .assert()
+            .stdout(contains("frate.toml and frate.lock are consistent")
+                .and(contains("all locked packages are installed")));
+        support::frate(dir.path(), cache.path())
+            .args(["install", "--name", "opt-tool"])
+            .assert()
+            .success();
+        assert!(bin.join("opt-tool").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_remove_and_update() {
+        let registry = Registry::start();
+        registry.add_tool("tool-a", "1.0.0", Archive::TarGz);
+        registry.add_tool("tool-a", "1.1.0", Archive::TarGz);
+        registry.add_tool("tool-b", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[]);
+        let cache = tempdir().unwrap();
+        let manifest = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        let manifest = manifest.replace("[dependencies]\n", "[dependencies]\n# pinned for CI\ntool-b = \"1.0.0\" # keep\n");
+        std::fs::write(dir.path().join("frate.toml"), &manifest).unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .args(["add", "tool-a@1.0.0"])
+            .assert()
+            .success();
+        let content = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        assert!(content.contains("[dependencies]\ntool-a = \"1.0.0\"\n# pinned for CI\ntool-b = \"1.0.0\" # keep\n"));
+
+        support::frate(dir.path(), cache.path())
+            .args(["update", "tool-a@1.1.0"])
+            .assert()
+            .success()
+            .stdout(contains("Updated"));
+        let lock = std::fs::read_to_string(dir.path().join("frate.lock")).unwrap();
+        assert!(lock.contains(&format!("1.1.0-{}", current_target_triple())));
+        assert!(std::fs::read_to_string(dir.path().join("frate.toml")).unwrap().contains("tool-a = \"1.1.0\"\n"));
+
+        // An unknown version leaves both files as they were.
+        let before = (
+            std::fs::read_to_string(dir.path().join("frate.toml")).unwrap(),
+            std::fs::read_to_string(dir.path().join("frate.lock")).unwrap(),
+        );
+        support::frate(dir.path(), cache.path())
+            .args(["update", "tool-a@9.9.9"])
+            .assert()
+            .code(5);
+        let after = (
+            std::fs::read_to_string(dir.path().join("frate.toml")).unwrap(),
+            std::fs::read_to_string(dir.path().join("frate.lock")).unwrap(),
+        );
+        assert_eq!(after, before);
+
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .args(["remove", "tool-a"])
+            .assert()
+            .success()
+            .stdout(contains("Removed"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("frate.toml")).unwrap(), manifest);
+        assert!(!std::fs::read_to_string(dir.path().join("frate.lock")).unwrap().contains("tool-a"));
+        assert!(!dir.path().join(".frate").join("bin").join("tool-a").exists());
+
+        support::frate(dir.path(), cache.path())
+            .args(["remove", "tool-a"])
+            .assert()
+            .code(17);
+    }
+
+    #[test]
+    fn test_execute_lock_diff() {
+        let registry = Registry::start();
+        registry.add_tool("tool-b", "1.0.0", Archive::TarGz);
+        registry.add_tool("tool-a", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("tool-b", "1.0.0"), ("tool-a", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        let lock = std::fs::read_to_string(dir.path().join("frate.lock")).unwrap();
+        assert!(lock.starts_with("# This file is generated by frate."));
+        assert!(lock.find("tool-a").unwrap() < lock.find("tool-b").unwrap());
+
+        let old = dir.path().join("old.lock");
+        std::fs::write(&old, concat!(
+            "[[packages]]\nname = \"tool-a\"\nversion = \"0.9.0\"\nsource = \"\"\nhash = \"\"\n\n",
+            "[[packages]]\nname = \"tool-c\"\nversion = \"1.0.0\"\nsource = \"\"\nhash = \"\"\n",
+        )).unwrap();
+        support::frate(dir.path(), cache.path())
+            .args(["lock", "diff", "old.lock", "frate.lock"])
+            .assert()
+            .success()
+            .stdout(contains("+ tool-b")
+                .and(contains("- tool-c 1.0.0"))
+                .and(contains(format!("~ tool-a 0.9.0 -> 1.0.0-{}", current_target_triple()))));
+        support::frate(dir.path(), cache.path())
+            .args(["lock", "diff", "frate.lock", "frate.lock"])
+            .assert()
+            .success()
+            .stdout(contains("No changes"));
+        support::frate(dir.path(), cache.path())
+            .args(["lock", "diff", "missing.lock", "frate.lock"])
+            .assert()
+            .code(13);
+    }
+
+    #[test]
+    fn test_dependency_groups() {
+        let registry = Registry::start();
+        for name in ["lint-tool", "docs-tool", "dev-tool"] {
+            registry.add_tool(name, "1.0.0", Archive::TarGz);
+        }
+        let dir = support::project(&registry, &[("dev-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        let manifest = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        let manifest = manifest.replace("[dependencies]\n", concat!(
+            "[dependencies]\n",
+            "lint-tool = { version = \"1.0.0\", groups = [\"ci\"] }\n",
+            "docs-tool = { version = \"1.0.0\", groups = [\"docs\"] }\n",
+        ));
+        std::fs::write(dir.path().join("frate.toml"), manifest).unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        let lock = std::fs::read_to_string(dir.path().join("frate.lock")).unwrap();
+        assert!(lock.contains("lint-tool") && lock.contains("docs-tool") && lock.contains("dev-tool"));
+
+        let bin = dir.path().join(".frate").join("bin");
+        support::frate(dir.path(), cache.path())
+            .args(["install", "--group", "ci"])
+            .assert()
+            .success();
+        assert!(bin.join("lint-tool").exists());
+        assert!(!bin.join("docs-tool").exists());
+        assert!(!bin.join("dev-tool").exists());
+
+        support::frate(dir.path(), cache.path())
+            .args(["install", "--without", "docs"])
+            .assert()
+            .success();
+        assert!(bin.join("dev-tool").exists());
+        assert!(!bin.join("docs-tool").exists());
+
+        support::frate(dir.path(), cache.path())
+            .args(["install", "--group", "typo"])
+            .assert()
+            .failure()
+            .stderr(contains("unknown group `typo`"));
+
+        let project = frate::Project::load(dir.path()).unwrap();
+        let environment = project.group_environment(&["ci".to_string()]).unwrap();
+        let group_dir = dir.path().join(".frate").join("groups").join("ci");
+        assert_eq!(environment.paths[0], group_dir);
+        let shims: Vec<String> = std::fs::read_dir(&group_dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(shims.len(), 1);
+        assert!(shims[0].starts_with("lint-tool"));
+        #[cfg(unix)]
+        Command::new(group_dir.join("lint-tool"))
+            .assert()
+            .success()
+            .stdout(contains("lint-tool 1.0.0"));
+    }
+
+    #[test]
+    fn test_target_dependencies() {
+        let registry = Registry::start();
+        registry.add_tool("native-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[]);
+        let cache = tempdir().unwrap();
+        let family = std::env::consts::FAMILY;
+        let manifest = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        std::fs::write(dir.path().join("frate.toml"), format!(
+            concat!(
+                "{}\n",
+                "[target.'cfg(target_family = \"{}\")'.dependencies]\nnative-tool = \"1.0.0\"\n\n",
+                "[target.'cfg(not(target_family = \"{}\"))'.dependencies]\nforeign-tool = \"1.0.0\"\n",
+            ),
+            manifest, family, family
+        )).unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        let lock = std::fs::read_to_string(dir.path().join("frate.lock")).unwrap();
+        assert!(lock.contains("native-tool"));
+        assert!(!lock.contains("foreign-tool"));
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        assert!(dir.path().join(".frate").join("bin").join("native-tool").exists());
+        support::frate(dir.path(), cache.path())
+            .args(["list"])
+            .assert()
+            .success()
+            .stdout(contains("native-tool").and(contains("foreign-tool").not()));
+    }
+
+    #[test]
+    fn test_workspace() {
+        let registry = Registry::start();
+        registry.add_tool("api-tool", "1.0.0", Archive::TarGz);
+        registry.add_tool("web-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[]);
+        let cache = tempdir().unwrap();
+        let manifest = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        std::fs::write(dir.path().join("frate.toml"), format!("{}\n[workspace]\nmembers = [\"api\", \"web\"]\n", manifest)).unwrap();
+        let api = dir.path().join("api");
+        let web = dir.path().join("web");
+        std::fs::create_dir_all(&api).unwrap();
+        std::fs::create_dir_all(&web).unwrap();
+        std::fs::write(api.join("frate.toml"), "[dependencies]\napi-tool = \"1.0.0\"\n").unwrap();
+        std::fs::write(web.join("frate.toml"), "[dependencies]\nweb-tool = \"1.0.0\"\n").unwrap();
+
+        support::frate(&api, cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        let lock = std::fs::read_to_string(dir.path().join("frate.lock")).unwrap();
+        assert!(lock.contains("api-tool") && lock.contains("web-tool"));
+        assert!(!api.join("frate.lock").exists());
+        support::frate(&web, cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        assert!(dir.path().join(".frate").join("bin").join("api-tool").exists());
+        assert!(!web.join(".frate").exists());
+
+        // Settings belong to the workspace root, so a member saves `vendor-dir` there.
+        support::frate(&api, cache.path())
+            .args(["vendor", "third_party"])
+            .assert()
+            .success();
+        let root_toml = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        assert!(root_toml.contains("vendor-dir = \"third_party\""));
+        assert!(!std::fs::read_to_string(api.join("frate.toml")).unwrap().contains("vendor-dir"));
+        assert!(dir.path().join("third_party").join("vendor.toml").exists());
+
+        std::fs::write(web.join("frate.toml"), "[dependencies]\nweb-tool = \"1.0.0\"\napi-tool = \"2.0.0\"\n").unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .code(18)
+            .stderr(contains("api-tool: 1.0.0 (api), 2.0.0 (web)"));
+    }
+
+    #[test]
+    fn test_execute_import() {
+        let registry = Registry::start();
+        registry.add_tool("asdf-tool", "1.0.0", Archive::TarGz);
+        registry.add_tool("aqua-tool", "2.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[]);
+        let cache = tempdir().unwrap();
+        std::fs::write(dir.path().join(".tool-versions"), "asdf-tool 1.0.0
+unknown-tool 3.0.0
+").unwrap();
+        std::fs::write(dir.path().join("aqua.yaml"), "packages:\n- name: frate-fixtures/aqua-tool@v2.0.0\n- name: frate-fixtures/asdf-tool@v1.0.0\n").unwrap();
+
+        support::frate(dir.path(), cache.path())
+            .arg("import")
+            .assert()
+            .success()
+            .stdout(contains("asdf-tool@1.0.0 from").and(contains("aqua-tool@2.0.0 from")))
+            .stderr(contains("unknown-tool@3.0.0: no matching tool in the registry")
+                .and(contains("asdf-tool is already in frate.toml")));
+        let manifest = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        assert!(manifest.contains("aqua-tool = \"2.0.0\"\nasdf-tool = \"1.0.0\""));
+
+        std::fs::remove_file(dir.path().join(".tool-versions")).unwrap();
+        std::fs::remove_file(dir.path().join("aqua.yaml")).unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("import")
+            .assert()
+            .code(1)
+            .stderr(contains("Nothing to import"));
+    }
+
+    #[test]
+    fn test_execute_export() {
+        let registry = Registry::start();
+        let hash = registry.add_tool("export-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("export-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+
+        support::frate(dir.path(), cache.path())
+            .args(["export", "--format", "dockerfile"])
+            .assert()
+            .success()
+            .stdout(contains(format!("echo \"{}  /tmp/export-tool.tar.gz\" | sha256sum -c -", hash))
+                .and(contains("ENV PATH=\"$FRATE_DIR/shims:$PATH\"")));
+        support::frate(dir.path(), cache.path())
+            .args(["export", "--target", "riscv64gc-unknown-linux-gnu"])
+            .assert()
+            .code(5);
+
+        support::frate(dir.path(), cache.path())
+            .args(["export", "--format", "sh", "--output", "install-tools.sh"])
+            .assert()
+            .success();
+        #[cfg(unix)]
+        {
+            let tools = tempdir().unwrap();
+            Command::new("sh")
+                .arg(dir.path().join("install-tools.sh"))
+                .env("FRATE_DIR", tools.path())
+                .env("NO_PROXY", "127.0.0.1")
+                .assert()
+                .success();
+            Command::new(tools.path().join("shims").join("export-tool"))
+                .assert()
+                .success()
+                .stdout(contains("export-tool 1.0.0"));
+            let env = std::fs::read_to_string(tools.path().join("env.sh")).unwrap();
+            assert!(env.contains(&format!("export PATH=\"{}/shims:$PATH\"", tools.path().display())));
+        }
+    }
+
+    #[test]
+    fn test_execute_vendor() {
+        let registry = Registry::start();
+        registry.add_tool("vendored-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("vendored-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
             .assert()
             .success();
+        support::frate(dir.path(), cache.path())
+            .arg("vendor")
+            .assert()
+            .success()
+            .stdout(contains("Vendored 1 archives"));
+        let vendor_dir = dir.path().join("vendor").join("frate");
+        let manifest = std::fs::read_to_string(vendor_dir.join("vendor.toml")).unwrap();
+        assert!(manifest.contains("name = \"vendored-tool\""));
+
+        support::frate(dir.path(), cache.path())
+            .args(["vendor", "third_party"])
+            .assert()
+            .success();
+        let toml = std::fs::read_to_string(dir.path().join("frate.toml")).unwrap();
+        assert!(toml.contains("vendor-dir = \"third_party\""));
+
+        // Point the lock at an unreachable host: only the vendored archive can be installed.
+        let lock_path = dir.path().join("frate.lock");
+        let lock = std::fs::read_to_string(&lock_path).unwrap();
+        std::fs::write(&lock_path, lock.replace(registry.url(), "http://127.0.0.1:1")).unwrap();
+        let empty_cache = tempdir().unwrap();
+        support::frate(dir.path(), empty_cache.path())
+            .arg("install")
+            .assert()
+            .success()
+            .stdout(contains("Downloading").not());
+        assert!(dir.path().join(".frate").join("bin").join("vendored-tool").exists());
+    }
+
+    #[test]
+    fn test_execute_bundle() {
+        let registry = Registry::start();
+        registry.add_tool("bundled-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("bundled-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        let out = tempdir().unwrap();
+        let bundle = out.path().join("tools.tar.zst");
+        support::frate(dir.path(), cache.path())
+            .args(["bundle", "create"])
+            .arg(&bundle)
+            .assert()
+            .success()
+            .stdout(contains("Bundled 1 archives"));
+        assert!(dir.path().join("frate.lock").exists());
+
+        // A fresh directory and cache: everything has to come from the bundle.
+        let target = tempdir().unwrap();
+        let empty_cache = tempdir().unwrap();
+        support::frate(target.path(), empty_cache.path())
+            .args(["bundle", "install"])
+            .arg(&bundle)
+            .assert()
+            .success()
+            .stdout(contains("Downloading").not())
+            .stdout(contains("Installed 1 tools"));
+        assert_eq!(
+            std::fs::read_to_string(target.path().join("frate.toml")).unwrap(),
+            std::fs::read_to_string(dir.path().join("frate.toml")).unwrap()
+        );
+        assert!(target.path().join("frate.lock").exists());
+        assert!(target.path().join(".frate").join("bin").join("bundled-tool").exists());
+        assert!(std::fs::read_dir(empty_cache.path()).unwrap().next().is_some());
+
+        std::fs::write(target.path().join("frate.toml"), "[dependencies]\n").unwrap();
+        support::frate(target.path(), empty_cache.path())
+            .args(["bundle", "install"])
+            .arg(&bundle)
+            .assert()
+            .failure()
+            .stderr(contains("differs from the frate.toml in the bundle"));
+    }
+
+    #[test]
+    fn test_execute_env() {
+        let registry = Registry::start();
+        registry.add_tool("env-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("env-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+
+        let frate = assert_cmd::cargo::cargo_bin("frate");
+        let script = format!(
+            concat!(
+                "eval \"$('{0}' env --shell bash)\"\n",
+                "eval \"$('{0}' env --shell bash)\"\n",
+                "env-tool\n",
+                "eval \"$('{0}' env --shell bash --unset)\"\n",
+                "echo \"PATH=$PATH\"\n",
+            ),
+            frate.display()
+        );
+        Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .current_dir(dir.path())
+            .env("PATH", "/usr/bin:/bin")
+            .env("FRATE_CACHE_DIR", cache.path())
+            .env_remove("FRATE_OLD_PATH")
+            .assert()
+            .success()
+            .stdout(contains("env-tool 1.0.0").and(contains("PATH=/usr/bin:/bin\n")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_hook_bash() {
+        let registry = Registry::start();
+        registry.add_tool("hook-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("hook-tool", "1.0.0")]);
+        let cache = tempdir().unwrap();
+        support::frate(dir.path(), cache.path())
+            .arg("sync")
+            .assert()
+            .success();
+        support::frate(dir.path(), cache.path())
+            .arg("install")
+            .assert()
+            .success();
+        let nested = dir.path().join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let frate = assert_cmd::cargo::cargo_bin("frate");
+        // PROMPT_COMMAND only runs in interactive shells, so the hook is called by hand.
+        let script = format!(
+            concat!(
+                "eval \"$('{}' hook bash)\"\n",
+                "cd '{}' && _frate_hook && hook-tool\n",
+                "cd / && _frate_hook\n",
+                "command -v hook-tool || echo \"left PATH=$PATH\"\n",
+            ),
+            frate.display(),
+            nested.display()
+        );
+        Command::new("bash")
+            .arg("-c")
+            .arg(script)
+            .env("PATH", "/usr/bin:/bin")
+            .env("FRATE_CACHE_DIR", cache.path())
+            .env_remove("FRATE_OLD_PATH")
+            .assert()
+            .success()
+            .stdout(contains("hook-tool 1.0.0").and(contains("left PATH=/usr/bin:/bin\n")));
     }
 
     #[test]
@@ -176,7 +1230,7 @@ mod cli_integration_tests {
         let dir_path = dir.path();
 
         Command::cargo_bin("frate").unwrap()
-            .current_dir(&dir_path)
+            .current_dir(dir_path)
             .arg("shell")
             .assert()
             .success();
@@ -0,0 +1,281 @@
+//! Test support: a local HTTP server serving a fixture registry and generated archives,
+//! so integration tests run without network access.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex, Once};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use frate::{current_target_triple, FrateToml};
+
+type Files = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// Archive formats the fixture registry can generate.
+#[derive(Debug, Clone, Copy)]
+pub enum Archive {
+    TarGz,
+    Zip,
+    /// A `.tar.gz` that also contains a symlink to a directory (`lib64 -> lib`)
+    /// and a dangling symlink.
+    TarGzWithSymlinks,
+}
+
+/// A frate registry served over HTTP from `127.0.0.1`.
+///
+/// Tools added with [`Registry::add_tool`] get a generated archive containing a single
+/// executable, which prints `<name> <version>` followed by one `arg: <arg>` line per argument.
+/// If the first argument is `--stdin`, it also echoes each line of stdin as `stdin: <line>`.
+/// It exits with the code in `FIXTURE_EXIT`, or `0` if it is not set.
+pub struct Registry {
+    url: String,
+    files: Files,
+    tools: Mutex<HashMap<String, serde_json::Map<String, serde_json::Value>>>,
+    environments: Mutex<HashMap<String, serde_json::Value>>,
+}
+
+impl Registry {
+    /// Starts a server on a random port. It keeps running until the test process exits.
+    pub fn start() -> Registry {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let files: Files = Arc::default();
+        let server_files = files.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let files = server_files.clone();
+                std::thread::spawn(move || handle(stream, files));
+            }
+        });
+        let registry = Registry { url, files, tools: Mutex::default(), environments: Mutex::default() };
+        registry.write_index();
+        registry
+    }
+    /// Returns the base URL of the registry.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    /// Serves `body` at `path` (e.g. `/files/tool.tar.gz`).
+    pub fn serve(&self, path: &str, body: Vec<u8>) {
+        self.files.lock().unwrap().insert(path.to_string(), body);
+    }
+    /// Adds a release for the current platform and serves its archive.
+    /// Returns the SHA-256 hash of the archive.
+    pub fn add_tool(&self, name: &str, version: &str, format: Archive) -> String {
+        let (file, bytes) = build_archive(name, version, format);
+        let hash = sha256(&bytes);
+        self.serve(&format!("/files/{}", file), bytes);
+        self.add_release(name, version, &file, &hash);
+        hash
+    }
+    /// Adds a release whose registry entry records a wrong hash for the served archive.
+    pub fn add_tool_with_wrong_hash(&self, name: &str, version: &str) {
+        let (file, bytes) = build_archive(name, version, Archive::TarGz);
+        self.serve(&format!("/files/{}", file), bytes);
+        self.add_release(name, version, &file, &"0".repeat(64));
+    }
+    /// Adds a release whose archive is not served, so downloading it fails with a 404.
+    pub fn add_tool_without_archive(&self, name: &str, version: &str) {
+        let (file, bytes) = build_archive(name, version, Archive::TarGz);
+        self.add_release(name, version, &file, &sha256(&bytes));
+    }
+
+    /// Adds a release like [`Registry::add_tool`], with `env` and `paths` in the registry entry.
+    pub fn add_tool_with_env(&self, name: &str, version: &str, env: &[(&str, &str)], paths: &[&str]) -> String {
+        let env: serde_json::Map<_, _> = env.iter()
+            .map(|(key, value)| (key.to_string(), serde_json::json!(value)))
+            .collect();
+        self.environments.lock().unwrap()
+            .insert(name.to_string(), serde_json::json!({ "env": env, "paths": paths }));
+        self.add_tool(name, version, Archive::TarGz)
+    }
+
+    fn add_release(&self, name: &str, version: &str, file: &str, hash: &str) {
+        let mut tools = self.tools.lock().unwrap();
+        let releases = tools.entry(name.to_string()).or_default();
+        releases.insert(
+            format!("{}-{}", version, current_target_triple()),
+            serde_json::json!({
+                "url": format!("{}/files/{}", self.url, file),
+                "hash": format!("sha256:{}", hash),
+            }),
+        );
+        let mut tool = serde_json::json!({
+            "name": name,
+            "repo": format!("https://github.com/frate-fixtures/{}", name),
+            "releases": releases,
+        });
+        if let Some(environment) = self.environments.lock().unwrap().get(name) {
+            tool["env"] = environment["env"].clone();
+            tool["paths"] = environment["paths"].clone();
+        }
+        self.serve(&format!("/tools/{}.json", name), tool.to_string().into_bytes());
+        drop(tools);
+        self.write_index();
+    }
+
+    fn write_index(&self) {
+        let tools = self.tools.lock().unwrap();
+        let mut names: Vec<_> = tools.keys().cloned().collect();
+        names.sort();
+        let registered: Vec<_> = names.iter()
+            .map(|name| serde_json::json!({
+                "name": name,
+                "repo": format!("https://github.com/frate-fixtures/{}", name),
+            }))
+            .collect();
+        let index = serde_json::json!({ "registered": registered });
+        self.serve("/registry.json", index.to_string().into_bytes());
+    }
+}
+
+/// Creates a project directory with a `frate.toml` that uses `registry` and declares `dependencies`.
+pub fn project(registry: &Registry, dependencies: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let mut toml = FrateToml::default("fixture");
+    toml.settings.registry = registry.url().to_string();
+    for (name, version) in dependencies {
+        toml.add(name, version).unwrap();
+    }
+    toml.save(dir.path().join("frate.toml")).unwrap();
+    dir
+}
+
+/// Returns a `frate` command running in `project`, with its archive cache in `cache`.
+pub fn frate(project: &Path, cache: &Path) -> assert_cmd::Command {
+    let mut cmd = assert_cmd::Command::cargo_bin("frate").unwrap();
+    cmd.current_dir(project)
+        .env("FRATE_CACHE_DIR", cache)
+        .env("NO_PROXY", "127.0.0.1");
+    cmd
+}
+
+/// Points the archive cache of this test process at a directory below `target/`, so
+/// in-process tests never touch the user's global cache.
+///
+/// This changes the process environment. Every test of a binary that calls it must call it
+/// as its first statement, including tests that do not use the cache: the environment must
+/// not be read, e.g. by spawning a process, while another test thread sets the variable.
+/// Tests that run `frate` as a subprocess pass the cache with [`frate`] instead.
+pub fn isolate_cache() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let cache = Path::new(env!("CARGO_TARGET_TMPDIR")).join("frate-cache");
+        let _ = std::fs::remove_dir_all(&cache);
+        // SAFETY: every test in `tests/tests.rs`, the only binary calling this, calls it
+        // before doing anything else. Other test threads are therefore either not started
+        // yet or blocked in `call_once` until the variable is set, so no thread reads the
+        // environment while it is being modified.
+        unsafe { std::env::set_var("FRATE_CACHE_DIR", cache) };
+    });
+}
+
+/// Returns a fetcher that never goes through a proxy, for in-process tests.
+pub fn fetcher() -> frate::ReqwestFetcher {
+    let client = reqwest::blocking::Client::builder().no_proxy().build().unwrap();
+    frate::ReqwestFetcher::with_client(client)
+}
+
+fn handle(stream: TcpStream, files: Files) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if line == "\r\n" => break,
+            Ok(_) => {}
+        }
+    }
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let body = files.lock().unwrap().get(path).cloned();
+    let mut stream = &stream;
+    let _ = match body {
+        Some(body) => {
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+            stream.write_all(&body)
+        }
+        None => stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+    };
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(unix)]
+fn executable(name: &str, version: &str) -> (String, String) {
+    let script = format!(
+        concat!(
+            "#!/bin/sh\necho \"{} {}\"\nfor arg in \"$@\"; do echo \"arg: $arg\"; done\n",
+            "if [ \"$1\" = \"--stdin\" ]; then while read -r line; do echo \"stdin: $line\"; done; fi\n",
+            "exit ${{FIXTURE_EXIT:-0}}\n",
+        ),
+        name, version
+    );
+    (name.to_string(), script)
+}
+
+#[cfg(windows)]
+fn executable(name: &str, version: &str) -> (String, String) {
+    let script = format!(
+        concat!(
+            "@echo off\r\necho {} {}\r\nfor %%a in (%*) do echo arg: %%~a\r\n",
+            "if \"%~1\"==\"--stdin\" for /f \"delims=\" %%l in ('more') do echo stdin: %%l\r\n",
+            "if defined FIXTURE_EXIT exit /b %FIXTURE_EXIT%\r\n",
+        ),
+        name, version
+    );
+    (format!("{}.cmd", name), script)
+}
+
+/// Builds a deterministic archive containing `<name>-<version>/<executable>`.
+/// Returns the archive file name and its bytes.
+fn build_archive(name: &str, version: &str, format: Archive) -> (String, Vec<u8>) {
+    let (exe, script) = executable(name, version);
+    let entry = format!("{}-{}/{}", name, version, exe);
+    let base = format!("{}-{}-{}", name, version, current_target_triple());
+    match format {
+        Archive::TarGz | Archive::TarGzWithSymlinks => {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(script.len() as u64);
+            header.set_mode(0o755);
+            header.set_mtime(0);
+            let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            let mut builder = tar::Builder::new(encoder);
+            builder.append_data(&mut header, &entry, script.as_bytes()).unwrap();
+            if let Archive::TarGzWithSymlinks = format {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(3);
+                header.set_mode(0o644);
+                header.set_mtime(0);
+                builder.append_data(&mut header, format!("{}-{}/lib/data", name, version), &b"lib"[..]).unwrap();
+                for (link, target) in [("lib64", "lib"), ("dangling", "missing")] {
+                    let mut header = tar::Header::new_gnu();
+                    header.set_entry_type(tar::EntryType::Symlink);
+                    header.set_size(0);
+                    header.set_mode(0o777);
+                    header.set_mtime(0);
+                    builder.append_link(&mut header, format!("{}-{}/{}", name, version, link), target).unwrap();
+                }
+            }
+            let bytes = builder.into_inner().unwrap().finish().unwrap();
+            (format!("{}.tar.gz", base), bytes)
+        }
+        Archive::Zip => {
+            let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+            let options = zip::write::SimpleFileOptions::default()
+                .unix_permissions(0o755)
+                .last_modified_time(zip::DateTime::default());
+            zip.start_file(entry, options).unwrap();
+            zip.write_all(script.as_bytes()).unwrap();
+            let bytes = zip.finish().unwrap().into_inner();
+            (format!("{}.zip", base), bytes)
+        }
+    }
+}
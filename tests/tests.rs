@@ -1,27 +1,19 @@
-use tempfile::TempDir;
-use frate::toml::FrateToml;
-
-
-fn setup_tests() -> TempDir {
-    let temp_dir = TempDir::new().unwrap();
-    let mut toml = FrateToml::default(&temp_dir.path().file_name().unwrap().to_string_lossy());
-    toml.dependencies.insert("just".to_string(), "1.42.1".to_string());
-    toml.save(temp_dir.path().join("frate.toml").to_str().unwrap()).unwrap();
-    temp_dir
-}
+mod support;
 
 #[cfg(test)]
 mod tests {
-    use tempfile::TempDir;
     use frate::installer::install_packages;
     use frate::lock::FrateLock;
-    use frate::{get_binary};
+    use frate::{get_binary, Error, Project, ShimMode, Silent};
     use frate::toml::FrateToml;
-    use crate::setup_tests;
+    use crate::support::{self, Archive, Registry};
 
     #[test]
     fn test_sync_lock() {
-        let dir = setup_tests();
+        support::isolate_cache();
+        let registry = Registry::start();
+        let hash = registry.add_tool("sync-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("sync-tool", "1.0.0")]);
         let toml_path = dir.path().join("frate.toml");
 
         // Load frate.toml
@@ -30,58 +22,56 @@ mod tests {
 
         // Sync lockfile
         let mut lock = FrateLock::load_or_default(dir.path().join("frate.lock"));
-        lock.sync(&toml).unwrap();
+        lock.sync(&toml, &support::fetcher(), registry.url(), &Silent).unwrap();
         assert_eq!(lock.packages.len(), 1);
+        assert_eq!(lock.packages[0].hash, format!("sha256:{}", hash));
 
         // Save and assert
         lock.save(dir.path().join("frate.lock")).unwrap();
         assert!(dir.path().join("frate.lock").exists());
     }
 
-    #[cfg(not(ci_skip))]
     #[test]
     fn test_install_packages() {
-        let dir = setup_tests();
-        let old_cwd = std::env::current_dir().unwrap();
-        std::env::set_current_dir(dir.path()).unwrap();
+        support::isolate_cache();
+        let registry = Registry::start();
+        registry.add_tool("install-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("install-tool", "1.0.0")]);
         let toml_path = dir.path().join("frate.toml");
         let toml = FrateToml::load(toml_path.to_str().unwrap()).expect("frate.toml not found");
 
         // Lockfile sync + install
         let mut lock = FrateLock::load_or_default(dir.path().join("frate.lock"));
-        lock.sync(&toml).unwrap();
+        lock.sync(&toml, &support::fetcher(), registry.url(), &Silent).unwrap();
         lock.save(dir.path().join("frate.lock")).unwrap();
-        install_packages(&lock, dir.path()).unwrap();
+        install_packages(&lock, dir.path(), ShimMode::Symlink, None, &support::fetcher(), &Silent).unwrap();
 
         // Check binary existence
-        assert!(get_binary("just").expect("Binary not found").unwrap().exists());
-        std::env::set_current_dir(old_cwd).unwrap();
+        let frate_dir = dir.path().join(".frate");
+        assert!(get_binary(&frate_dir, "install-tool").expect("Binary not found").unwrap().exists());
     }
 
-    #[cfg(not(ci_skip))]
     #[test]
     fn test_shims() {
-        let dir = setup_tests();
-        let old_cwd = std::env::current_dir().unwrap();
-        std::env::set_current_dir(dir.path()).unwrap();
-        let toml_path = dir.path().join("frate.toml");
-        let toml = FrateToml::load(toml_path.to_str().unwrap()).expect("frate.toml not found");
+        support::isolate_cache();
+        let registry = Registry::start();
+        registry.add_tool("shim-tool", "1.0.0", Archive::Zip);
+        let dir = support::project(&registry, &[("shim-tool", "1.0.0")]);
 
         // Lock + Install
-        let mut lock = FrateLock::load_or_default(dir.path().join("frate.lock"));
-        lock.sync(&toml).unwrap();
-        lock.save(dir.path().join("frate.lock")).unwrap();
-        install_packages(&lock, dir.path()).unwrap();
+        let mut project = Project::load(dir.path()).unwrap().with_fetcher(support::fetcher());
+        project.sync(&Silent).unwrap();
+        project.install(None, &Silent).unwrap();
 
         #[cfg(target_os = "windows")]
         {
             // Check .bat shim
-            let shim_path = dir.path().join(".frate").join("shims").join("just.bat");
+            let shim_path = dir.path().join(".frate").join("shims").join("shim-tool.bat");
             assert!(shim_path.exists(), "Shim file not found: {:?}", shim_path);
 
             // Run shim
             let output = std::process::Command::new("cmd")
-                .args(&["/C", shim_path.to_str().unwrap(), "--version"])
+                .args(["/C", shim_path.to_str().unwrap(), "--version"])
                 .output()
                 .expect("failed to execute shim");
 
@@ -93,7 +83,7 @@ mod tests {
             use std::os::unix::fs::PermissionsExt;
 
             // Check shim file
-            let shim_path = dir.path().join(".frate").join("shims").join("just");
+            let shim_path = dir.path().join(".frate").join("shims").join("shim-tool");
             assert!(shim_path.exists(), "Shim file not found: {:?}", shim_path);
 
             // Ensure executable
@@ -111,15 +101,121 @@ mod tests {
                 .expect("Failed to execute shim");
 
             assert!(output.status.success(), "Shim execution failed");
+            assert!(String::from_utf8_lossy(&output.stdout).contains("shim-tool 1.0.0"));
         }
-        std::env::set_current_dir(old_cwd).unwrap();
+    }
+
+    #[test]
+    fn test_install_hash_mismatch() {
+        support::isolate_cache();
+        let registry = Registry::start();
+        registry.add_tool_with_wrong_hash("tampered-tool", "1.0.0");
+        let dir = support::project(&registry, &[("tampered-tool", "1.0.0")]);
+
+        let mut project = Project::load(dir.path()).unwrap().with_fetcher(support::fetcher());
+        project.sync(&Silent).unwrap();
+        let result = project.install(Some("tampered-tool"), &Silent);
+        assert!(matches!(result, Err(Error::HashMismatch { .. })), "{:?}", result);
+    }
+
+    #[test]
+    fn test_failed_reinstall_keeps_previous_install() {
+        support::isolate_cache();
+        let registry = Registry::start();
+        registry.add_tool("reinstall-tool", "1.0.0", Archive::TarGz);
+        let dir = support::project(&registry, &[("reinstall-tool", "1.0.0")]);
+
+        let mut project = Project::load(dir.path()).unwrap().with_fetcher(support::fetcher());
+        project.sync(&Silent).unwrap();
+        project.install(None, &Silent).unwrap();
+        let frate_dir = dir.path().join(".frate");
+        let binary = get_binary(&frate_dir, "reinstall-tool").unwrap().unwrap();
+
+        project.lock_mut().packages[0].hash = format!("sha256:{}", "0".repeat(64));
+        let result = project.install(Some("reinstall-tool"), &Silent);
+        assert!(matches!(result, Err(Error::HashMismatch { .. })), "{:?}", result);
+        assert!(binary.exists());
+        let staged = std::fs::read_dir(frate_dir.join("bin")).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".partial"))
+            .count();
+        assert_eq!(staged, 0);
+    }
+
+    #[test]
+    fn test_install_missing_archive() {
+        support::isolate_cache();
+        let registry = Registry::start();
+        registry.add_tool_without_archive("missing-archive-tool", "1.0.0");
+        let dir = support::project(&registry, &[("missing-archive-tool", "1.0.0")]);
+
+        let mut project = Project::load(dir.path()).unwrap().with_fetcher(support::fetcher());
+        project.sync(&Silent).unwrap();
+        let result = project.install(Some("missing-archive-tool"), &Silent);
+        assert!(matches!(result, Err(Error::Network(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn test_sync_unknown_tool() {
+        support::isolate_cache();
+        let registry = Registry::start();
+        let dir = support::project(&registry, &[("unknown-tool", "1.0.0")]);
+
+        let mut project = Project::load(dir.path()).unwrap().with_fetcher(support::fetcher());
+        assert!(matches!(project.sync(&Silent), Err(Error::RegistryNotFound(_))));
+        assert!(project.lock().packages.is_empty());
+
+        // Optional dependencies that cannot be resolved are skipped.
+        project.manifest_mut().dependencies = ::toml::from_str("unknown-tool = { version = \"1.0.0\", optional = true }").unwrap();
+        let added = project.sync(&Silent).unwrap();
+        assert!(added.is_empty());
+        assert!(project.lock().packages.is_empty());
     }
 
     #[test]
     fn test_load_or_default_fallback() {
+        support::isolate_cache();
         // No frate.lock should lead to empty FrateLock
-        let dir = TempDir::new().unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
         let lock = FrateLock::load_or_default(dir.path().join("frate.lock"));
         assert_eq!(lock.packages.len(), 0);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_activate_is_relocatable() {
+        support::isolate_cache();
+        let dir = tempfile::TempDir::new().unwrap();
+        Project::init(dir.path().join("before")).unwrap();
+        let moved = dir.path().join("after");
+        std::fs::rename(dir.path().join("before"), &moved).unwrap();
+
+        let script = format!(
+            concat!(
+                ". '{0}/.frate/activate'\n",
+                ". '{0}/.frate/activate'\n",
+                "echo \"$FRATE_ROOT\"\n",
+                "echo \"$PATH\"\n",
+                "deactivate\n",
+                "echo \"$PATH\"\n",
+                "command -v deactivate || echo \"deactivated ${{FRATE_ROOT:-}}\"\n",
+            ),
+            moved.display()
+        );
+        let output = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(script)
+            .current_dir("/")
+            .env("PATH", "/usr/bin:/bin")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let moved = moved.canonicalize().unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!(
+                "{0}\n{0}/.frate/shims:/usr/bin:/bin\n/usr/bin:/bin\ndeactivated \n",
+                moved.display()
+            )
+        );
+    }
 }